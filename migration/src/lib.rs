@@ -1,12 +1,38 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20241020_000002_create_listening_session;
+mod m20241021_000003_create_listening_stat;
+mod m20241022_000004_create_bookmark;
+mod m20241023_000005_add_episode_key;
+mod m20241024_000006_shared_channel_items;
+mod m20241025_000007_create_radio_station;
+mod m20241026_000008_create_radio_song;
+mod m20241027_000009_add_station_resolved_url;
+mod m20241028_000010_create_schedule_job;
+mod m20241029_000011_add_station_position_and_group;
+mod m20241030_000012_rekey_enclosure_query;
+mod m20241031_000013_scope_guid_keys;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20241020_000002_create_listening_session::Migration),
+            Box::new(m20241021_000003_create_listening_stat::Migration),
+            Box::new(m20241022_000004_create_bookmark::Migration),
+            Box::new(m20241023_000005_add_episode_key::Migration),
+            Box::new(m20241024_000006_shared_channel_items::Migration),
+            Box::new(m20241025_000007_create_radio_station::Migration),
+            Box::new(m20241026_000008_create_radio_song::Migration),
+            Box::new(m20241027_000009_add_station_resolved_url::Migration),
+            Box::new(m20241028_000010_create_schedule_job::Migration),
+            Box::new(m20241029_000011_add_station_position_and_group::Migration),
+            Box::new(m20241030_000012_rekey_enclosure_query::Migration),
+            Box::new(m20241031_000013_scope_guid_keys::Migration),
+        ]
     }
 }
@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ListeningSession::Table)
+                    .if_not_exists()
+                        .col(ColumnDef::new(ListeningSession::Id).integer().not_null().auto_increment().primary_key())
+                        .col(ColumnDef::new(ListeningSession::ChannelId).integer().not_null())
+                        .col(ColumnDef::new(ListeningSession::ChannelItemEnclosure).string().not_null())
+                        .col(ColumnDef::new(ListeningSession::StartedAt).timestamp_with_time_zone().not_null())
+                        .col(ColumnDef::new(ListeningSession::EndedAt).timestamp_with_time_zone())
+                        .col(ColumnDef::new(ListeningSession::StartPosition).float().not_null().default(0.0))
+                        .col(ColumnDef::new(ListeningSession::EndPosition).float())
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk_channel")
+                                .from(ListeningSession::Table, ListeningSession::ChannelId)
+                                .to(Channel::Table, Channel::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                            )
+                        .to_owned()
+                )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_listening_session_started_at")
+                    .table(ListeningSession::Table)
+                    .col(ListeningSession::StartedAt)
+                    .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ListeningSession::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ListeningSession {
+    Table,
+    Id,
+    ChannelId,
+    ChannelItemEnclosure,
+    StartedAt,
+    EndedAt,
+    StartPosition,
+    EndPosition
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ListeningStat::Table)
+                    .if_not_exists()
+                        .col(ColumnDef::new(ListeningStat::Id).integer().not_null().auto_increment().primary_key())
+                        .col(ColumnDef::new(ListeningStat::ChannelId).integer().not_null())
+                        .col(ColumnDef::new(ListeningStat::ChannelItemEnclosure).string().not_null())
+                        .col(ColumnDef::new(ListeningStat::Day).date().not_null())
+                        .col(ColumnDef::new(ListeningStat::Seconds).float().not_null().default(0.0))
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk_channel")
+                                .from(ListeningStat::Table, ListeningStat::ChannelId)
+                                .to(Channel::Table, Channel::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                            )
+                        .to_owned()
+                )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_listening_stat_item_day")
+                    .table(ListeningStat::Table)
+                    .col(ListeningStat::ChannelId)
+                    .col(ListeningStat::ChannelItemEnclosure)
+                    .col(ListeningStat::Day)
+                    .unique()
+                    .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ListeningStat::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ListeningStat {
+    Table,
+    Id,
+    ChannelId,
+    ChannelItemEnclosure,
+    Day,
+    Seconds
+}
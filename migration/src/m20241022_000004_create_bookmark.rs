@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bookmark::Table)
+                    .if_not_exists()
+                        .col(ColumnDef::new(Bookmark::Id).integer().not_null().auto_increment().primary_key())
+                        .col(ColumnDef::new(Bookmark::ChannelId).integer().not_null())
+                        .col(ColumnDef::new(Bookmark::ChannelItemEnclosure).string().not_null())
+                        .col(ColumnDef::new(Bookmark::Time).float().not_null().default(0.0))
+                        .col(ColumnDef::new(Bookmark::Note).string())
+                        .col(ColumnDef::new(Bookmark::CreatedAt).timestamp_with_time_zone().not_null())
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk_channel")
+                                .from(Bookmark::Table, Bookmark::ChannelId)
+                                .to(Channel::Table, Channel::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                            )
+                        .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bookmark::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Bookmark {
+    Table,
+    Id,
+    ChannelId,
+    ChannelItemEnclosure,
+    Time,
+    Note,
+    CreatedAt
+}
@@ -0,0 +1,148 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+
+/// Adds `episode_key` (feed GUID, or normalized enclosure when item has no GUID)
+/// to every table which references an episode and fills it for existing rows.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tables::ChannelItem)
+                    .add_column(ColumnDef::new(EpisodeKey::EpisodeKey).string().not_null().default(""))
+                    .to_owned()
+                )
+            .await?;
+
+        for table in [Tables::ListeningState, Tables::ListeningSession, Tables::ListeningStat, Tables::Bookmark] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(ColumnDef::new(EpisodeKey::EpisodeKey).string().not_null().default(""))
+                        .to_owned()
+                    )
+                .await?;
+        }
+
+        for (name, table) in [("idx_channel_item_episode_key", Tables::ChannelItem), ("idx_listening_state_episode_key", Tables::ListeningState)] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(table)
+                        .col(EpisodeKey::EpisodeKey)
+                        .to_owned()
+                    )
+                .await?;
+        }
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let rows = db.query_all(Statement::from_string(backend, "SELECT channel_id, enclosure, guid FROM channel_item")).await?;
+        for row in rows {
+            let channel_id: i32 = row.try_get("", "channel_id")?;
+            let enclosure: String = row.try_get("", "enclosure")?;
+            let guid: Option<String> = row.try_get("", "guid")?;
+            db.execute(Statement::from_sql_and_values(backend,
+                "UPDATE channel_item SET episode_key = ? WHERE channel_id = ? AND enclosure = ?",
+                [episode_key(guid.as_deref(), &enclosure).into(), channel_id.into(), enclosure.into()])).await?;
+        }
+
+        // rows referencing an episode take the key of the matching channel item,
+        // or the normalized enclosure if the item is not in the feed any more
+        for table in ["listening_state", "listening_session", "listening_stat", "bookmark"] {
+            let rows = db.query_all(Statement::from_string(backend, format!("SELECT id, channel_item_enclosure FROM {}", table))).await?;
+            for row in rows {
+                let id: i32 = row.try_get("", "id")?;
+                let enclosure: String = row.try_get("", "channel_item_enclosure")?;
+                let item = db.query_one(Statement::from_sql_and_values(backend,
+                    "SELECT episode_key FROM channel_item WHERE enclosure = ?", [enclosure.clone().into()])).await?;
+                let key = match item {
+                    Some(i) => i.try_get("", "episode_key")?,
+                    None => episode_key(None, &enclosure),
+                };
+                db.execute(Statement::from_sql_and_values(backend,
+                    format!("UPDATE {} SET episode_key = ? WHERE id = ?", table),
+                    [key.into(), id.into()])).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, table) in [("idx_channel_item_episode_key", Tables::ChannelItem), ("idx_listening_state_episode_key", Tables::ListeningState)] {
+            manager
+                .drop_index(Index::drop().name(name).table(table).to_owned())
+                .await?;
+        }
+
+        manager
+            .alter_table(Table::alter().table(Tables::ChannelItem).drop_column(EpisodeKey::EpisodeKey).to_owned())
+            .await?;
+
+        for table in [Tables::ListeningState, Tables::ListeningSession, Tables::ListeningStat, Tables::Bookmark] {
+            manager
+                .alter_table(Table::alter().table(table).drop_column(EpisodeKey::EpisodeKey).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rules of `ui_models::episode_key` at the time of this migration, also used to re-key
+/// rows in later migrations. GUID keys are scoped to their channel by `m20241031_000013`
+pub(crate) fn episode_key(guid: Option<&str>, enclosure: &str) -> String {
+    match guid.map(str::trim).filter(|g| !g.is_empty()) {
+        Some(g) => format!("guid:{}", g),
+        None => format!("url:{}", normalize_enclosure(enclosure)),
+    }
+}
+
+/// Strip scheme, tracking query parameters and fragment, and lowercase host.
+/// Other parameters are kept, they may be what tells episodes apart (`?id=123`)
+pub(crate) fn normalize_enclosure(enclosure: &str) -> String {
+    let url = enclosure.trim();
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let url = url.split('#').next().unwrap_or_default();
+    let (url, query) = url.split_once('?').unwrap_or((url, ""));
+    let url = match url.split_once('/') {
+        Some((host, path)) => format!("{}/{}", host.to_lowercase(), path),
+        None => url.to_lowercase(),
+    };
+    let query: Vec<&str> = query.split('&').filter(|p| !p.is_empty() && !is_tracking_param(p)).collect();
+    if query.is_empty() {
+        url
+    } else {
+        format!("{}?{}", url, query.join("&"))
+    }
+}
+
+/// Parameters added by analytics and ad networks, they differ between requests for the same file
+fn is_tracking_param(param: &str) -> bool {
+    let name = param.split('=').next().unwrap_or_default().to_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+const TRACKING_PARAMS: [&str; 8] = ["fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "_ga", "awcollectionid"];
+
+#[derive(DeriveIden)]
+enum EpisodeKey {
+    EpisodeKey,
+}
+
+#[derive(DeriveIden)]
+enum Tables {
+    ChannelItem,
+    ListeningState,
+    ListeningSession,
+    ListeningStat,
+    Bookmark,
+}
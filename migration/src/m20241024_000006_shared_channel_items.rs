@@ -0,0 +1,161 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+
+/// Drops global uniqueness of `channel_item.enclosure` so the same episode can be
+/// stored once per channel, and makes listening state unique per episode
+/// so progress is shared between channels.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can't drop a constraint, so rebuild the table
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelItemNew::Table)
+                        .col(ColumnDef::new(ChannelItem::Ordering).integer().not_null())
+                        .col(ColumnDef::new(ChannelItem::ChannelId).integer().not_null())
+                        .col(ColumnDef::new(ChannelItem::Title).string())
+                        .col(ColumnDef::new(ChannelItem::Link).string())
+                        .col(ColumnDef::new(ChannelItem::Source).string())
+                        .col(ColumnDef::new(ChannelItem::Enclosure).string().not_null())
+                        .col(ColumnDef::new(ChannelItem::Description).string())
+                        .col(ColumnDef::new(ChannelItem::Guid).string())
+                        .col(ColumnDef::new(ChannelItem::PubDate).timestamp_with_time_zone())
+                        .col(ColumnDef::new(ChannelItem::EpisodeKey).string().not_null().default(""))
+                        .primary_key(Index::create().col(ChannelItem::ChannelId).col(ChannelItem::Enclosure))
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk_channel")
+                                .from(ChannelItemNew::Table, ChannelItem::ChannelId)
+                                .to(Channel::Table, Channel::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                            )
+                        .to_owned()
+                )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let columns = "ordering, channel_id, title, link, source, enclosure, description, guid, pub_date, episode_key";
+        db.execute(Statement::from_string(backend,
+            format!("INSERT INTO channel_item_new ({0}) SELECT {0} FROM channel_item", columns))).await?;
+
+        manager
+            .drop_table(Table::drop().table(ChannelItem::Table).to_owned())
+            .await?;
+
+        manager
+            .rename_table(Table::rename().table(ChannelItemNew::Table, ChannelItem::Table).to_owned())
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_channel_item_episode_key")
+                    .table(ChannelItem::Table)
+                    .col(ChannelItem::EpisodeKey)
+                    .to_owned()
+                )
+            .await?;
+
+        // keep the state of every episode which was played last: the one matching its latest
+        // listening session, states have no update time. Without sessions the newest state is kept
+        db.execute(Statement::from_string(backend,
+            "DELETE FROM listening_state WHERE id NOT IN (
+                SELECT COALESCE(
+                    (SELECT s.id FROM listening_state s
+                        JOIN listening_session ls ON ls.episode_key = s.episode_key
+                            AND ls.channel_id = s.channel_id AND ls.channel_item_enclosure = s.channel_item_enclosure
+                        WHERE s.episode_key = g.episode_key
+                        ORDER BY ls.id DESC, s.id DESC LIMIT 1),
+                    MAX(g.id))
+                FROM listening_state g GROUP BY g.episode_key)")).await?;
+
+        manager
+            .drop_index(Index::drop().name("idx_listening_state_episode_key").table(ListeningState::Table).to_owned())
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_listening_state_episode_key")
+                    .table(ListeningState::Table)
+                    .col(ListeningState::EpisodeKey)
+                    .unique()
+                    .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("idx_listening_state_episode_key").table(ListeningState::Table).to_owned())
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_listening_state_episode_key")
+                    .table(ListeningState::Table)
+                    .col(ListeningState::EpisodeKey)
+                    .to_owned()
+                )
+            .await?;
+
+        // shared items can't be restored under a global unique enclosure
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        db.execute(Statement::from_string(backend,
+            "DELETE FROM channel_item WHERE rowid NOT IN (SELECT MIN(rowid) FROM channel_item GROUP BY enclosure)")).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_channel_item_enclosure")
+                    .table(ChannelItem::Table)
+                    .col(ChannelItem::Enclosure)
+                    .unique()
+                    .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ChannelItemNew {
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum ChannelItem {
+    Table,
+    Ordering,
+    ChannelId,
+    Enclosure,
+    Title,
+    Link,
+    Source,
+    Description,
+    Guid,
+    PubDate,
+    EpisodeKey
+}
+
+#[derive(DeriveIden)]
+enum ListeningState {
+    Table,
+    EpisodeKey
+}
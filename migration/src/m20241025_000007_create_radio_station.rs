@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RadioStation::Table)
+                    .if_not_exists()
+                        .col(ColumnDef::new(RadioStation::Id).integer().not_null().auto_increment().primary_key())
+                        .col(ColumnDef::new(RadioStation::Name).string())
+                        .col(ColumnDef::new(RadioStation::Url).string().not_null())
+                        .col(ColumnDef::new(RadioStation::Genre).string())
+                        .col(ColumnDef::new(RadioStation::Homepage).string())
+                        .col(ColumnDef::new(RadioStation::Country).string())
+                        .col(ColumnDef::new(RadioStation::Codec).string())
+                        .col(ColumnDef::new(RadioStation::Bitrate).integer())
+                        .col(ColumnDef::new(RadioStation::Favorite).boolean().not_null().default(false))
+                        .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RadioStation::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RadioStation {
+    Table,
+    Id,
+    Name,
+    Url,
+    Genre,
+    Homepage,
+    Country,
+    Codec,
+    Bitrate,
+    Favorite
+}
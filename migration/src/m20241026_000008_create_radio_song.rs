@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RadioSong::Table)
+                    .if_not_exists()
+                        .col(ColumnDef::new(RadioSong::Id).integer().not_null().auto_increment().primary_key())
+                        .col(ColumnDef::new(RadioSong::StationId).integer().not_null())
+                        .col(ColumnDef::new(RadioSong::StationName).string())
+                        .col(ColumnDef::new(RadioSong::Title).string().not_null())
+                        .col(ColumnDef::new(RadioSong::HeardAt).timestamp_with_time_zone().not_null())
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk_radio_station")
+                                .from(RadioSong::Table, RadioSong::StationId)
+                                .to(RadioStation::Table, RadioStation::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                            )
+                        .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RadioSong::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RadioStation {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RadioSong {
+    Table,
+    Id,
+    StationId,
+    StationName,
+    Title,
+    HeardAt
+}
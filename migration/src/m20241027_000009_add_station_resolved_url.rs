@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+/// Adds stream url resolved from station playlist (`.pls`, `.m3u`, ...)
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RadioStation::Table)
+                    .add_column(ColumnDef::new(RadioStation::ResolvedUrl).string())
+                    .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(RadioStation::Table).drop_column(RadioStation::ResolvedUrl).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RadioStation {
+    Table,
+    ResolvedUrl,
+}
@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScheduleJob::Table)
+                    .if_not_exists()
+                        .col(ColumnDef::new(ScheduleJob::Id).integer().not_null().auto_increment().primary_key())
+                        .col(ColumnDef::new(ScheduleJob::StationId).integer().not_null())
+                        .col(ColumnDef::new(ScheduleJob::Kind).string().not_null())
+                        .col(ColumnDef::new(ScheduleJob::StartTime).time().not_null())
+                        .col(ColumnDef::new(ScheduleJob::DurationMinutes).integer().not_null())
+                        .col(ColumnDef::new(ScheduleJob::Recurrence).string().not_null())
+                        .col(ColumnDef::new(ScheduleJob::Date).date())
+                        .col(ColumnDef::new(ScheduleJob::Enabled).boolean().not_null().default(true))
+                        .col(ColumnDef::new(ScheduleJob::LastRunAt).timestamp_with_time_zone())
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk_radio_station")
+                                .from(ScheduleJob::Table, ScheduleJob::StationId)
+                                .to(RadioStation::Table, RadioStation::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                            )
+                        .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScheduleJob::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RadioStation {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ScheduleJob {
+    Table,
+    Id,
+    StationId,
+    Kind,
+    StartTime,
+    DurationMinutes,
+    Recurrence,
+    Date,
+    Enabled,
+    LastRunAt
+}
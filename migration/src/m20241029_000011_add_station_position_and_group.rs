@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+/// Adds user defined station order and group (folder) name
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RadioStation::Table)
+                    .add_column(ColumnDef::new(RadioStation::Position).integer().not_null().default(0))
+                    .to_owned()
+                )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RadioStation::Table)
+                    .add_column(ColumnDef::new(RadioStation::GroupName).string())
+                    .to_owned()
+                )
+            .await?;
+
+        // keep insertion order of existing stations
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(RadioStation::Table)
+                    .value(RadioStation::Position, Expr::col(RadioStation::Id))
+                    .to_owned()
+                )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(RadioStation::Table).drop_column(RadioStation::GroupName).to_owned())
+            .await?;
+
+        manager
+            .alter_table(Table::alter().table(RadioStation::Table).drop_column(RadioStation::Position).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RadioStation {
+    Table,
+    Id,
+    Position,
+    GroupName,
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+
+use crate::m20241023_000005_add_episode_key::episode_key;
+
+/// Recomputes enclosure based episode keys (items without GUID) now that only tracking
+/// parameters are stripped from the query, enclosures like `get?id=123` and `get?id=124`
+/// were taken as one episode before.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let rows = db.query_all(Statement::from_string(backend, "SELECT channel_id, enclosure, guid FROM channel_item WHERE episode_key LIKE 'url:%'")).await?;
+        for row in rows {
+            let channel_id: i32 = row.try_get("", "channel_id")?;
+            let enclosure: String = row.try_get("", "enclosure")?;
+            let guid: Option<String> = row.try_get("", "guid")?;
+            db.execute(Statement::from_sql_and_values(backend,
+                "UPDATE channel_item SET episode_key = ? WHERE channel_id = ? AND enclosure = ?",
+                [episode_key(guid.as_deref(), &enclosure).into(), channel_id.into(), enclosure.into()])).await?;
+        }
+
+        // a key only gets more specific, so the unique listening state keys stay unique
+        for table in ["listening_state", "listening_session", "listening_stat", "bookmark"] {
+            let rows = db.query_all(Statement::from_string(backend,
+                format!("SELECT id, channel_item_enclosure FROM {} WHERE episode_key LIKE 'url:%'", table))).await?;
+            for row in rows {
+                let id: i32 = row.try_get("", "id")?;
+                let enclosure: String = row.try_get("", "channel_item_enclosure")?;
+                db.execute(Statement::from_sql_and_values(backend,
+                    format!("UPDATE {} SET episode_key = ? WHERE id = ?", table),
+                    [episode_key(None, &enclosure).into(), id.into()])).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // the more specific keys are valid under the old rules as well
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+
+use crate::m20241023_000005_add_episode_key::normalize_enclosure;
+
+/// Scopes GUID based episode keys to their channel, GUIDs like `1` or `episode-12`
+/// are only unique within a feed and unrelated shows shared progress.
+/// Items of feeds sharing an episode (same GUID and enclosure) keep one key.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        // (channel id, enclosure) -> new key
+        let mut item_keys: HashMap<(i32, String), String> = HashMap::new();
+        let mut own: HashMap<(i32, String), String> = HashMap::new();
+        let mut shared: HashMap<(String, String), String> = HashMap::new();
+        let rows = db.query_all(Statement::from_string(backend,
+            "SELECT channel_id, enclosure, guid FROM channel_item WHERE episode_key LIKE 'guid:%' ORDER BY channel_id, ordering")).await?;
+        for row in rows {
+            let channel_id: i32 = row.try_get("", "channel_id")?;
+            let enclosure: String = row.try_get("", "enclosure")?;
+            let guid: Option<String> = row.try_get("", "guid")?;
+            let guid = guid.unwrap_or_default().trim().to_string();
+            let key = own.get(&(channel_id, guid.clone()))
+                .or_else(|| shared.get(&(guid.clone(), normalize_enclosure(&enclosure))))
+                .cloned()
+                .unwrap_or_else(|| guid_key(channel_id, &guid));
+            own.entry((channel_id, guid.clone())).or_insert(key.clone());
+            shared.entry((guid, normalize_enclosure(&enclosure))).or_insert(key.clone());
+            item_keys.insert((channel_id, enclosure), key);
+        }
+        for ((channel_id, enclosure), key) in item_keys.iter() {
+            db.execute(Statement::from_sql_and_values(backend,
+                "UPDATE channel_item SET episode_key = ? WHERE channel_id = ? AND enclosure = ?",
+                [key.clone().into(), (*channel_id).into(), enclosure.clone().into()])).await?;
+        }
+
+        // rows referencing an episode go to the episode of their channel, the old keys
+        // are unique per GUID, so the unique listening state keys stay unique
+        for table in ["listening_state", "listening_session", "listening_stat", "bookmark"] {
+            let rows = db.query_all(Statement::from_string(backend,
+                format!("SELECT id, channel_id, channel_item_enclosure, episode_key FROM {} WHERE episode_key LIKE 'guid:%'", table))).await?;
+            for row in rows {
+                let id: i32 = row.try_get("", "id")?;
+                let channel_id: i32 = row.try_get("", "channel_id")?;
+                let enclosure: String = row.try_get("", "channel_item_enclosure")?;
+                let old_key: String = row.try_get("", "episode_key")?;
+                let key = item_keys.get(&(channel_id, enclosure)).cloned()
+                    .unwrap_or_else(|| guid_key(channel_id, old_key.trim_start_matches("guid:")));
+                db.execute(Statement::from_sql_and_values(backend,
+                    format!("UPDATE {} SET episode_key = ? WHERE id = ?", table),
+                    [key.into(), id.into()])).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        // `guid:<channel id>:<guid>` -> `guid:<guid>`, states of one GUID in several channels can't all be kept
+        db.execute(Statement::from_string(backend,
+            "DELETE FROM listening_state WHERE episode_key LIKE 'guid:%' AND id NOT IN (
+                SELECT MAX(id) FROM listening_state WHERE episode_key LIKE 'guid:%'
+                GROUP BY substr(episode_key, instr(substr(episode_key, 6), ':') + 6))")).await?;
+        for table in ["channel_item", "listening_state", "listening_session", "listening_stat", "bookmark"] {
+            db.execute(Statement::from_string(backend,
+                format!("UPDATE {} SET episode_key = 'guid:' || substr(episode_key, instr(substr(episode_key, 6), ':') + 6)
+                    WHERE episode_key LIKE 'guid:%'", table))).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Same rule as `ui_models::episode_key` for items with GUID
+fn guid_key(channel_id: i32, guid: &str) -> String {
+    format!("guid:{}:{}", channel_id, guid)
+}
@@ -0,0 +1,89 @@
+use ratatui::{layout::Rect, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, List, ListState}, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{keymap::{self, Action, Scope}, list_navigation, mouse::Mouse, theme, ui_models::{self, time_to_display}, AsyncAction};
+
+pub struct BookmarksModel {
+    pub bookmarks: Vec<ui_models::Bookmark>,
+    pub list_state: ListState,
+    /// where the list was rendered, for mouse input
+    area: Rect,
+    tx: UnboundedSender<AsyncAction>,
+}
+
+impl BookmarksModel {
+    pub fn new(tx: UnboundedSender<AsyncAction>) -> Self {
+        Self {
+            bookmarks: vec![],
+            list_state: Default::default(),
+            area: Rect::default(),
+            tx,
+        }
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<ui_models::Bookmark>) {
+        self.bookmarks = bookmarks;
+        let selected = match self.list_state.selected() {
+            _ if self.bookmarks.is_empty() => None,
+            Some(s) => Some(std::cmp::min(s, self.bookmarks.len() - 1)),
+            None => Some(0),
+        };
+        self.list_state.select(selected);
+    }
+
+    pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
+        let lines: Vec<Line> = self.bookmarks.iter().map(|b| {
+            let title = b.item.as_ref()
+                .and_then(|i| i.title.clone())
+                .unwrap_or(b.enclosure.clone());
+            let channel = b.channel_title.clone().unwrap_or("-".to_string());
+
+            Line::from(vec![
+                Span::styled(format!("[{}] ", time_to_display(b.time)), theme.highlight),
+                Span::styled(format!("{} - ", channel), theme.accent),
+                Span::styled(title, Style::default()),
+                Span::styled(b.note.as_ref().map(|n| format!("  {}", n)).unwrap_or_default(), theme.muted),
+            ])
+        }).collect();
+
+        let list = List::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Bookmarks").title_bottom(keymap::get().hint(Scope::Bookmarks, &[Action::Select, Action::Delete])))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ")
+            .repeat_highlight_symbol(true);
+
+        self.area = rect;
+        f.render_stateful_widget(list, rect, &mut self.list_state);
+    }
+
+    pub async fn handle_mouse(&mut self, mouse: Mouse) -> std::io::Result<()> {
+        match mouse.list_action(self.area, &mut self.list_state, self.bookmarks.len()) {
+            Some(action) => self.handle_action(action).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn handle_action(&mut self, action: Action) -> std::io::Result<()> {
+        let selected = self.list_state.selected().and_then(|i| self.bookmarks.get(i));
+        match action {
+            Action::Select => {
+                if let Some(bookmark) = selected {
+                    if let Some(item) = bookmark.item.clone() {
+                        let _ = self.tx.send(AsyncAction::ResumeItem(item, bookmark.time));
+                    }
+                }
+            },
+            Action::Delete => {
+                if let Some(bookmark) = selected {
+                    let _ = self.tx.send(AsyncAction::DeleteBookmark(bookmark.id));
+                }
+            },
+            action if list_navigation::is_motion(action) => {
+                list_navigation::navigate(&mut self.list_state, self.bookmarks.len(), self.area.height.saturating_sub(2) as usize, action, None);
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+}
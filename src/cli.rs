@@ -0,0 +1,413 @@
+use std::error::Error;
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ColumnTrait, Database, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde_json::{json, Value};
+
+use crate::{config, entity::channel, ui_models::{time_to_display, ChannelItem}};
+use crate::data_layer::{data_provider::DataProvider, listening_state_data_layer::ListeningStateDataLayer, listening_stats_data_layer::ListeningStatsDataLayer};
+
+const USAGE: &str = "usage: librecast [--json] <command>
+  add <feed url>                         subscribe to a podcast feed
+  list channels                          list subscribed podcasts
+  list episodes <channel>                list episodes of a podcast
+  refresh <channel> | --all              fetch feeds again
+  mark-played <channel> <episode>...     mark episodes as played
+  export opml                            print subscriptions as OPML
+  stats                                  print listening statistics
+<channel> is a channel id or title, <episode> an episode number (first column of `list episodes`) or key";
+
+const COMMANDS: [&str; 6] = ["add", "list", "refresh", "mark-played", "export", "stats"];
+
+/// Exit codes of headless commands
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+enum CliError {
+    Usage(String),
+    Failed(String),
+}
+
+impl<E: Error> From<E> for CliError {
+    fn from(e: E) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+fn failed(e: Box<dyn Error>) -> CliError {
+    CliError::Failed(e.to_string())
+}
+
+/// Whether `arg` starts a headless command instead of the tui
+pub fn is_command(arg: &str) -> bool {
+    COMMANDS.contains(&arg) || matches!(arg, "--json" | "help" | "-h" | "--help")
+}
+
+/// Headless command parsed from the arguments
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    Add(&'a str),
+    ListChannels,
+    ListEpisodes(&'a str),
+    /// `None` refreshes all channels
+    Refresh(Option<&'a str>),
+    MarkPlayed(&'a str, &'a [&'a str]),
+    ExportOpml,
+    Stats,
+}
+
+/// Run headless command from `args` and return the process exit code.
+/// Output is human readable, or JSON with `--json`; errors go to stderr
+pub async fn run(args: &[String]) -> i32 {
+    let json = args.iter().any(|a| a == "--json");
+    let args: Vec<&str> = args.iter().map(String::as_str).filter(|a| *a != "--json").collect();
+
+    if args.is_empty() || matches!(args[0], "help" | "-h" | "--help") {
+        println!("{}\n{}", USAGE, config::OPTIONS_USAGE);
+        return if args.is_empty() { EXIT_USAGE } else { 0 };
+    }
+
+    let result = match parse(&args) {
+        Ok(command) => execute(command, json).await,
+        Err(e) => Err(e),
+    };
+    exit_code(result)
+}
+
+fn parse<'a>(args: &'a [&'a str]) -> Result<Command<'a>, CliError> {
+    let (command, args) = (args[0], &args[1..]);
+    match (command, args) {
+        ("add", [url]) => Ok(Command::Add(url)),
+        ("list", ["channels"]) => Ok(Command::ListChannels),
+        ("list", ["episodes", channel]) => Ok(Command::ListEpisodes(channel)),
+        ("refresh", ["--all"]) => Ok(Command::Refresh(None)),
+        ("refresh", [channel]) => Ok(Command::Refresh(Some(channel))),
+        ("mark-played", [channel, episodes @ ..]) if !episodes.is_empty() => Ok(Command::MarkPlayed(channel, episodes)),
+        ("export", ["opml"]) => Ok(Command::ExportOpml),
+        ("stats", []) => Ok(Command::Stats),
+        _ => Err(CliError::Usage(format!("invalid arguments for {}", command))),
+    }
+}
+
+/// Print the error of `result` and map it to the exit code
+fn exit_code(result: Result<bool, CliError>) -> i32 {
+    match result {
+        Ok(ok) => if ok { 0 } else { EXIT_FAILED },
+        Err(CliError::Usage(e)) => {
+            eprintln!("{}\n{}", e, USAGE);
+            EXIT_USAGE
+        },
+        Err(CliError::Failed(e)) => {
+            eprintln!("{}", e);
+            EXIT_FAILED
+        },
+    }
+}
+
+async fn execute(command: Command<'_>, json: bool) -> Result<bool, CliError> {
+    let db = Database::connect(config::database_url()).await?;
+    Migrator::up(&db, None).await?;
+    execute_on(&db, command, json).await
+}
+
+/// Returns false when some of the work failed, e.g. one of the refreshed feeds
+async fn execute_on(db: &DatabaseConnection, command: Command<'_>, json: bool) -> Result<bool, CliError> {
+    match command {
+        Command::Add(url) => add(db, url, json).await,
+        Command::ListChannels => list_channels(db, json).await,
+        Command::ListEpisodes(channel) => list_episodes(db, channel, json).await,
+        Command::Refresh(None) => {
+            let channels = channel::Entity::find().order_by_asc(channel::Column::Id).all(db).await?;
+            refresh(db, channels, json).await
+        },
+        Command::Refresh(Some(channel)) => {
+            let channel = find_channel(db, channel).await?;
+            refresh(db, vec![channel], json).await
+        },
+        Command::MarkPlayed(channel, episodes) => mark_played(db, channel, episodes, json).await,
+        Command::ExportOpml => export_opml(db).await,
+        Command::Stats => stats(db, json).await,
+    }
+}
+
+async fn add(db: &DatabaseConnection, url: &str, json: bool) -> Result<bool, CliError> {
+    let exists = channel::Entity::find().filter(channel::Column::Link.eq(url)).one(db).await?;
+    if let Some(c) = exists {
+        return Err(CliError::Failed(format!("already subscribed as {} ({})", c.id, c.title.unwrap_or_default())));
+    }
+    let id = DataProvider::add_channel(url.to_string(), db.clone()).await.map_err(failed)?;
+    let channel = channel::Entity::find_by_id(id).one(db).await?.ok_or(CliError::Failed(format!("channel {} disappeared", id)))?;
+    let episodes = DataProvider::get_items_from_db(id, db).await?.len();
+
+    if json {
+        let mut value = channel_json(&channel);
+        value["episodes"] = json!(episodes);
+        println!("{}", value);
+    } else {
+        println!("{}  {} ({} episodes)", channel.id, channel.title.unwrap_or_default(), episodes);
+    }
+    Ok(true)
+}
+
+async fn list_channels(db: &DatabaseConnection, json: bool) -> Result<bool, CliError> {
+    let channels = channel::Entity::find().order_by_asc(channel::Column::Id).all(db).await?;
+    if json {
+        println!("{}", Value::Array(channels.iter().map(channel_json).collect()));
+    } else {
+        for c in channels {
+            println!("{:>4}  {}  {}", c.id, c.title.unwrap_or("-".to_string()), c.link.unwrap_or_default());
+        }
+    }
+    Ok(true)
+}
+
+async fn list_episodes(db: &DatabaseConnection, channel: &str, json: bool) -> Result<bool, CliError> {
+    let channel = find_channel(db, channel).await?;
+    let items = DataProvider::get_items_from_db(channel.id, db).await?;
+    if json {
+        println!("{}", Value::Array(items.iter().map(item_json).collect()));
+    } else {
+        for i in items {
+            let date = i.pub_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or("-".repeat(10));
+            let state = match i.listening_state.as_ref() {
+                Some(ls) if ls.finished => "played".to_string(),
+                Some(ls) => time_to_display(ls.time),
+                None => "new".to_string(),
+            };
+            println!("{:>4}  {}  {:<11}  {}", i.ordering, date, state, i.title.unwrap_or("-".to_string()));
+        }
+    }
+    Ok(true)
+}
+
+/// Feeds are fetched one after another, a failing feed doesn't stop the others
+async fn refresh(db: &DatabaseConnection, channels: Vec<channel::Model>, json: bool) -> Result<bool, CliError> {
+    let mut results = vec![];
+    for c in channels {
+        let res = match c.link.clone() {
+            Some(link) => DataProvider::fetch_data(link, c.id, db.clone()).await.map_err(|e| e.to_string()),
+            None => Err("channel has no feed url".to_string()),
+        };
+        let res = match res {
+            Ok(fetched) => Ok((DataProvider::get_items_from_db(fetched.channel_id, db).await?.len(), fetched.failed_items)),
+            Err(e) => Err(e),
+        };
+        if !json {
+            match &res {
+                Ok((episodes, failed_items)) => {
+                    println!("{}  {}: {} episodes", c.id, c.title.clone().unwrap_or_default(), episodes);
+                    for f in failed_items {
+                        eprintln!("{}  {}: episode not stored: {}", c.id, c.title.clone().unwrap_or_default(), f);
+                    }
+                },
+                Err(e) => eprintln!("{}  {}: {}", c.id, c.title.clone().unwrap_or_default(), e),
+            }
+        }
+        results.push((c, res));
+    }
+
+    let ok = results.iter().all(|(_, r)| r.is_ok());
+    if json {
+        let values = results.into_iter().map(|(c, res)| {
+            let mut value = channel_json(&c);
+            match res {
+                Ok((episodes, failed_items)) => {
+                    value["episodes"] = json!(episodes);
+                    value["failed_items"] = json!(failed_items);
+                },
+                Err(e) => value["error"] = json!(e),
+            }
+            value
+        }).collect();
+        println!("{}", Value::Array(values));
+    }
+    Ok(ok)
+}
+
+async fn mark_played(db: &DatabaseConnection, channel: &str, episodes: &[&str], json: bool) -> Result<bool, CliError> {
+    let channel = find_channel(db, channel).await?;
+    let items = DataProvider::get_items_from_db(channel.id, db).await?;
+    let selected: Vec<&ChannelItem> = episodes.iter().map(|e| {
+        items.iter()
+            .find(|i| e.parse() == Ok(i.ordering) || i.episode_key == *e)
+            .ok_or(CliError::Failed(format!("no episode {} in {}", e, channel.title.clone().unwrap_or_default())))
+    }).collect::<Result<_, _>>()?;
+
+    for item in selected.iter() {
+        // finished is only set on an existing listening state
+        if item.listening_state.is_none() {
+            ListeningStateDataLayer::update_current_time_for_item(db.clone(), item.episode_key.clone(), item.enclosure.clone(), item.channel_id, 0.0).await?;
+        }
+        ListeningStateDataLayer::mark_item_as_finished(db.clone(), item.episode_key.clone()).await?;
+    }
+
+    if json {
+        println!("{}", json!(selected.iter().map(|i| &i.episode_key).collect::<Vec<_>>()));
+    } else {
+        for item in selected {
+            println!("played: {}", item.title.clone().unwrap_or(item.episode_key.clone()));
+        }
+    }
+    Ok(true)
+}
+
+async fn export_opml(db: &DatabaseConnection) -> Result<bool, CliError> {
+    let channels = channel::Entity::find().order_by_asc(channel::Column::Id).all(db).await?;
+    println!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    println!("<opml version=\"2.0\">");
+    println!("  <head><title>{} subscriptions</title></head>", env!("CARGO_PKG_NAME"));
+    println!("  <body>");
+    for c in channels.iter().filter(|c| c.link.is_some()) {
+        let title = xml_escape(c.title.as_deref().unwrap_or_default());
+        println!("    <outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"/>", title, title, xml_escape(c.link.as_deref().unwrap_or_default()));
+    }
+    println!("  </body>");
+    println!("</opml>");
+    Ok(true)
+}
+
+async fn stats(db: &DatabaseConnection, json: bool) -> Result<bool, CliError> {
+    let stats = ListeningStatsDataLayer::get_stats(db).await?;
+    if json {
+        println!("{}", json!({
+            "total_seconds": stats.total_seconds,
+            "episodes_started": stats.episodes_started,
+            "episodes_finished": stats.episodes_finished,
+            "completion_rate": stats.completion_rate,
+            "longest_streak_days": stats.longest_streak,
+            "per_channel": stats.per_channel.iter().map(|(id, title, seconds)| json!({"channel_id": id, "title": title, "seconds": seconds})).collect::<Vec<_>>(),
+            "per_week": stats.per_week.iter().map(|(week, seconds)| json!({"week": week, "seconds": seconds})).collect::<Vec<_>>(),
+        }));
+    } else {
+        println!("Total listening time: {}", time_to_display(stats.total_seconds));
+        println!("Episodes finished:    {} of {} started ({:.0}%)", stats.episodes_finished, stats.episodes_started, stats.completion_rate * 100.0);
+        println!("Longest streak:       {} days", stats.longest_streak);
+        for (_, title, seconds) in stats.per_channel.iter() {
+            println!("  {:>14}  {}", time_to_display(*seconds), title);
+        }
+    }
+    Ok(true)
+}
+
+/// Channel by id, or by title ignoring case
+async fn find_channel(db: &DatabaseConnection, channel: &str) -> Result<channel::Model, CliError> {
+    let channels = channel::Entity::find().all(db).await?;
+    if let Ok(id) = channel.parse::<i32>() {
+        if let Some(c) = channels.iter().find(|c| c.id == id) {
+            return Ok(c.clone());
+        }
+    }
+    let mut found: Vec<channel::Model> = channels.into_iter()
+        .filter(|c| c.title.as_ref().is_some_and(|t| t.eq_ignore_ascii_case(channel)))
+        .collect();
+    match found.len() {
+        0 => Err(CliError::Failed(format!("no channel {}", channel))),
+        1 => Ok(found.remove(0)),
+        _ => Err(CliError::Failed(format!("{} channels are titled {}, use the id", found.len(), channel))),
+    }
+}
+
+fn channel_json(channel: &channel::Model) -> Value {
+    json!({
+        "id": channel.id,
+        "title": channel.title,
+        "link": channel.link,
+    })
+}
+
+fn item_json(item: &ChannelItem) -> Value {
+    json!({
+        "number": item.ordering,
+        "channel_id": item.channel_id,
+        "title": item.title,
+        "pub_date": item.pub_date.map(|d| d.to_rfc3339()),
+        "enclosure": item.enclosure,
+        "episode_key": item.episode_key,
+        "position": item.listening_state.as_ref().map(|ls| ls.time),
+        "finished": item.listening_state.as_ref().is_some_and(|ls| ls.finished),
+    })
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveValue, PaginatorTrait};
+
+    use super::*;
+
+    async fn memory_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        db
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse(&["add", "http://feed"]).ok(), Some(Command::Add("http://feed")));
+        assert_eq!(parse(&["list", "channels"]).ok(), Some(Command::ListChannels));
+        assert_eq!(parse(&["list", "episodes", "7"]).ok(), Some(Command::ListEpisodes("7")));
+        assert_eq!(parse(&["refresh", "--all"]).ok(), Some(Command::Refresh(None)));
+        assert_eq!(parse(&["refresh", "News"]).ok(), Some(Command::Refresh(Some("News"))));
+        assert_eq!(parse(&["mark-played", "News", "1", "3"]).ok(), Some(Command::MarkPlayed("News", &["1", "3"])));
+        assert_eq!(parse(&["export", "opml"]).ok(), Some(Command::ExportOpml));
+        assert_eq!(parse(&["stats"]).ok(), Some(Command::Stats));
+    }
+
+    #[test]
+    fn invalid_arguments_are_usage_errors() {
+        for args in [&["add"][..], &["list"], &["list", "podcasts"], &["refresh"], &["mark-played", "News"], &["export", "csv"], &["stats", "all"]] {
+            assert!(matches!(parse(args), Err(CliError::Usage(e)) if e == format!("invalid arguments for {}", args[0])), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code(Ok(true)), 0);
+        assert_eq!(exit_code(Ok(false)), EXIT_FAILED);
+        assert_eq!(exit_code(Err(CliError::Failed("no channel x".to_string()))), EXIT_FAILED);
+        assert_eq!(exit_code(Err(CliError::Usage("invalid arguments for add".to_string()))), EXIT_USAGE);
+    }
+
+    #[tokio::test]
+    async fn run_without_command_is_a_usage_error() {
+        assert_eq!(run(&[]).await, EXIT_USAGE);
+        assert_eq!(run(&["--json".to_string()]).await, EXIT_USAGE);
+        assert_eq!(run(&["help".to_string()]).await, 0);
+    }
+
+    #[tokio::test]
+    async fn failing_feeds_fail_the_command() {
+        let db = memory_db().await;
+        assert!(execute_on(&db, Command::Refresh(None), false).await.is_ok_and(|ok| ok));
+        assert!(matches!(execute_on(&db, Command::ListEpisodes("News"), false).await, Err(CliError::Failed(e)) if e == "no channel News"));
+
+        channel::Entity::insert(channel::ActiveModel {
+            id: ActiveValue::NotSet,
+            title: ActiveValue::set(Some("News".to_string())),
+            link: ActiveValue::set(None),
+            description: ActiveValue::set(None),
+        }).exec(&db).await.unwrap();
+        assert!(execute_on(&db, Command::Refresh(Some("news")), true).await.is_ok_and(|ok| !ok));
+        assert!(matches!(execute_on(&db, Command::MarkPlayed("News", &["4"]), false).await, Err(CliError::Failed(e)) if e == "no episode 4 in News"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_feed_is_not_added() {
+        let db = memory_db().await;
+        let result = execute_on(&db, Command::Add("http://127.0.0.1:1/feed.xml"), false).await;
+        assert!(matches!(result, Err(CliError::Failed(_))));
+        assert_eq!(channel::Entity::find().count(&db).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn xml_escape_quotes_markup() {
+        assert_eq!(xml_escape(r#"Tom & Jerry's <"Show">"#), "Tom &amp; Jerry's &lt;&quot;Show&quot;&gt;");
+        assert_eq!(xml_escape("plain"), "plain");
+    }
+}
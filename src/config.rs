@@ -1,17 +1,490 @@
-use std::path::PathBuf;
+use std::{fmt, path::{Path, PathBuf}, sync::OnceLock};
 
-fn config_path() -> PathBuf {
-    home::home_dir().unwrap_or("./".into()).join(".librecast.radio")
+use serde::Deserialize;
+
+use crate::{keymap::{Keymap, KeysConfig}, theme::{Theme, ThemeConfig}};
+
+const DEFAULT_RADIO_DIRECTORY_URL: &str = "https://all.api.radio-browser.info";
+const DEFAULT_RECONNECT_ATTEMPTS: u32 = 5;
+
+pub const OPTIONS_USAGE: &str = "options (override the config file):
+  --config <file>                        config file, default ~/.config/librecast/config.toml
+  --database-url <url>                   [database] url
+  --radio-directory-url <url>            [radio] directory_url
+  --recordings-dir <dir>                 [radio] recordings_dir
+  --reconnect-attempts <n>               [radio] reconnect_attempts
+  --tick-rate <ms>                       [ui] tick_rate_ms
+  --render-rate <ms>                     [ui] render_rate_ms
+  --seek-forward <seconds>               [player] seek_forward_seconds
+  --seek-backward <seconds>              [player] seek_backward_seconds
+  --socket <path>                        [control] socket
+  --theme <name>                         [theme] name: dark, light, high-contrast or a user theme";
+
+/// (command line flag, environment variable, config key) of settings which can be overridden,
+/// flags win over environment variables which win over the config file
+const OVERRIDES: [(&str, Option<&str>, &str); 10] = [
+    ("--database-url", Some("DATABASE_URL"), "database.url"),
+    ("--radio-directory-url", Some("LIBRECAST_RADIO_DIRECTORY_URL"), "radio.directory_url"),
+    ("--recordings-dir", None, "radio.recordings_dir"),
+    ("--reconnect-attempts", Some("LIBRECAST_RECONNECT_ATTEMPTS"), "radio.reconnect_attempts"),
+    ("--tick-rate", None, "ui.tick_rate_ms"),
+    ("--render-rate", None, "ui.render_rate_ms"),
+    ("--seek-forward", None, "player.seek_forward_seconds"),
+    ("--seek-backward", None, "player.seek_backward_seconds"),
+    ("--socket", Some("LIBRECAST_SOCKET"), "control.socket"),
+    ("--theme", Some("LIBRECAST_THEME"), "theme.name"),
+];
+
+/// Application settings from `~/.config/librecast/config.toml`,
+/// every key is optional, e.g.
+///
+/// ```toml
+/// [database]
+/// url = "sqlite:///home/me/podcasts.db?mode=rwc"
+///
+/// [radio]
+/// directory_url = "https://de1.api.radio-browser.info"
+/// recordings_dir = "/home/me/Music/radio"
+/// reconnect_attempts = 10
+///
+/// [ui]
+/// tick_rate_ms = 250
+/// render_rate_ms = 40
+///
+/// [player]
+/// seek_forward_seconds = 30
+/// seek_backward_seconds = 10
+///
+/// [control]
+/// socket = "/tmp/librecast.sock"
+///
+/// [theme]
+/// name = "light"
+///
+/// [keys.radio]
+/// record = ["r", "g r"]
+/// ```
+///
+/// see [`crate::theme::ThemeConfig`] for themes and [`crate::keymap::KeysConfig`] for key bindings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub radio: RadioConfig,
+    pub ui: UiConfig,
+    pub player: PlayerConfig,
+    pub control: ControlConfig,
+    pub theme: ThemeConfig,
+    pub keys: KeysConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// sea-orm connection string
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RadioConfig {
+    /// Radio Browser compatible directory
+    pub directory_url: String,
+    pub recordings_dir: PathBuf,
+    /// before giving up on a dropped stream
+    pub reconnect_attempts: u32,
+    /// stream urls of old versions, imported once
+    pub legacy_file: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    /// how often playback state is checked
+    pub tick_rate_ms: u64,
+    /// how often the screen is drawn
+    pub render_rate_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub seek_forward_seconds: f64,
+    pub seek_backward_seconds: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlConfig {
+    /// Unix socket of the control API
+    pub socket: PathBuf,
+}
+
+fn home_dir() -> PathBuf {
+    home::home_dir().unwrap_or("./".into())
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: format!("sqlite://{}/.librecast.db?mode=rwc", home_dir().display()),
+        }
+    }
+}
+
+impl Default for RadioConfig {
+    fn default() -> Self {
+        RadioConfig {
+            directory_url: DEFAULT_RADIO_DIRECTORY_URL.to_string(),
+            recordings_dir: home_dir().join("librecast-recordings"),
+            reconnect_attempts: DEFAULT_RECONNECT_ATTEMPTS,
+            legacy_file: home_dir().join(".librecast.radio"),
+        }
+    }
 }
 
-pub fn load() -> Result<Vec<String>, std::io::Error> {
-        let config = std::fs::read_to_string(&config_path()).unwrap_or("[]".to_string());
-        let streams_collection: Vec<String> = serde_json::from_str(&config)?;
-        Ok(streams_collection)
+impl Default for UiConfig {
+    fn default() -> Self {
+        UiConfig {
+            tick_rate_ms: 250,
+            render_rate_ms: 40,
+        }
+    }
 }
 
-pub fn save(streams_collection: Vec<String>) -> Result<(), std::io::Error> {
-    let string_content = serde_json::to_string(&streams_collection)?;
-    std::fs::write(config_path(), string_content)?;
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            seek_forward_seconds: 30.0,
+            seek_backward_seconds: 10.0,
+        }
+    }
+}
+
+impl Default for ControlConfig {
+    /// `$XDG_RUNTIME_DIR/librecast.sock` when available
+    fn default() -> Self {
+        let socket = match std::env::var("XDG_RUNTIME_DIR").ok().filter(|d| !d.trim().is_empty()) {
+            Some(dir) => PathBuf::from(dir).join("librecast.sock"),
+            None => home_dir().join(".librecast.sock"),
+        };
+        ControlConfig { socket }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Load config file (`--config` or the default one, which may be missing),
+    /// then apply environment variables and command line flags.
+    /// Returns the config and the remaining arguments
+    pub fn load(args: &[String]) -> Result<(Config, Vec<String>), ConfigError> {
+        let (path, flags, rest) = parse_flags(args)?;
+
+        let default_path = default_path();
+        let explicit = path.is_some();
+        let path = path.unwrap_or(default_path);
+        let mut table = if path.exists() || explicit {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| ConfigError(format!("unable to read config file {}: {}", path.display(), e)))?;
+            // deserialize the file alone first, errors then point to its lines
+            toml::from_str::<Config>(&content)
+                .map_err(|e| ConfigError(format!("invalid config file {}: {}", path.display(), e)))?;
+            toml::from_str::<toml::Table>(&content)
+                .map_err(|e| ConfigError(format!("invalid config file {}: {}", path.display(), e)))?
+        } else {
+            toml::Table::new()
+        };
+
+        for (flag, env, key) in OVERRIDES {
+            let from_env = env.and_then(|e| std::env::var(e).ok()).filter(|v| !v.trim().is_empty()).map(|v| (v, format!("${}", env.unwrap_or_default())));
+            let from_flag = flags.iter().find(|(f, _)| f == flag).map(|(_, v)| (v.clone(), flag.to_string()));
+            if let Some((value, source)) = from_flag.or(from_env) {
+                // checked one by one, so errors name the flag or variable
+                set(&mut table, key, &value)
+                    .and_then(|_| toml::Value::Table(table.clone()).try_into::<Config>().map_err(|e| e.message().to_string()))
+                    .map_err(|e| ConfigError(format!("invalid value {:?} of {}: {}", value, source, e)))?;
+            }
+        }
+
+        let config: Config = toml::Value::Table(table).try_into()
+            .map_err(|e: toml::de::Error| ConfigError(format!("invalid configuration: {}", e.message())))?;
+        config.validate()?;
+        Ok((config, rest))
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = vec![];
+        if self.database.url.trim().is_empty() {
+            problems.push("[database] url must not be empty".to_string());
+        }
+        if !self.radio.directory_url.starts_with("http://") && !self.radio.directory_url.starts_with("https://") {
+            problems.push(format!("[radio] directory_url must be a http(s) url, not {:?}", self.radio.directory_url));
+        }
+        if self.ui.tick_rate_ms == 0 {
+            problems.push("[ui] tick_rate_ms must be greater than 0".to_string());
+        }
+        if self.ui.render_rate_ms == 0 {
+            problems.push("[ui] render_rate_ms must be greater than 0".to_string());
+        }
+        if self.player.seek_forward_seconds.is_nan() || self.player.seek_forward_seconds <= 0.0 {
+            problems.push("[player] seek_forward_seconds must be greater than 0".to_string());
+        }
+        if self.player.seek_backward_seconds.is_nan() || self.player.seek_backward_seconds <= 0.0 {
+            problems.push("[player] seek_backward_seconds must be greater than 0".to_string());
+        }
+        if self.control.socket.as_os_str().is_empty() {
+            problems.push("[control] socket must not be empty".to_string());
+        }
+        if let Err(e) = Theme::load(&self.theme) {
+            problems.extend(e);
+        }
+        if let Err(e) = Keymap::new(&self.keys) {
+            problems.extend(e);
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError(format!("invalid configuration:\n  {}", problems.join("\n  "))))
+        }
+    }
+}
+
+/// `~/.config/librecast`, or under `$XDG_CONFIG_HOME`
+pub fn config_dir() -> PathBuf {
+    let dir = std::env::var("XDG_CONFIG_HOME").ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or(home_dir().join(".config"));
+    dir.join("librecast")
+}
+
+fn default_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+type Flags = (Option<PathBuf>, Vec<(String, String)>, Vec<String>);
+
+/// Split leading `--flag value` / `--flag=value` options from the command
+fn parse_flags(args: &[String]) -> Result<Flags, ConfigError> {
+    let mut path = None;
+    let mut flags = vec![];
+    let mut args = args.iter();
+    let mut rest = vec![];
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((f, v)) => (f, Some(v.to_string())),
+            None => (arg.as_str(), None),
+        };
+        if flag != "--config" && !OVERRIDES.iter().any(|(f, _, _)| *f == flag) {
+            rest.push(arg.clone());
+            break;
+        }
+        let value = match inline.or_else(|| args.next().cloned()) {
+            Some(v) => v,
+            None => return Err(ConfigError(format!("{} needs a value\n{}", flag, OPTIONS_USAGE))),
+        };
+        if flag == "--config" {
+            path = Some(PathBuf::from(value));
+        } else {
+            flags.push((flag.to_string(), value));
+        }
+    }
+    rest.extend(args.cloned());
+    Ok((path, flags, rest))
+}
+
+/// Set `section.key` in table, numbers are kept as numbers
+fn set(table: &mut toml::Table, key: &str, value: &str) -> Result<(), String> {
+    let (section, key) = key.split_once('.').unwrap_or(("", key));
+    let value = toml::from_str::<toml::Table>(&format!("v = {}", value)).ok()
+        .and_then(|mut t| t.remove("v"))
+        .filter(|v| v.is_integer() || v.is_float())
+        .unwrap_or(toml::Value::String(value.to_string()));
+    table.entry(section).or_insert(toml::Value::Table(Default::default()))
+        .as_table_mut()
+        .ok_or(format!("{} is not a section", section))?
+        .insert(key.to_string(), value);
     Ok(())
 }
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Make loaded config available to all components, once at startup
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Loaded config, panics when `init` wasn't called
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("config::init must be called at startup")
+}
+
+fn legacy_radio_path() -> &'static Path {
+    &get().radio.legacy_file
+}
+
+/// Load stream urls from the legacy `~/.librecast.radio` json file.
+/// Returns `None` if the file doesn't exist (never created or already imported)
+pub fn load_legacy_radio() -> Result<Option<Vec<String>>, std::io::Error> {
+    let path = legacy_radio_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let config = std::fs::read_to_string(path)?;
+    let streams_collection: Vec<String> = serde_json::from_str(&config)?;
+    Ok(Some(streams_collection))
+}
+
+/// Rename the legacy radio file so it is imported only once
+pub fn mark_legacy_radio_imported() -> Result<(), std::io::Error> {
+    let path = legacy_radio_path();
+    std::fs::rename(path, path.with_extension("radio.imported"))
+}
+
+/// Sqlite database, `~/.librecast.db` by default
+pub fn database_url() -> String {
+    get().database.url.clone()
+}
+
+/// Base url of Radio Browser compatible directory (e.g. a local server)
+pub fn radio_directory_url() -> String {
+    get().radio.directory_url.clone()
+}
+
+/// Directory where radio recordings are written
+pub fn recordings_dir() -> PathBuf {
+    get().radio.recordings_dir.clone()
+}
+
+/// Reconnect attempts for a dropped radio stream before giving up
+pub fn reconnect_attempts() -> u32 {
+    get().radio.reconnect_attempts
+}
+
+/// Unix socket of the control API
+pub fn control_socket_path() -> PathBuf {
+    get().control.socket.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// `Config::load` reads environment variables which tests change
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    /// Config file with `content` in the temp dir
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("librecast-config-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Removes the environment overrides, so a developer's own settings don't leak into tests
+    fn clear_environment() {
+        for env in OVERRIDES.iter().filter_map(|(_, env, _)| *env) {
+            std::env::remove_var(env);
+        }
+    }
+
+    #[test]
+    fn flags_end_at_the_command() {
+        let (path, flags, rest) = parse_flags(&args(&["--config", "my.toml", "--tick-rate", "100", "--seek-forward=5", "add", "--tick-rate", "7"])).unwrap();
+        assert_eq!(path, Some(PathBuf::from("my.toml")));
+        assert_eq!(flags, vec![("--tick-rate".to_string(), "100".to_string()), ("--seek-forward".to_string(), "5".to_string())]);
+        assert_eq!(rest, args(&["add", "--tick-rate", "7"]));
+
+        let error = parse_flags(&args(&["--tick-rate"])).unwrap_err().to_string();
+        assert!(error.starts_with("--tick-rate needs a value\n"), "{}", error);
+    }
+
+    #[test]
+    fn set_keeps_numbers() {
+        let mut table = toml::Table::new();
+        set(&mut table, "ui.tick_rate_ms", "100").unwrap();
+        set(&mut table, "player.seek_forward_seconds", "2.5").unwrap();
+        set(&mut table, "theme.name", "light").unwrap();
+        assert_eq!(table["ui"]["tick_rate_ms"], toml::Value::Integer(100));
+        assert_eq!(table["player"]["seek_forward_seconds"], toml::Value::Float(2.5));
+        assert_eq!(table["theme"]["name"], toml::Value::String("light".to_string()));
+
+        let mut table: toml::Table = toml::from_str("ui = 1").unwrap();
+        assert_eq!(set(&mut table, "ui.tick_rate_ms", "100").unwrap_err(), "ui is not a section");
+    }
+
+    #[test]
+    fn flags_override_environment_and_file() {
+        let _env = ENV.lock().unwrap();
+        let path = config_file("precedence", "[radio]\nreconnect_attempts = 3\n[ui]\ntick_rate_ms = 100\n");
+        let load = |flags: &[&str]| {
+            let mut all = args(&["--config", path.to_str().unwrap()]);
+            all.extend(args(flags));
+            Config::load(&all).unwrap().0
+        };
+
+        clear_environment();
+        let config = load(&[]);
+        assert_eq!((config.radio.reconnect_attempts, config.ui.tick_rate_ms), (3, 100));
+        std::env::set_var("LIBRECAST_RECONNECT_ATTEMPTS", "4");
+        assert_eq!(load(&[]).radio.reconnect_attempts, 4);
+        let config = load(&["--reconnect-attempts", "6", "--tick-rate=50"]);
+        assert_eq!((config.radio.reconnect_attempts, config.ui.tick_rate_ms), (6, 50));
+
+        std::env::set_var("LIBRECAST_RECONNECT_ATTEMPTS", "many");
+        let error = Config::load(&args(&["--config", path.to_str().unwrap()])).unwrap_err().to_string();
+        assert!(error.starts_with("invalid value \"many\" of $LIBRECAST_RECONNECT_ATTEMPTS: "), "{}", error);
+        std::env::remove_var("LIBRECAST_RECONNECT_ATTEMPTS");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_values_name_their_source() {
+        let _env = ENV.lock().unwrap();
+        clear_environment();
+        let path = config_file("valid", "[ui]\ntick_rate_ms = 100\n");
+        let error = Config::load(&args(&["--config", path.to_str().unwrap(), "--tick-rate", "fast"])).unwrap_err().to_string();
+        assert!(error.starts_with("invalid value \"fast\" of --tick-rate: "), "{}", error);
+        std::fs::remove_file(path).unwrap();
+
+        let path = config_file("invalid", "[ui]\ntick_rate_ms = \"fast\"\n");
+        let error = Config::load(&args(&["--config", path.to_str().unwrap()])).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("invalid config file {}: ", path.display())), "{}", error);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn validate_lists_all_problems() {
+        let mut config = Config::default();
+        assert!(config.validate().is_ok());
+
+        config.database.url = " ".to_string();
+        config.radio.directory_url = "ftp://radio".to_string();
+        config.ui.tick_rate_ms = 0;
+        config.player.seek_forward_seconds = f64::NAN;
+        config.player.seek_backward_seconds = -1.0;
+        config.control.socket = PathBuf::new();
+        assert_eq!(config.validate().unwrap_err().to_string(), "invalid configuration:
+  [database] url must not be empty
+  [radio] directory_url must be a http(s) url, not \"ftp://radio\"
+  [ui] tick_rate_ms must be greater than 0
+  [player] seek_forward_seconds must be greater than 0
+  [player] seek_backward_seconds must be greater than 0
+  [control] socket must not be empty");
+    }
+}
@@ -0,0 +1,294 @@
+use std::{error::Error, fs::Permissions, io::{BufRead, BufReader, ErrorKind, Write}, os::unix::{fs::PermissionsExt, net::UnixStream}, path::PathBuf, time::Duration};
+
+use serde_json::{json, Value};
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader, Lines}, net::{unix::{OwnedReadHalf, OwnedWriteHalf}, UnixListener}, sync::{mpsc::UnboundedSender, oneshot}};
+use url2audio::player_engine::Playing;
+
+use crate::{config, playback::{Playback, PlaybackSource, PlaybackStatus, PlayerCommand}, AsyncAction};
+
+/// How often status is compared for subscribed clients
+const SUBSCRIBE_INTERVAL: Duration = Duration::from_millis(500);
+const USAGE: &str = "usage: librecast ctl play|pause|toggle|stop|next|previous|status|subscribe|refresh|seek <[+|-]seconds>";
+
+/// Control API on a Unix socket, for scripts and window manager key bindings.
+///
+/// Clients send one JSON object per line, e.g. `{"cmd": "seek", "offset": -10}`,
+/// and get one JSON object per line back, `{"ok": true, ...}` or `{"ok": false, "error": "..."}`.
+/// Commands: `play`, `pause`, `toggle`, `stop`, `next`, `previous`,
+/// `seek` (`offset` or `position` in seconds), `status`, `refresh` (reload data
+/// changed outside of the app) and `subscribe` (status is sent on every change
+/// until the client disconnects).
+///
+/// Player commands go through `AsyncAction` like key presses do, the response is sent
+/// once the app executed them, with the status afterwards, or the reason it could not.
+/// The socket is only accessible by the user and removed when the server is dropped
+pub struct ControlServer {
+    path: PathBuf,
+}
+
+impl ControlServer {
+    pub fn start(path: PathBuf, playback: Playback, tx: UnboundedSender<AsyncAction>) -> std::io::Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(std::io::Error::new(ErrorKind::AddrInUse, format!("{} is used by another librecast", path.display())));
+            }
+            // left over from a crashed instance
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        // without XDG_RUNTIME_DIR the socket is in the home directory
+        std::fs::set_permissions(&path, Permissions::from_mode(0o600))?;
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let playback = playback.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    let _ = handle_client(AsyncBufReader::new(reader).lines(), writer, playback, tx).await;
+                });
+            }
+        });
+
+        Ok(ControlServer { path })
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn handle_client(mut lines: Lines<AsyncBufReader<OwnedReadHalf>>, mut writer: OwnedWriteHalf, playback: Playback, tx: UnboundedSender<AsyncAction>) -> std::io::Result<()> {
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(r) => r,
+            Err(e) => {
+                write_line(&mut writer, &json!({"ok": false, "error": format!("invalid json: {}", e)})).await?;
+                continue;
+            },
+        };
+
+        let cmd = request["cmd"].as_str().unwrap_or_default();
+        if cmd == "subscribe" {
+            write_line(&mut writer, &json!({"ok": true})).await?;
+            return subscribe(lines, writer, playback).await;
+        }
+        let response = match dispatch(cmd, &request, &playback, &tx).await {
+            Ok(r) => r,
+            Err(e) => json!({"ok": false, "error": e}),
+        };
+        write_line(&mut writer, &response).await?;
+    }
+    Ok(())
+}
+
+async fn dispatch(cmd: &str, request: &Value, playback: &Playback, tx: &UnboundedSender<AsyncAction>) -> Result<Value, String> {
+    let command = match cmd {
+        "status" => return Ok(json!({"ok": true, "status": status_json(&playback.status())})),
+        "refresh" => {
+            tx.send(AsyncAction::Refresh).map_err(|e| e.to_string())?;
+            return Ok(json!({"ok": true}));
+        },
+        "play" => PlayerCommand::Play,
+        "pause" => PlayerCommand::Pause,
+        "toggle" => PlayerCommand::PlayPause,
+        "stop" => PlayerCommand::Stop,
+        "next" => PlayerCommand::Next,
+        "previous" => PlayerCommand::Previous,
+        "seek" => match (request["offset"].as_f64(), request["position"].as_f64()) {
+            (Some(offset), _) => PlayerCommand::Seek(offset),
+            (None, Some(position)) => PlayerCommand::SetPosition(position),
+            (None, None) => return Err("seek needs offset or position in seconds".to_string()),
+        },
+        "" => return Err("missing cmd".to_string()),
+        other => return Err(format!("unknown command: {}", other)),
+    };
+    let (reply, result) = oneshot::channel();
+    tx.send(AsyncAction::PlayerCommand(command, Some(reply))).map_err(|e| e.to_string())?;
+    result.await.map_err(|_| "librecast is shutting down".to_string())??;
+    Ok(json!({"ok": true, "status": status_json(&playback.status())}))
+}
+
+/// Send status whenever something else than the position changed
+async fn subscribe(mut lines: Lines<AsyncBufReader<OwnedReadHalf>>, mut writer: OwnedWriteHalf, playback: Playback) -> std::io::Result<()> {
+    let mut last: Option<Value> = None;
+    loop {
+        let status = status_json(&playback.status());
+        let mut compared = status.clone();
+        compared["position"] = Value::Null;
+        if last.as_ref() != Some(&compared) {
+            write_line(&mut writer, &json!({"event": "status", "status": status})).await?;
+            last = Some(compared);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(SUBSCRIBE_INTERVAL) => {},
+            line = lines.next_line() => {
+                // client disconnected
+                if !matches!(line, Ok(Some(_))) {
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
+
+async fn write_line(writer: &mut OwnedWriteHalf, value: &Value) -> std::io::Result<()> {
+    writer.write_all(format!("{}\n", value).as_bytes()).await
+}
+
+pub fn status_json(status: &PlaybackStatus) -> Value {
+    let state = match status.playing {
+        Playing::Playing => "playing",
+        Playing::Paused => "paused",
+        Playing::Finished => "stopped",
+    };
+    let mut json = json!({
+        "state": state,
+        "source": null,
+        "url": status.url,
+        "stream_title": status.stream_title,
+        "position": status.position,
+        "duration": status.duration,
+        "volume": status.volume,
+    });
+    match status.source.as_ref() {
+        Some(PlaybackSource::Radio { station_id, name }) => {
+            json["source"] = json!("radio");
+            json["title"] = json!(name);
+            json["station_id"] = json!(station_id);
+        },
+        Some(PlaybackSource::Podcast(item)) => {
+            json["source"] = json!("podcast");
+            json["title"] = json!(item.title);
+            json["channel_id"] = json!(item.channel_id);
+            json["episode_key"] = json!(item.episode_key);
+        },
+        None => {},
+    }
+    json
+}
+
+/// `librecast ctl <command> [seconds]` client, prints responses as received.
+/// Returns false when librecast reported an error
+pub fn ctl(args: &[String]) -> Result<bool, Box<dyn Error>> {
+    let request = request_from_args(args)?;
+    let path = config::control_socket_path();
+    let mut stream = UnixStream::connect(&path).map_err(|e| format!("librecast is not running ({}): {}", path.display(), e))?;
+    writeln!(stream, "{}", request)?;
+
+    let subscribe = request["cmd"] == "subscribe";
+    let mut ok = true;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        println!("{}", line);
+        let response: Value = serde_json::from_str(&line)?;
+        if response["ok"] == false {
+            ok = false;
+        }
+        if !subscribe {
+            break;
+        }
+    }
+    Ok(ok)
+}
+
+/// `seek +30` / `seek -10` is relative, `seek 120` absolute
+fn request_from_args(args: &[String]) -> Result<Value, String> {
+    let cmd = args.first().ok_or(USAGE)?;
+    match cmd.as_str() {
+        "seek" => {
+            let arg = args.get(1).ok_or(USAGE)?;
+            let seconds: f64 = arg.parse().map_err(|_| format!("invalid seconds: {}", arg))?;
+            if arg.starts_with(['+', '-']) {
+                Ok(json!({"cmd": "seek", "offset": seconds}))
+            } else {
+                Ok(json!({"cmd": "seek", "position": seconds}))
+            }
+        },
+        "-h" | "--help" | "help" => Err(USAGE.to_string()),
+        _ => Ok(json!({"cmd": cmd})),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn seek_with_sign_is_an_offset() {
+        assert_eq!(request_from_args(&args(&["seek", "+30"])).unwrap(), json!({"cmd": "seek", "offset": 30.0}));
+        assert_eq!(request_from_args(&args(&["seek", "-10"])).unwrap(), json!({"cmd": "seek", "offset": -10.0}));
+        assert_eq!(request_from_args(&args(&["seek", "120"])).unwrap(), json!({"cmd": "seek", "position": 120.0}));
+    }
+
+    #[test]
+    fn invalid_args_are_rejected() {
+        assert_eq!(request_from_args(&args(&["seek", "abc"])).unwrap_err(), "invalid seconds: abc");
+        assert_eq!(request_from_args(&args(&["seek"])).unwrap_err(), USAGE);
+        assert_eq!(request_from_args(&args(&[])).unwrap_err(), USAGE);
+        assert_eq!(request_from_args(&args(&["--help"])).unwrap_err(), USAGE);
+        assert_eq!(request_from_args(&args(&["next"])).unwrap(), json!({"cmd": "next"}));
+    }
+
+    fn playback() -> (Playback, UnboundedSender<AsyncAction>, mpsc::UnboundedReceiver<AsyncAction>) {
+        config::init(Default::default());
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Playback::new(tx.clone()), tx, rx)
+    }
+
+    #[tokio::test]
+    async fn requests_without_player_command() {
+        let (playback, tx, mut rx) = playback();
+        let status = dispatch("status", &json!({"cmd": "status"}), &playback, &tx).await.unwrap();
+        assert_eq!(status["status"]["state"], "stopped");
+        assert_eq!(dispatch("", &json!({}), &playback, &tx).await.unwrap_err(), "missing cmd");
+        assert_eq!(dispatch("rewind", &json!({"cmd": "rewind"}), &playback, &tx).await.unwrap_err(), "unknown command: rewind");
+        assert_eq!(dispatch("seek", &json!({"cmd": "seek"}), &playback, &tx).await.unwrap_err(), "seek needs offset or position in seconds");
+
+        assert_eq!(dispatch("refresh", &json!({"cmd": "refresh"}), &playback, &tx).await.unwrap(), json!({"ok": true}));
+        assert!(matches!(rx.try_recv(), Ok(AsyncAction::Refresh)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn player_command_waits_for_the_result() {
+        let (playback, tx, mut rx) = playback();
+        playback.begin(PlaybackSource::Radio { station_id: Some(3), name: "Test FM".to_string() });
+        let app = tokio::spawn(async move {
+            let mut commands = vec![];
+            for _ in 0..2 {
+                let Some(AsyncAction::PlayerCommand(command, Some(reply))) = rx.recv().await else {
+                    panic!("expected a player command with reply");
+                };
+                let result = match command {
+                    PlayerCommand::Seek(_) => Err("seek is only supported for podcasts".to_string()),
+                    _ => Ok(()),
+                };
+                commands.push(command);
+                reply.send(result).unwrap();
+            }
+            commands
+        });
+
+        let response = dispatch("next", &json!({"cmd": "next"}), &playback, &tx).await.unwrap();
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["status"]["title"], "Test FM");
+        let error = dispatch("seek", &json!({"cmd": "seek", "offset": 10}), &playback, &tx).await.unwrap_err();
+        assert_eq!(error, "seek is only supported for podcasts");
+
+        let commands = app.await.unwrap();
+        assert!(matches!(commands[..], [PlayerCommand::Next, PlayerCommand::Seek(offset)] if offset == 10.0));
+    }
+}
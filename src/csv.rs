@@ -0,0 +1,8 @@
+/// Quote a CSV field when it contains separators or quotes
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, QueryOrder};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use crate::data_layer::data_provider::ChannelItemToListeningState;
+use crate::entity::{bookmark, channel, channel_item};
+use crate::entity::bookmark::Entity as BookmarkEntity;
+use crate::entity::bookmark::ActiveModel as BookmarkModel;
+use crate::ui_models;
+
+pub struct BookmarkDataLayer {}
+
+impl BookmarkDataLayer {
+    pub async fn add_bookmark(db: DatabaseConnection, episode_key: String, enclosure_url: String, channel_id: i32, time: f32, note: Option<String>) -> Result<i32, DbErr> {
+        let model = BookmarkModel {
+            id: ActiveValue::NotSet,
+            channel_id: ActiveValue::set(channel_id),
+            channel_item_enclosure: ActiveValue::set(enclosure_url),
+            episode_key: ActiveValue::set(episode_key),
+            time: ActiveValue::set(time),
+            note: ActiveValue::set(note),
+            created_at: ActiveValue::set(Local::now().fixed_offset()),
+        };
+
+        let res = BookmarkEntity::insert(model).exec(&db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    pub async fn delete_bookmark(db: DatabaseConnection, bookmark_id: i32) -> Result<(), DbErr> {
+        BookmarkEntity::delete_by_id(bookmark_id).exec(&db).await?;
+        Ok(())
+    }
+
+    /// Get all bookmarks ordered by episode and time inside episode
+    pub async fn get_bookmarks(db: &DatabaseConnection) -> Result<Vec<ui_models::Bookmark>, DbErr> {
+        let bookmarks = BookmarkEntity::find()
+            .order_by_asc(bookmark::Column::ChannelId)
+            .order_by_asc(bookmark::Column::EpisodeKey)
+            .order_by_asc(bookmark::Column::Time)
+            .all(db).await?;
+
+        let keys: Vec<String> = bookmarks.iter().map(|b| b.episode_key.clone()).collect();
+        let items: HashMap<(i32, String), ui_models::ChannelItem> = channel_item::Entity::find()
+            .filter(channel_item::Column::EpisodeKey.is_in(keys))
+            .find_also_linked(ChannelItemToListeningState)
+            .all(db).await?
+            .iter()
+            .map(|i| ((i.0.channel_id, i.0.episode_key.clone()), i.into()))
+            .collect();
+
+        let channels: HashMap<i32, Option<String>> = channel::Entity::find()
+            .all(db).await?
+            .into_iter()
+            .map(|c| (c.id, c.title))
+            .collect();
+
+        let to_ret = bookmarks.into_iter().map(|b| {
+            ui_models::Bookmark {
+                id: b.id,
+                channel_id: b.channel_id,
+                channel_title: channels.get(&b.channel_id).cloned().flatten(),
+                item: items.get(&(b.channel_id, b.episode_key.clone())).cloned(),
+                enclosure: b.channel_item_enclosure,
+                episode_key: b.episode_key,
+                time: b.time,
+                note: b.note,
+                created_at: b.created_at,
+            }
+        }).collect();
+
+        Ok(to_ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    /// Database with channel 1 holding episodes `guid:1:<title>`
+    async fn memory_db(titles: &[&str]) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        channel::Entity::insert(channel::ActiveModel {
+            id: ActiveValue::set(1),
+            title: ActiveValue::set(Some("Daily News".to_string())),
+            link: ActiveValue::set(None),
+            description: ActiveValue::set(None),
+        }).exec(&db).await.unwrap();
+        for (ordering, title) in titles.iter().enumerate() {
+            channel_item::Entity::insert(channel_item::ActiveModel {
+                ordering: ActiveValue::set(ordering as i32),
+                channel_id: ActiveValue::set(1),
+                title: ActiveValue::set(Some(title.to_string())),
+                link: ActiveValue::set(None),
+                source: ActiveValue::set(None),
+                enclosure: ActiveValue::set(enclosure(title)),
+                description: ActiveValue::set(None),
+                guid: ActiveValue::set(Some(title.to_string())),
+                pub_date: ActiveValue::set(None),
+                episode_key: ActiveValue::set(key(title)),
+            }).exec(&db).await.unwrap();
+        }
+        db
+    }
+
+    fn key(title: &str) -> String {
+        format!("guid:1:{}", title)
+    }
+
+    fn enclosure(title: &str) -> String {
+        format!("http://news.example.com/{}.mp3", title)
+    }
+
+    async fn add(db: &DatabaseConnection, title: &str, time: f32, note: Option<&str>) -> i32 {
+        BookmarkDataLayer::add_bookmark(db.clone(), key(title), enclosure(title), 1, time, note.map(str::to_string)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn bookmarks_are_grouped_by_episode_and_ordered_by_time() {
+        let db = memory_db(&["mon", "tue"]).await;
+        add(&db, "tue", 30.0, None).await;
+        let late = add(&db, "mon", 600.0, Some("weather")).await;
+        add(&db, "mon", 5.5, Some("intro")).await;
+        add(&db, "tue", 10.0, None).await;
+
+        let bookmarks = BookmarkDataLayer::get_bookmarks(&db).await.unwrap();
+        let listed: Vec<(String, f32, Option<String>)> = bookmarks.iter()
+            .map(|b| (b.item.as_ref().and_then(|i| i.title.clone()).unwrap(), b.time, b.note.clone()))
+            .collect();
+        assert_eq!(listed, vec![
+            ("mon".to_string(), 5.5, Some("intro".to_string())),
+            ("mon".to_string(), 600.0, Some("weather".to_string())),
+            ("tue".to_string(), 10.0, None),
+            ("tue".to_string(), 30.0, None),
+        ]);
+        assert!(bookmarks.iter().all(|b| b.channel_title.as_deref() == Some("Daily News")));
+        assert_eq!(bookmarks[1].id, late);
+        assert_eq!(bookmarks[1].enclosure, enclosure("mon"));
+
+        BookmarkDataLayer::delete_bookmark(db.clone(), late).await.unwrap();
+        let times: Vec<f32> = BookmarkDataLayer::get_bookmarks(&db).await.unwrap().iter().map(|b| b.time).collect();
+        assert_eq!(times, vec![5.5, 10.0, 30.0]);
+    }
+
+    #[tokio::test]
+    async fn bookmarks_outlive_their_episode_in_the_feed() {
+        let db = memory_db(&[]).await;
+        add(&db, "gone", 42.0, None).await;
+        let bookmarks = BookmarkDataLayer::get_bookmarks(&db).await.unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].item, None);
+        assert_eq!(bookmarks[0].episode_key, key("gone"));
+    }
+}
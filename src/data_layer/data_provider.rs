@@ -3,78 +3,122 @@ use crate::podcasts_model::PodcastsModel;
 use crate::ui_models;
 use chrono::FixedOffset;
 use regex::Regex;
-use sea_orm::{ActiveValue, DatabaseConnection, DbErr, QueryOrder};
+use rss::Channel;
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, QueryOrder, TransactionTrait};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, RelationDef, Linked};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::io::ErrorKind;
 
 pub struct DataProvider {}
 
+/// Feed written to the db by `fetch_data`
+pub struct FetchedChannel {
+    pub channel_id: i32,
+    /// `title: error` of items which could not be stored
+    pub failed_items: Vec<String>,
+}
+
 impl DataProvider {
     /// Fetch data from provided url,
     /// and write data in db
-    pub async fn fetch_data(podcast_url: String, selected_channel_id: i32, db: DatabaseConnection) -> Result<i32, Box<dyn Error>> {
-        match PodcastsModel::get_channel_from_url(&podcast_url).await.map_err(|_| std::io::Error::new(ErrorKind::Other, "")) {
-            Ok(channel) => {
-                use entity::channel::{ Entity, ActiveModel };
-                let am: ActiveModel = ActiveModel {
-                    title: ActiveValue::set(Some(channel.title().to_string())),
-                    link: ActiveValue::set(Some(podcast_url.to_string())),
-                    description: ActiveValue::set(Some(channel.description().to_string())),
-                    id: ActiveValue::set(selected_channel_id) // ActiveValue::NotSet
-                };
-
-                let exist = Entity::find().filter(entity::channel::Column::Link.eq(channel.link())).one(&db).await.unwrap();
-
-                let channel_id = if let Some(exist) = exist {
-                    exist.id
-                } else {
-                    let res = Entity::update(am).exec(&db).await.unwrap();
-                    res.id
-                };
-
-                let mut order = 0;
-                let items: Vec<_> = channel.items().iter().map(|i| {
-                    order = order + 1;
-                    let d: Option<chrono::DateTime<FixedOffset>> = match chrono::DateTime::parse_from_rfc2822(i.pub_date().unwrap_or(Default::default())) {
-                        Ok(s) => Some(s),
-                        Err(_) => None,
-                    };
-                    entity::channel_item::ActiveModel {
-                        ordering: ActiveValue::set(order),
-                        channel_id: ActiveValue::set(channel_id),
-                        title: ActiveValue::set(i.title().map(|t| t.to_string())),
-                        // link: ActiveValue::set(i.link().map(|l| l.to_string())),
-                        link: ActiveValue::set(Some(podcast_url.to_string())), // atom:link
-                        source: ActiveValue::set(i.source().map(|s| s.url.to_string())),
-                        enclosure: ActiveValue::set(i.enclosure().map(|e| e.url.to_string()).unwrap()),
-                        description: ActiveValue::set(i.description().map(|d| d.to_string())),
-                        guid: ActiveValue::set(i.guid().map(|g| g.value.clone())),
-                        pub_date: ActiveValue::set(d)
-                    }
-                }).collect();
+    pub async fn fetch_data(podcast_url: String, selected_channel_id: i32, db: DatabaseConnection) -> Result<FetchedChannel, Box<dyn Error>> {
+        let channel = Self::fetch_channel(&podcast_url).await?;
+        Self::store_channel(channel, podcast_url, Some(selected_channel_id), db).await
+    }
 
+    async fn fetch_channel(podcast_url: &str) -> Result<Channel, std::io::Error> {
+        PodcastsModel::get_channel_from_url(podcast_url).await.map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string()))
+    }
 
-                let r = entity::channel_item::Entity::delete_many().filter(entity::channel_item::Column::ChannelId.eq(channel_id)).exec(&db).await;
-                match r {
-                    Ok(_) => { },
-                    Err(e) => return Err(Box::new(e)),
-                }
+    /// Write fetched `channel` and its items in db, as channel `selected_channel_id`
+    /// or a new one when `None`
+    async fn store_channel(channel: Channel, podcast_url: String, selected_channel_id: Option<i32>, db: DatabaseConnection) -> Result<FetchedChannel, Box<dyn Error>> {
+        use entity::channel::{ Entity, ActiveModel };
+        let am: ActiveModel = ActiveModel {
+            title: ActiveValue::set(Some(channel.title().to_string())),
+            link: ActiveValue::set(Some(podcast_url.to_string())),
+            description: ActiveValue::set(Some(channel.description().to_string())),
+            id: selected_channel_id.map_or(ActiveValue::NotSet, ActiveValue::set),
+        };
 
-                for c in items.chunks(500) {
-                    let r = entity::channel_item::Entity::insert_many(c.to_vec()).exec(&db).await;
-                    if r.is_err() {
-                        return Err(Box::new(r.unwrap_err()));
+        let exist = Entity::find().filter(entity::channel::Column::Link.eq(channel.link())).one(&db).await?;
+
+        let channel_id = match (exist, selected_channel_id) {
+            (Some(exist), _) => exist.id,
+            (None, Some(_)) => Entity::update(am).exec(&db).await?.id,
+            (None, None) => Entity::insert(am).exec(&db).await?.last_insert_id,
+        };
+
+        let guids: Vec<String> = channel.items().iter()
+            .filter_map(|i| i.guid().map(|g| g.value.clone()))
+            .filter(|g| !g.trim().is_empty())
+            .collect();
+        let known_keys = GuidKeys::load(&db, channel_id, guids).await?;
+
+        // items without enclosure can't be played, and an enclosure
+        // repeated inside one feed is kept only once per channel
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut order = 0;
+        let items: Vec<_> = channel.items().iter().filter_map(|i| {
+            let enclosure = i.enclosure().map(|e| e.url.trim().to_string()).filter(|e| !e.is_empty())?;
+            if !seen.insert(enclosure.clone()) {
+                return None;
+            }
+            order = order + 1;
+            let d: Option<chrono::DateTime<FixedOffset>> = match chrono::DateTime::parse_from_rfc2822(i.pub_date().unwrap_or(Default::default())) {
+                Ok(s) => Some(s),
+                Err(_) => None,
+            };
+            let guid = i.guid().map(|g| g.value.clone());
+            Some(entity::channel_item::ActiveModel {
+                ordering: ActiveValue::set(order),
+                channel_id: ActiveValue::set(channel_id),
+                title: ActiveValue::set(i.title().map(|t| t.to_string())),
+                // link: ActiveValue::set(i.link().map(|l| l.to_string())),
+                link: ActiveValue::set(Some(podcast_url.to_string())), // atom:link
+                source: ActiveValue::set(i.source().map(|s| s.url.to_string())),
+                episode_key: ActiveValue::set(known_keys.key(channel_id, guid.as_deref(), &enclosure)),
+                enclosure: ActiveValue::set(enclosure),
+                description: ActiveValue::set(i.description().map(|d| d.to_string())),
+                guid: ActiveValue::set(guid),
+                pub_date: ActiveValue::set(d)
+            })
+        }).collect();
+
+        let mut failed_items = vec![];
+        let txn = db.begin().await?;
+
+        entity::channel_item::Entity::delete_many().filter(entity::channel_item::Column::ChannelId.eq(channel_id)).exec(&txn).await?;
+
+        for c in items.chunks(500) {
+            let r = entity::channel_item::Entity::insert_many(c.to_vec()).exec(&txn).await;
+            if r.is_err() {
+                // insert one by one so a single bad item doesn't drop the whole chunk
+                for item in c {
+                    if let Err(e) = entity::channel_item::Entity::insert(item.clone()).exec(&txn).await {
+                        let title = match (&item.title, &item.enclosure) {
+                            (ActiveValue::Set(Some(title)), _) => title.clone(),
+                            (_, ActiveValue::Set(enclosure)) => enclosure.clone(),
+                            _ => String::new(),
+                        };
+                        failed_items.push(format!("{}: {}", title, e));
                     }
                 }
-
-                Ok(channel_id)
-            },
-            Err(e) => {
-                // handle error opening channel
-                Err(Box::new(e))
             }
         }
+
+        txn.commit().await?;
+
+        Ok(FetchedChannel { channel_id, failed_items })
+    }
+
+    /// Subscribe to feed at `podcast_url` and store its items,
+    /// the channel is only added once the feed was fetched
+    pub async fn add_channel(podcast_url: String, db: DatabaseConnection) -> Result<i32, Box<dyn Error>> {
+        let channel = Self::fetch_channel(&podcast_url).await?;
+        let FetchedChannel { channel_id, .. } = Self::store_channel(channel, podcast_url, None, db).await?;
+        Ok(channel_id)
     }
 
     /// Get all podcast items from channel with id `channel_id`
@@ -94,6 +138,54 @@ impl DataProvider {
 
         Ok(to_ret)
     }
+
+    /// Get podcast items of all channels in one query, newest first within a channel
+    pub async fn get_all_items_from_db(db: &DatabaseConnection) -> Result<Vec<ui_models::ChannelItem>, DbErr> {
+        let items = entity::channel_item::Entity::find()
+            .order_by_desc(channel_item::Column::PubDate)
+            .order_by_asc(channel_item::Column::Ordering)
+            .find_also_linked(ChannelItemToListeningState)
+            .all(db).await?;
+
+        Ok(items.iter().map(|i| i.into()).collect())
+    }
+}
+
+/// Keys of stored items with GUIDs, so a GUID keeps its key when the enclosure changes
+/// and an episode shared by feeds (same GUID and enclosure) gets one key
+struct GuidKeys {
+    /// of the channel being stored, by GUID
+    own: HashMap<String, String>,
+    /// of other channels, by GUID and normalized enclosure
+    shared: HashMap<(String, String), String>,
+}
+
+impl GuidKeys {
+    async fn load(db: &DatabaseConnection, channel_id: i32, guids: Vec<String>) -> Result<Self, DbErr> {
+        let mut keys = GuidKeys { own: HashMap::new(), shared: HashMap::new() };
+        let items = entity::channel_item::Entity::find()
+            .filter(channel_item::Column::Guid.is_in(guids))
+            .filter(channel_item::Column::EpisodeKey.starts_with("guid:"))
+            .order_by_asc(channel_item::Column::ChannelId)
+            .all(db).await?;
+        for item in items {
+            let guid = item.guid.as_deref().unwrap_or_default().trim().to_string();
+            if item.channel_id == channel_id {
+                keys.own.entry(guid).or_insert(item.episode_key);
+            } else {
+                keys.shared.entry((guid, ui_models::normalize_enclosure(&item.enclosure))).or_insert(item.episode_key);
+            }
+        }
+        Ok(keys)
+    }
+
+    fn key(&self, channel_id: i32, guid: Option<&str>, enclosure: &str) -> String {
+        guid.map(str::trim)
+            .filter(|g| !g.is_empty())
+            .and_then(|g| self.own.get(g).or_else(|| self.shared.get(&(g.to_string(), ui_models::normalize_enclosure(enclosure)))))
+            .cloned()
+            .unwrap_or_else(|| ui_models::episode_key(channel_id, guid, enclosure))
+    }
 }
 
 pub struct ChannelItemToListeningState;
@@ -105,8 +197,8 @@ impl Linked for ChannelItemToListeningState {
     fn link(&self) -> Vec<RelationDef> {
         vec![
             channel_item::Entity::belongs_to(listening_state::Entity)
-                .from(channel_item::Column::Enclosure)
-                .to(listening_state::Column::ChannelItemEnclosure)
+                .from(channel_item::Column::EpisodeKey)
+                .to(listening_state::Column::EpisodeKey)
                 .into(),
         ]
     }
@@ -132,7 +224,8 @@ impl From<&(entity::channel_item::Model, Option<listening_state::Model>)> for ui
             link: i.link.clone(),
             description: i.description.as_ref().map(|d| rg.replace_all(d, "\n").to_string()).clone(),
             enclosure: i.enclosure.to_string(),
-            guid: None,
+            guid: i.guid.clone(),
+            episode_key: i.episode_key.clone(),
             pub_date: i.pub_date.clone(),
             source: i.source,
             ordering: i.ordering,
@@ -178,3 +271,109 @@ impl From<&(entity::channel_item::Model, Option<listening_state::Model>)> for ui
 //
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ConnectionTrait, Database, Statement};
+
+    use super::*;
+    use crate::data_layer::listening_state_data_layer::ListeningStateDataLayer;
+
+    async fn memory_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        db
+    }
+
+    /// Feed with `(guid, enclosure)` items
+    fn feed(link: &str, items: &[(&str, &str)]) -> Channel {
+        let items: String = items.iter()
+            .map(|(guid, enclosure)| format!("<item><title>{0}</title><guid>{0}</guid><enclosure url=\"{1}\" length=\"1\" type=\"audio/mpeg\"/></item>", guid, enclosure.replace('&', "&amp;")))
+            .collect();
+        let xml = format!("<rss version=\"2.0\"><channel><title>{0}</title><link>{0}</link><description></description>{1}</channel></rss>", link, items);
+        Channel::read_from(xml.as_bytes()).unwrap()
+    }
+
+    async fn store(db: &DatabaseConnection, link: &str, channel_id: Option<i32>, items: &[(&str, &str)]) -> i32 {
+        DataProvider::store_channel(feed(link, items), format!("{}/feed", link), channel_id, db.clone()).await.unwrap().channel_id
+    }
+
+    async fn first_item(db: &DatabaseConnection, channel_id: i32) -> ui_models::ChannelItem {
+        DataProvider::get_items_from_db(channel_id, db).await.unwrap().remove(0)
+    }
+
+    #[tokio::test]
+    async fn feeds_reusing_a_guid_keep_their_own_progress() {
+        let db = memory_db().await;
+        let a = store(&db, "http://a.example.com", None, &[("1", "http://a.example.com/1.mp3")]).await;
+        let b = store(&db, "http://b.example.com", None, &[("1", "http://b.example.com/1.mp3")]).await;
+        let (item_a, item_b) = (first_item(&db, a).await, first_item(&db, b).await);
+        assert_ne!(item_a.episode_key, item_b.episode_key);
+
+        ListeningStateDataLayer::update_current_time_for_item(db.clone(), item_a.episode_key, item_a.enclosure, a, 42.0).await.unwrap();
+        ListeningStateDataLayer::update_current_time_for_item(db.clone(), item_b.episode_key, item_b.enclosure, b, 7.0).await.unwrap();
+
+        assert_eq!(first_item(&db, a).await.listening_state.map(|s| s.time), Some(42.0));
+        assert_eq!(first_item(&db, b).await.listening_state.map(|s| s.time), Some(7.0));
+    }
+
+    #[tokio::test]
+    async fn shared_episode_and_moved_enclosure_keep_their_key() {
+        let db = memory_db().await;
+        let show = store(&db, "http://show.example.com", None, &[("ep-1", "https://cdn.example.com/ep1.mp3")]).await;
+        // same episode in a network feed, the enclosure differs by tracking parameters only
+        let network = store(&db, "http://network.example.com", None, &[("ep-1", "http://CDN.example.com/ep1.mp3?utm_source=network")]).await;
+        let item = first_item(&db, show).await;
+        assert_eq!(first_item(&db, network).await.episode_key, item.episode_key);
+
+        ListeningStateDataLayer::update_current_time_for_item(db.clone(), item.episode_key.clone(), item.enclosure, show, 42.0).await.unwrap();
+        assert_eq!(first_item(&db, network).await.listening_state.map(|s| s.time), Some(42.0));
+
+        // the show moves to another CDN
+        store(&db, "http://show.example.com", Some(show), &[("ep-1", "https://media.example.org/ep1.mp3")]).await;
+        let moved = first_item(&db, show).await;
+        assert_eq!(moved.enclosure, "https://media.example.org/ep1.mp3");
+        assert_eq!(moved.episode_key, item.episode_key);
+        assert_eq!(moved.listening_state.map(|s| s.time), Some(42.0));
+    }
+
+    #[tokio::test]
+    async fn migration_scopes_global_guid_keys_to_channels() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, Some(12)).await.unwrap();
+        let backend = db.get_database_backend();
+        for sql in [
+            "INSERT INTO channel (id, title, link, description) VALUES (1, 'A', 'a', ''), (2, 'B', 'b', ''), (3, 'Network', 'n', '')",
+            "INSERT INTO channel_item (ordering, channel_id, enclosure, guid, episode_key) VALUES
+                (1, 1, 'http://a.example.com/1.mp3', '1', 'guid:1'),
+                (1, 2, 'http://b.example.com/1.mp3', '1', 'guid:1'),
+                (1, 3, 'https://a.example.com/1.mp3', '1', 'guid:1'),
+                (2, 1, 'http://a.example.com/2.mp3', NULL, 'url:a.example.com/2.mp3')",
+            "INSERT INTO listening_state (id, channel_id, channel_item_enclosure, time, finished, episode_key) VALUES
+                (1, 2, 'http://b.example.com/1.mp3', 30.0, 0, 'guid:1'),
+                (2, 1, 'http://a.example.com/2.mp3', 5.0, 0, 'url:a.example.com/2.mp3')",
+        ] {
+            db.execute(Statement::from_string(backend, sql)).await.unwrap();
+        }
+
+        Migrator::up(&db, None).await.unwrap();
+
+        let keys: Vec<(i32, String)> = entity::channel_item::Entity::find()
+            .order_by_asc(channel_item::Column::ChannelId)
+            .order_by_asc(channel_item::Column::Ordering)
+            .all(&db).await.unwrap()
+            .into_iter().map(|i| (i.channel_id, i.episode_key)).collect();
+        assert_eq!(keys, vec![
+            (1, "guid:1:1".to_string()),
+            (1, "url:a.example.com/2.mp3".to_string()),
+            (2, "guid:2:1".to_string()),
+            // shares the episode of channel 1
+            (3, "guid:1:1".to_string()),
+        ]);
+        let states: Vec<String> = listening_state::Entity::find().all(&db).await.unwrap().into_iter().map(|s| s.episode_key).collect();
+        assert_eq!(states, vec!["guid:2:1", "url:a.example.com/2.mp3"]);
+        assert_eq!(first_item(&db, 1).await.listening_state, None);
+        assert_eq!(first_item(&db, 2).await.listening_state.map(|s| s.time), Some(30.0));
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, QueryOrder, QuerySelect};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use crate::data_layer::data_provider::ChannelItemToListeningState;
+use crate::entity::{channel, channel_item, listening_session};
+use crate::entity::listening_session::Entity as ListeningSessionEntity;
+use crate::entity::listening_session::ActiveModel as ListeningSessionModel;
+use crate::ui_models;
+
+pub struct ListeningHistoryDataLayer {}
+
+impl ListeningHistoryDataLayer {
+    /// Open a new listening session for item and return its id
+    pub async fn start_session(db: DatabaseConnection, episode_key: String, enclosure_url: String, channel_id: i32, position: f32) -> Result<i32, DbErr> {
+        let model = ListeningSessionModel {
+            id: ActiveValue::NotSet,
+            channel_id: ActiveValue::set(channel_id),
+            channel_item_enclosure: ActiveValue::set(enclosure_url),
+            episode_key: ActiveValue::set(episode_key),
+            started_at: ActiveValue::set(Local::now().fixed_offset()),
+            ended_at: ActiveValue::set(None),
+            start_position: ActiveValue::set(position),
+            end_position: ActiveValue::set(None),
+        };
+
+        let res = ListeningSessionEntity::insert(model).exec(&db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    /// Close session with id `session_id` at media `position`
+    /// and return the closed session
+    pub async fn end_session(db: DatabaseConnection, session_id: i32, position: f32) -> Result<Option<listening_session::Model>, DbErr> {
+        let res = ListeningSessionEntity::find_by_id(session_id).one(&db).await?;
+
+        match res {
+            Some(s) => {
+                let mut m: ListeningSessionModel = s.into();
+                m.ended_at = ActiveValue::set(Some(Local::now().fixed_offset()));
+                m.end_position = ActiveValue::set(Some(position));
+                let updated = ListeningSessionEntity::update(m).exec(&db).await?;
+                Ok(Some(updated))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Get last `limit` sessions across all channels, newest first
+    pub async fn get_recent_sessions(db: &DatabaseConnection, limit: u64) -> Result<Vec<ui_models::ListeningSession>, DbErr> {
+        let sessions = ListeningSessionEntity::find()
+            .order_by_desc(listening_session::Column::StartedAt)
+            .limit(limit)
+            .all(db).await?;
+
+        let keys: Vec<String> = sessions.iter().map(|s| s.episode_key.clone()).collect();
+        let items: HashMap<(i32, String), ui_models::ChannelItem> = channel_item::Entity::find()
+            .filter(channel_item::Column::EpisodeKey.is_in(keys))
+            .find_also_linked(ChannelItemToListeningState)
+            .all(db).await?
+            .iter()
+            .map(|i| ((i.0.channel_id, i.0.episode_key.clone()), i.into()))
+            .collect();
+
+        let channels: HashMap<i32, Option<String>> = channel::Entity::find()
+            .all(db).await?
+            .into_iter()
+            .map(|c| (c.id, c.title))
+            .collect();
+
+        let to_ret = sessions.into_iter().map(|s| {
+            ui_models::ListeningSession {
+                id: s.id,
+                channel_title: channels.get(&s.channel_id).cloned().flatten(),
+                item: items.get(&(s.channel_id, s.episode_key.clone())).cloned(),
+                enclosure: s.channel_item_enclosure,
+                episode_key: s.episode_key,
+                started_at: s.started_at,
+                ended_at: s.ended_at,
+                start_position: s.start_position,
+                end_position: s.end_position,
+            }
+        }).collect();
+
+        Ok(to_ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+
+    const KEY: &str = "guid:1:mon";
+    const ENCLOSURE: &str = "http://news.example.com/mon.mp3";
+
+    /// Database with channel 1 holding episode `KEY`
+    async fn memory_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        channel::Entity::insert(channel::ActiveModel {
+            id: ActiveValue::set(1),
+            title: ActiveValue::set(Some("Daily News".to_string())),
+            link: ActiveValue::set(None),
+            description: ActiveValue::set(None),
+        }).exec(&db).await.unwrap();
+        channel_item::Entity::insert(channel_item::ActiveModel {
+            ordering: ActiveValue::set(0),
+            channel_id: ActiveValue::set(1),
+            title: ActiveValue::set(Some("Monday".to_string())),
+            link: ActiveValue::set(None),
+            source: ActiveValue::set(None),
+            enclosure: ActiveValue::set(ENCLOSURE.to_string()),
+            description: ActiveValue::set(None),
+            guid: ActiveValue::set(Some("mon".to_string())),
+            pub_date: ActiveValue::set(None),
+            episode_key: ActiveValue::set(KEY.to_string()),
+        }).exec(&db).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn sessions_are_listed_newest_first_with_their_episode() {
+        let db = memory_db().await;
+        let first = ListeningHistoryDataLayer::start_session(db.clone(), KEY.to_string(), ENCLOSURE.to_string(), 1, 0.0).await.unwrap();
+        let ended = ListeningHistoryDataLayer::end_session(db.clone(), first, 95.5).await.unwrap().unwrap();
+        assert_eq!((ended.start_position, ended.end_position), (0.0, Some(95.5)));
+        assert!(ended.ended_at.is_some_and(|end| end >= ended.started_at));
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        // episode no longer in the feed
+        let second = ListeningHistoryDataLayer::start_session(db.clone(), "url:news.example.com/old.mp3".to_string(), "http://news.example.com/old.mp3".to_string(), 1, 12.0).await.unwrap();
+        assert_eq!(ListeningHistoryDataLayer::end_session(db.clone(), second + 1, 20.0).await.unwrap(), None);
+
+        let sessions = ListeningHistoryDataLayer::get_recent_sessions(&db, 10).await.unwrap();
+        assert_eq!(sessions.iter().map(|s| s.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!((sessions[0].start_position, sessions[0].end_position, sessions[0].ended_at), (12.0, None, None));
+        assert_eq!(sessions[0].item, None);
+        assert_eq!(sessions[1].channel_title.as_deref(), Some("Daily News"));
+        assert_eq!(sessions[1].item.as_ref().and_then(|i| i.title.as_deref()), Some("Monday"));
+        assert_eq!(sessions[1].end_position, Some(95.5));
+
+        assert_eq!(ListeningHistoryDataLayer::get_recent_sessions(&db, 1).await.unwrap().len(), 1);
+    }
+}
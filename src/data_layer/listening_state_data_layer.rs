@@ -9,11 +9,12 @@ use crate::entity::listening_state::ActiveModel as ListeningStateModel;
 pub struct ListeningStateDataLayer {}
 
 impl ListeningStateDataLayer {
-    async fn create_listenitg_state_for_item(db: DatabaseConnection, enclosure_url: String, channel_id: i32, time: f32) {
+    async fn create_listenitg_state_for_item(db: DatabaseConnection, episode_key: String, enclosure_url: String, channel_id: i32, time: f32) {
         let model = ListeningStateModel {
             id: ActiveValue::NotSet,
             channel_id: ActiveValue::set(channel_id),
             channel_item_enclosure: ActiveValue::set(enclosure_url),
+            episode_key: ActiveValue::set(episode_key),
             time: ActiveValue::set(time),
             finished: ActiveValue::set(false),
         };
@@ -23,10 +24,9 @@ impl ListeningStateDataLayer {
 
     }
 
-    pub async fn mark_item_as_finished(db: DatabaseConnection, enclosure_url: String, channel_id: i32) -> Result<(), sea_orm::DbErr> {
+    pub async fn mark_item_as_finished(db: DatabaseConnection, episode_key: String) -> Result<(), sea_orm::DbErr> {
         let res = ListeningStateEntity::find()
-            .filter(listening_state::Column::ChannelId.eq(channel_id))
-            .filter(listening_state::Column::ChannelItemEnclosure.eq(enclosure_url))
+            .filter(listening_state::Column::EpisodeKey.eq(episode_key))
             .one(&db).await?;
 
         match res {
@@ -43,10 +43,10 @@ impl ListeningStateDataLayer {
         }
     }
 
-    pub async fn update_current_time_for_item(db: DatabaseConnection, enclosure_url: String, channel_id: i32, time: f32) -> Result<(), sea_orm::DbErr> {
+    /// Listening state is shared by all channels containing the episode
+    pub async fn update_current_time_for_item(db: DatabaseConnection, episode_key: String, enclosure_url: String, channel_id: i32, time: f32) -> Result<(), sea_orm::DbErr> {
         let res = ListeningStateEntity::find()
-            .filter(listening_state::Column::ChannelId.eq(channel_id))
-            .filter(listening_state::Column::ChannelItemEnclosure.eq(&enclosure_url))
+            .filter(listening_state::Column::EpisodeKey.eq(&episode_key))
             .one(&db).await?;
 
         match res {
@@ -54,11 +54,12 @@ impl ListeningStateDataLayer {
                 let mut m: ListeningStateModel = i.into();
                 m.finished = ActiveValue::set(false);
                 m.time = ActiveValue::set(time);
+                m.channel_item_enclosure = ActiveValue::set(enclosure_url);
                 let _ = ListeningStateEntity::update(m).exec(&db).await?;
                 Ok(())
             },
             None => {
-                ListeningStateDataLayer::create_listenitg_state_for_item(db, enclosure_url, channel_id, time).await;
+                ListeningStateDataLayer::create_listenitg_state_for_item(db, episode_key, enclosure_url, channel_id, time).await;
                 Ok(())
             },
         }
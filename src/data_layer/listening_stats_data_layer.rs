@@ -0,0 +1,247 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, TimeZone};
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, QueryOrder};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use crate::entity::{channel, channel_item, listening_stat, listening_state};
+use crate::entity::listening_stat::Entity as ListeningStatEntity;
+use crate::entity::listening_stat::ActiveModel as ListeningStatModel;
+use crate::ui_models;
+
+/// Number of weeks shown in the per week chart
+const WEEKS: i64 = 12;
+
+pub struct ListeningStatsDataLayer {}
+
+impl ListeningStatsDataLayer {
+    /// Account wall time between `from` and `to` as listening time for item,
+    /// split over local calendar days
+    pub async fn add_listening_time(db: DatabaseConnection, episode_key: String, enclosure_url: String, channel_id: i32, from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> Result<(), DbErr> {
+        let mut start = from.with_timezone(&Local);
+        let end = to.with_timezone(&Local);
+
+        while start < end {
+            let day = start.date_naive();
+            let next_day = day.succ_opt()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .and_then(|d| Local.from_local_datetime(&d).earliest())
+                .unwrap_or(end);
+            let chunk_end = std::cmp::min(end, next_day);
+            let seconds = (chunk_end - start).num_milliseconds() as f32 / 1000.0;
+            Self::add_seconds(&db, &episode_key, &enclosure_url, channel_id, day, seconds).await?;
+            start = chunk_end;
+        }
+        Ok(())
+    }
+
+    async fn add_seconds(db: &DatabaseConnection, episode_key: &str, enclosure_url: &str, channel_id: i32, day: NaiveDate, seconds: f32) -> Result<(), DbErr> {
+        let res = ListeningStatEntity::find()
+            .filter(listening_stat::Column::ChannelId.eq(channel_id))
+            .filter(listening_stat::Column::EpisodeKey.eq(episode_key))
+            .filter(listening_stat::Column::Day.eq(day))
+            .one(db).await?;
+
+        match res {
+            Some(s) => {
+                let total = s.seconds + seconds;
+                let mut m: ListeningStatModel = s.into();
+                m.seconds = ActiveValue::set(total);
+                ListeningStatEntity::update(m).exec(db).await?;
+            },
+            None => {
+                let m = ListeningStatModel {
+                    id: ActiveValue::NotSet,
+                    channel_id: ActiveValue::set(channel_id),
+                    channel_item_enclosure: ActiveValue::set(enclosure_url.to_string()),
+                    episode_key: ActiveValue::set(episode_key.to_string()),
+                    day: ActiveValue::set(day),
+                    seconds: ActiveValue::set(seconds),
+                };
+                ListeningStatEntity::insert(m).exec(db).await?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Aggregate all accounted listening time
+    pub async fn get_stats(db: &DatabaseConnection) -> Result<ui_models::ListeningStats, DbErr> {
+        let rows = ListeningStatEntity::find()
+            .order_by_asc(listening_stat::Column::Day)
+            .all(db).await?;
+
+        let channels: HashMap<i32, Option<String>> = channel::Entity::find()
+            .all(db).await?
+            .into_iter()
+            .map(|c| (c.id, c.title))
+            .collect();
+
+        let keys: Vec<String> = rows.iter().map(|r| r.episode_key.clone()).collect();
+        let titles: HashMap<(i32, String), Option<String>> = channel_item::Entity::find()
+            .filter(channel_item::Column::EpisodeKey.is_in(keys))
+            .all(db).await?
+            .into_iter()
+            .map(|i| ((i.channel_id, i.episode_key), i.title))
+            .collect();
+
+        let daily: Vec<ui_models::DailyListening> = rows.into_iter().map(|r| {
+            ui_models::DailyListening {
+                day: r.day,
+                channel_id: r.channel_id,
+                channel_title: channels.get(&r.channel_id).cloned().flatten(),
+                item_title: titles.get(&(r.channel_id, r.episode_key.clone())).cloned().flatten(),
+                enclosure: r.channel_item_enclosure,
+                episode_key: r.episode_key,
+                seconds: r.seconds,
+            }
+        }).collect();
+
+        let total_seconds = daily.iter().map(|d| d.seconds).sum();
+
+        // keyed by id, channels may share a title
+        let mut per_channel: HashMap<i32, f32> = HashMap::new();
+        daily.iter().for_each(|d| {
+            *per_channel.entry(d.channel_id).or_default() += d.seconds;
+        });
+        let mut per_channel: Vec<(i32, String, f32)> = per_channel.into_iter()
+            .map(|(id, seconds)| (id, channels.get(&id).cloned().flatten().unwrap_or("-".to_string()), seconds))
+            .collect();
+        per_channel.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        let today = Local::now().date_naive();
+        let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let per_week = (0..WEEKS).rev().map(|w| {
+            let monday = this_monday - Duration::weeks(w);
+            let sunday = monday + Duration::days(6);
+            let seconds = daily.iter()
+                .filter(|d| d.day >= monday && d.day <= sunday)
+                .map(|d| d.seconds)
+                .sum();
+            (format!("W{:02}", monday.iso_week().week()), seconds)
+        }).collect();
+
+        let days: BTreeSet<NaiveDate> = daily.iter().filter(|d| d.seconds > 0.0).map(|d| d.day).collect();
+        let mut longest_streak = 0;
+        let mut streak = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for day in days {
+            streak = match previous {
+                Some(p) if p.succ_opt() == Some(day) => streak + 1,
+                _ => 1,
+            };
+            longest_streak = std::cmp::max(longest_streak, streak);
+            previous = Some(day);
+        }
+
+        let states = listening_state::Entity::find().all(db).await?;
+        let episodes_started = states.len();
+        let episodes_finished = states.iter().filter(|s| s.finished).count();
+        let completion_rate = if episodes_started > 0 {
+            episodes_finished as f32 / episodes_started as f32
+        } else {
+            0.0
+        };
+
+        Ok(ui_models::ListeningStats {
+            total_seconds,
+            per_channel,
+            per_week,
+            episodes_started,
+            episodes_finished,
+            completion_rate,
+            longest_streak,
+            daily,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::Database;
+
+    use super::*;
+    use crate::data_layer::listening_state_data_layer::ListeningStateDataLayer;
+
+    async fn memory_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        for (id, title) in [(1, "Daily News"), (2, "Long Talks")] {
+            channel::Entity::insert(channel::ActiveModel {
+                id: ActiveValue::set(id),
+                title: ActiveValue::set(Some(title.to_string())),
+                link: ActiveValue::set(None),
+                description: ActiveValue::set(None),
+            }).exec(&db).await.unwrap();
+        }
+        db
+    }
+
+    fn local(day: NaiveDate, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        Local.from_local_datetime(&day.and_time(NaiveTime::from_hms_opt(hour, minute, 0).unwrap())).earliest().unwrap().fixed_offset()
+    }
+
+    /// Listen to `episode` of `channel_id` for `seconds` from noon of `day`
+    async fn listen(db: &DatabaseConnection, channel_id: i32, episode: &str, day: NaiveDate, seconds: i64) {
+        let from = local(day, 12, 0);
+        let key = format!("url:example.com/{}.mp3", episode);
+        ListeningStatsDataLayer::add_listening_time(db.clone(), key, format!("http://example.com/{}.mp3", episode), channel_id, from, from + Duration::seconds(seconds)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn listening_over_midnight_is_split_between_days() {
+        let db = memory_db().await;
+        let day = Local::now().date_naive() - Duration::days(3);
+        let key = "url:example.com/1.mp3".to_string();
+        let enclosure = "http://example.com/1.mp3".to_string();
+        ListeningStatsDataLayer::add_listening_time(db.clone(), key.clone(), enclosure.clone(), 1, local(day, 23, 30), local(day + Duration::days(1), 0, 45)).await.unwrap();
+        // more listening on the same day adds up
+        ListeningStatsDataLayer::add_listening_time(db.clone(), key, enclosure, 1, local(day, 8, 0), local(day, 8, 10)).await.unwrap();
+
+        let stats = ListeningStatsDataLayer::get_stats(&db).await.unwrap();
+        let daily: Vec<(NaiveDate, f32)> = stats.daily.iter().map(|d| (d.day, d.seconds)).collect();
+        assert_eq!(daily, vec![(day, 2400.0), (day + Duration::days(1), 2700.0)]);
+        assert_eq!(stats.total_seconds, 5100.0);
+        assert_eq!(stats.longest_streak, 2);
+    }
+
+    #[tokio::test]
+    async fn stats_are_bucketed_per_week_and_channel() {
+        let db = memory_db().await;
+        let today = Local::now().date_naive();
+        let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        listen(&db, 1, "a", this_monday, 600).await;
+        listen(&db, 2, "b", this_monday - Duration::days(7), 300).await;
+        listen(&db, 2, "b", this_monday - Duration::days(6), 300).await;
+        listen(&db, 1, "c", this_monday - Duration::days(5), 300).await;
+        // older than the chart
+        listen(&db, 2, "d", this_monday - Duration::weeks(WEEKS), 1000).await;
+
+        let stats = ListeningStatsDataLayer::get_stats(&db).await.unwrap();
+        assert_eq!(stats.total_seconds, 2500.0);
+        assert_eq!(stats.per_channel, vec![(2, "Long Talks".to_string(), 1600.0), (1, "Daily News".to_string(), 900.0)]);
+
+        assert_eq!(stats.per_week.len(), WEEKS as usize);
+        assert_eq!(stats.per_week.last(), Some(&(format!("W{:02}", this_monday.iso_week().week()), 600.0)));
+        assert_eq!(stats.per_week[WEEKS as usize - 2].1, 900.0);
+        assert_eq!(stats.per_week.iter().map(|w| w.1).sum::<f32>(), 1500.0);
+
+        assert_eq!(stats.longest_streak, 3);
+    }
+
+    #[tokio::test]
+    async fn completion_rate_is_finished_of_started_episodes() {
+        let db = memory_db().await;
+        assert_eq!(ListeningStatsDataLayer::get_stats(&db).await.unwrap().completion_rate, 0.0);
+
+        for episode in ["a", "b", "c", "d"] {
+            ListeningStateDataLayer::update_current_time_for_item(db.clone(), format!("url:example.com/{}.mp3", episode), format!("http://example.com/{}.mp3", episode), 1, 60.0).await.unwrap();
+        }
+        ListeningStateDataLayer::mark_item_as_finished(db.clone(), "url:example.com/b.mp3".to_string()).await.unwrap();
+
+        let stats = ListeningStatsDataLayer::get_stats(&db).await.unwrap();
+        assert_eq!((stats.episodes_started, stats.episodes_finished), (4, 1));
+        assert_eq!(stats.completion_rate, 0.25);
+        assert_eq!(stats.longest_streak, 0);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod bookmark_data_layer;
 pub mod data_provider;
+pub mod listening_history_data_layer;
 pub mod listening_state_data_layer;
+pub mod listening_stats_data_layer;
+pub mod radio_directory_data_layer;
+pub mod radio_song_data_layer;
+pub mod radio_station_data_layer;
+pub mod schedule_data_layer;
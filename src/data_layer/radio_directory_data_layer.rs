@@ -0,0 +1,150 @@
+use serde_json::Value;
+use sea_orm::ActiveValue;
+use crate::entity::radio_station::ActiveModel as RadioStationModel;
+use crate::ui_models::{DirectorySearch, DirectoryStation};
+
+/// Max number of stations returned by one search
+const SEARCH_LIMIT: usize = 100;
+
+/// Client for Radio Browser compatible directory API (https://api.radio-browser.info)
+pub struct RadioDirectoryDataLayer {}
+
+impl RadioDirectoryDataLayer {
+    /// Search stations ordered by votes, broken stations are skipped
+    pub fn search(base_url: &str, search: &DirectorySearch) -> Result<Vec<DirectoryStation>, Box<dyn std::error::Error>> {
+        let url = format!("{}/json/stations/search", base_url.trim_end_matches('/'));
+        let limit = SEARCH_LIMIT.to_string();
+        let mut request = ureq::get(&url)
+            .set("User-Agent", &format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")))
+            .query("limit", &limit)
+            .query("hidebroken", "true")
+            .query("order", "votes")
+            .query("reverse", "true");
+        for (param, value) in [("name", &search.name), ("tag", &search.tag), ("country", &search.country), ("language", &search.language)] {
+            if !value.is_empty() {
+                request = request.query(param, value);
+            }
+        }
+
+        let content = request.call()?.into_string()?;
+        let stations: Vec<Value> = serde_json::from_str(&content)?;
+        Ok(stations.iter().filter_map(station_from_json).collect())
+    }
+
+    /// Station for the collection with directory metadata
+    pub fn to_radio_station(station: &DirectoryStation) -> RadioStationModel {
+        RadioStationModel {
+            id: ActiveValue::NotSet,
+            name: ActiveValue::set(Some(station.name.clone())),
+            url: ActiveValue::set(station.url.clone()),
+            genre: ActiveValue::set(station.tags.clone()),
+            homepage: ActiveValue::set(station.homepage.clone()),
+            country: ActiveValue::set(station.country.clone()),
+            codec: ActiveValue::set(station.codec.clone()),
+            bitrate: ActiveValue::set(station.bitrate),
+            favorite: ActiveValue::set(false),
+            resolved_url: ActiveValue::set(None),
+            position: ActiveValue::NotSet,
+            group_name: ActiveValue::set(None),
+        }
+    }
+}
+
+fn station_from_json(value: &Value) -> Option<DirectoryStation> {
+    let text = |key: &str| value.get(key)
+        .and_then(Value::as_str)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    let url = text("url_resolved").or(text("url"))?;
+    Some(DirectoryStation {
+        name: text("name").unwrap_or(url.clone()),
+        url,
+        homepage: text("homepage"),
+        tags: text("tags"),
+        country: text("country"),
+        language: text("language"),
+        codec: text("codec"),
+        bitrate: value.get("bitrate").and_then(Value::as_i64).filter(|b| *b > 0).map(|b| b as i32),
+        votes: value.get("votes").and_then(Value::as_i64).unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader, Write}, net::TcpListener, thread};
+
+    use super::*;
+
+    /// Serve `body` to one request on a local port, returns the base url and the request line
+    fn stub_server(body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+            request_line
+        });
+        (base_url, handle)
+    }
+
+    #[test]
+    fn search_queries_directory_and_parses_stations() {
+        let (base_url, server) = stub_server(r#"[
+            {"name": " Jazz Radio ", "url": "http://jazz.example.com/pls", "url_resolved": "http://jazz.example.com/stream",
+             "homepage": "", "tags": "jazz,smooth", "country": "United Kingdom", "language": "english",
+             "codec": "MP3", "bitrate": 128, "votes": 42},
+            {"name": "No stream", "url": "", "url_resolved": ""},
+            {"url": "http://plain.example.com/", "bitrate": 0}
+        ]"#);
+        let search = DirectorySearch::parse("jazz country:\"United Kingdom\"");
+
+        let stations = RadioDirectoryDataLayer::search(&format!("{}/", base_url), &search).unwrap();
+
+        let request_line = server.join().unwrap();
+        assert!(request_line.starts_with("GET /json/stations/search?"), "{}", request_line);
+        assert!(request_line.contains("name=jazz"), "{}", request_line);
+        assert!(request_line.contains("country=United%20Kingdom") || request_line.contains("country=United+Kingdom"), "{}", request_line);
+        assert!(!request_line.contains("tag="), "{}", request_line);
+        assert!(request_line.contains("hidebroken=true"), "{}", request_line);
+
+        assert_eq!(stations, vec![
+            DirectoryStation {
+                name: "Jazz Radio".to_string(),
+                url: "http://jazz.example.com/stream".to_string(),
+                homepage: None,
+                tags: Some("jazz,smooth".to_string()),
+                country: Some("United Kingdom".to_string()),
+                language: Some("english".to_string()),
+                codec: Some("MP3".to_string()),
+                bitrate: Some(128),
+                votes: 42,
+            },
+            DirectoryStation {
+                name: "http://plain.example.com/".to_string(),
+                url: "http://plain.example.com/".to_string(),
+                homepage: None,
+                tags: None,
+                country: None,
+                language: None,
+                codec: None,
+                bitrate: None,
+                votes: 0,
+            },
+        ]);
+    }
+
+    #[test]
+    fn search_fails_on_invalid_response() {
+        let (base_url, server) = stub_server("<html>not json</html>");
+        assert!(RadioDirectoryDataLayer::search(&base_url, &DirectorySearch::default()).is_err());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::Local;
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, EntityTrait, QueryOrder, QuerySelect};
+use crate::entity::radio_song;
+use crate::entity::radio_song::Entity as RadioSongEntity;
+use crate::entity::radio_song::ActiveModel as RadioSongModel;
+
+pub struct RadioSongDataLayer {}
+
+impl RadioSongDataLayer {
+    pub async fn add_song(db: &DatabaseConnection, station_id: i32, station_name: Option<String>, title: String) -> Result<i32, DbErr> {
+        let model = RadioSongModel {
+            id: ActiveValue::NotSet,
+            station_id: ActiveValue::set(station_id),
+            station_name: ActiveValue::set(station_name),
+            title: ActiveValue::set(title),
+            heard_at: ActiveValue::set(Local::now().fixed_offset()),
+        };
+
+        let res = RadioSongEntity::insert(model).exec(db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    /// Most recently heard songs first, all songs when `limit` is `None`
+    pub async fn get_songs(db: &DatabaseConnection, limit: Option<u64>) -> Result<Vec<radio_song::Model>, DbErr> {
+        RadioSongEntity::find()
+            .order_by_desc(radio_song::Column::HeardAt)
+            .order_by_desc(radio_song::Column::Id)
+            .limit(limit)
+            .all(db).await
+    }
+}
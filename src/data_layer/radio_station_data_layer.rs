@@ -0,0 +1,116 @@
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, QueryOrder};
+use sea_orm::EntityTrait;
+use std::error::Error;
+use crate::config;
+use crate::entity::radio_station;
+use crate::entity::radio_station::Entity as RadioStationEntity;
+use crate::entity::radio_station::ActiveModel as RadioStationModel;
+
+pub struct RadioStationDataLayer {}
+
+impl RadioStationDataLayer {
+    pub async fn get_stations(db: &DatabaseConnection) -> Result<Vec<radio_station::Model>, DbErr> {
+        RadioStationEntity::find()
+            .order_by_asc(radio_station::Column::Position)
+            .order_by_asc(radio_station::Column::Id)
+            .all(db).await
+    }
+
+    /// New station with only url known
+    pub fn station_from_url(url: &str) -> RadioStationModel {
+        RadioStationModel {
+            id: ActiveValue::NotSet,
+            name: ActiveValue::set(None),
+            url: ActiveValue::set(url.trim().to_string()),
+            genre: ActiveValue::set(None),
+            homepage: ActiveValue::set(None),
+            country: ActiveValue::set(None),
+            codec: ActiveValue::set(None),
+            bitrate: ActiveValue::set(None),
+            favorite: ActiveValue::set(false),
+            resolved_url: ActiveValue::set(None),
+            position: ActiveValue::NotSet,
+            group_name: ActiveValue::set(None),
+        }
+    }
+
+    /// Add station at the end of the list
+    pub async fn add_station(db: &DatabaseConnection, mut station: RadioStationModel) -> Result<i32, DbErr> {
+        let last = Self::get_stations(db).await?.iter().map(|s| s.position).max().unwrap_or_default();
+        station.position = ActiveValue::set(last + 1);
+        let res = RadioStationEntity::insert(station).exec(db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    /// Cache stream url resolved from station playlist
+    pub async fn set_resolved_url(db: &DatabaseConnection, station_id: i32, resolved_url: Option<String>) -> Result<(), DbErr> {
+        let station = RadioStationModel {
+            id: ActiveValue::unchanged(station_id),
+            resolved_url: ActiveValue::set(resolved_url),
+            ..Default::default()
+        };
+        RadioStationEntity::update(station).exec(db).await?;
+        Ok(())
+    }
+
+    /// Change name, url and group of station, cached resolved url is dropped when url changes
+    pub async fn update_station(db: &DatabaseConnection, station: &radio_station::Model, name: Option<String>, url: String, group_name: Option<String>) -> Result<(), DbErr> {
+        let url_changed = url != station.url;
+        let station = RadioStationModel {
+            id: ActiveValue::unchanged(station.id),
+            name: ActiveValue::set(name),
+            url: ActiveValue::set(url),
+            group_name: ActiveValue::set(group_name),
+            resolved_url: if url_changed { ActiveValue::set(None) } else { ActiveValue::NotSet },
+            ..Default::default()
+        };
+        RadioStationEntity::update(station).exec(db).await?;
+        Ok(())
+    }
+
+    pub async fn set_favorite(db: &DatabaseConnection, station_id: i32, favorite: bool) -> Result<(), DbErr> {
+        let station = RadioStationModel {
+            id: ActiveValue::unchanged(station_id),
+            favorite: ActiveValue::set(favorite),
+            ..Default::default()
+        };
+        RadioStationEntity::update(station).exec(db).await?;
+        Ok(())
+    }
+
+    /// Exchange list positions of two stations
+    pub async fn swap_positions(db: &DatabaseConnection, a: &radio_station::Model, b: &radio_station::Model) -> Result<(), DbErr> {
+        for (id, position) in [(a.id, b.position), (b.id, a.position)] {
+            let station = RadioStationModel {
+                id: ActiveValue::unchanged(id),
+                position: ActiveValue::set(position),
+                ..Default::default()
+            };
+            RadioStationEntity::update(station).exec(db).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_station(db: &DatabaseConnection, station_id: i32) -> Result<(), DbErr> {
+        RadioStationEntity::delete_by_id(station_id).exec(db).await?;
+        Ok(())
+    }
+
+    /// One time import of `~/.librecast.radio` stream list
+    pub async fn import_legacy(db: &DatabaseConnection) -> Result<usize, Box<dyn Error>> {
+        let urls = match config::load_legacy_radio()? {
+            Some(urls) => urls,
+            None => return Ok(0),
+        };
+
+        let existing: Vec<String> = Self::get_stations(db).await?.into_iter().map(|s| s.url).collect();
+        let mut imported = 0;
+        for url in urls.iter().filter(|u| !u.trim().is_empty() && !existing.contains(&u.trim().to_string())) {
+            Self::add_station(db, Self::station_from_url(url)).await?;
+            imported += 1;
+        }
+
+        config::mark_legacy_radio_imported()?;
+        Ok(imported)
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::Local;
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr, EntityTrait, QueryOrder};
+use crate::entity::schedule_job;
+use crate::entity::schedule_job::Entity as ScheduleJobEntity;
+use crate::entity::schedule_job::ActiveModel as ScheduleJobModel;
+
+pub struct ScheduleDataLayer {}
+
+impl ScheduleDataLayer {
+    pub async fn get_jobs(db: &DatabaseConnection) -> Result<Vec<schedule_job::Model>, DbErr> {
+        ScheduleJobEntity::find()
+            .order_by_asc(schedule_job::Column::StartTime)
+            .order_by_asc(schedule_job::Column::Id)
+            .all(db).await
+    }
+
+    /// Insert job without id, update the existing one otherwise
+    pub async fn save_job(db: &DatabaseConnection, job: ScheduleJobModel) -> Result<(), DbErr> {
+        if let ActiveValue::NotSet = job.id {
+            ScheduleJobEntity::insert(job).exec(db).await?;
+        } else {
+            ScheduleJobEntity::update(job).exec(db).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_job(db: &DatabaseConnection, job_id: i32) -> Result<(), DbErr> {
+        ScheduleJobEntity::delete_by_id(job_id).exec(db).await?;
+        Ok(())
+    }
+
+    pub async fn set_enabled(db: &DatabaseConnection, job_id: i32, enabled: bool) -> Result<(), DbErr> {
+        let job = ScheduleJobModel {
+            id: ActiveValue::unchanged(job_id),
+            enabled: ActiveValue::set(enabled),
+            ..Default::default()
+        };
+        ScheduleJobEntity::update(job).exec(db).await?;
+        Ok(())
+    }
+
+    pub async fn set_last_run(db: &DatabaseConnection, job_id: i32) -> Result<(), DbErr> {
+        let job = ScheduleJobModel {
+            id: ActiveValue::unchanged(job_id),
+            last_run_at: ActiveValue::set(Some(Local::now().fixed_offset())),
+            ..Default::default()
+        };
+        ScheduleJobEntity::update(job).exec(db).await?;
+        Ok(())
+    }
+}
@@ -15,18 +15,42 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::bookmark::Entity")]
+    Bookmark,
     #[sea_orm(has_many = "super::channel_item::Entity")]
     ChannelItem,
+    #[sea_orm(has_many = "super::listening_session::Entity")]
+    ListeningSession,
+    #[sea_orm(has_many = "super::listening_stat::Entity")]
+    ListeningStat,
     #[sea_orm(has_many = "super::listening_state::Entity")]
     ListeningState,
 }
 
+impl Related<super::bookmark::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bookmark.def()
+    }
+}
+
 impl Related<super::channel_item::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ChannelItem.def()
     }
 }
 
+impl Related<super::listening_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ListeningSession.def()
+    }
+}
+
+impl Related<super::listening_stat::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ListeningStat.def()
+    }
+}
+
 impl Related<super::listening_state::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ListeningState.def()
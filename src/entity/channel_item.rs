@@ -11,11 +11,12 @@ pub struct Model {
     pub title: Option<String>,
     pub link: Option<String>,
     pub source: Option<String>,
-    #[sea_orm(primary_key, auto_increment = false, unique)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub enclosure: String,
     pub description: Option<String>,
     pub guid: Option<String>,
     pub pub_date: Option<DateTimeWithTimeZone>,
+    pub episode_key: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
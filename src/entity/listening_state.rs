@@ -12,6 +12,7 @@ pub struct Model {
     #[sea_orm(column_type = "Float")]
     pub time: f32,
     pub finished: bool,
+    pub episode_key: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
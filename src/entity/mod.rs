@@ -2,6 +2,12 @@
 
 pub mod prelude;
 
+pub mod bookmark;
 pub mod channel;
 pub mod channel_item;
+pub mod listening_session;
+pub mod listening_stat;
 pub mod listening_state;
+pub mod radio_song;
+pub mod radio_station;
+pub mod schedule_job;
@@ -1,5 +1,11 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+pub use super::bookmark::Entity as Bookmark;
 pub use super::channel::Entity as Channel;
 pub use super::channel_item::Entity as ChannelItem;
+pub use super::listening_session::Entity as ListeningSession;
+pub use super::listening_stat::Entity as ListeningStat;
 pub use super::listening_state::Entity as ListeningState;
+pub use super::radio_song::Entity as RadioSong;
+pub use super::radio_station::Entity as RadioStation;
+pub use super::schedule_job::Entity as ScheduleJob;
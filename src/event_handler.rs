@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use color_eyre::eyre::Result;
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
 use futures::{FutureExt, StreamExt};
 use tokio::{sync::mpsc, task::JoinHandle};
 
@@ -9,6 +11,7 @@ pub enum Event {
     Tick,
     Render,
     Key(KeyEvent),
+    Mouse(MouseEvent),
 }
 
 #[derive(Debug)]
@@ -19,10 +22,7 @@ pub struct EventHandler {
 }
 
 impl EventHandler {
-    pub fn new() -> Self {
-        let tick_rate = std::time::Duration::from_millis(250);
-        let render_delay = std::time::Duration::from_millis(40);
-
+    pub fn new(tick_rate: Duration, render_delay: Duration) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
         let _tx = tx.clone();
 
@@ -44,6 +44,10 @@ impl EventHandler {
                                             tx.send(Event::Key(key)).unwrap();
                                         }
                                     },
+                                    // plain moves would flood the channel
+                                    crossterm::event::Event::Mouse(mouse) if mouse.kind != MouseEventKind::Moved => {
+                                        tx.send(Event::Mouse(mouse)).unwrap();
+                                    },
                                     _ => {},
                                 }
                             }
@@ -0,0 +1,98 @@
+use ratatui::{layout::Rect, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, List, ListState}, Frame};
+use sea_orm::{DatabaseConnection, DbErr};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{data_layer::listening_history_data_layer::ListeningHistoryDataLayer, keymap::{self, Action, Scope}, list_navigation, mouse::Mouse, theme, ui_models::{self, time_to_display}, AsyncAction};
+
+/// Number of sessions shown in "Recently played"
+const HISTORY_LIMIT: u64 = 200;
+
+pub struct HistoryModel {
+    db: DatabaseConnection,
+    pub sessions: Vec<ui_models::ListeningSession>,
+    pub list_state: ListState,
+    /// where the list was rendered, for mouse input
+    area: Rect,
+    tx: UnboundedSender<AsyncAction>,
+}
+
+impl HistoryModel {
+    pub fn new(db: DatabaseConnection, tx: UnboundedSender<AsyncAction>) -> Self {
+        Self {
+            db,
+            sessions: vec![],
+            list_state: Default::default(),
+            area: Rect::default(),
+            tx,
+        }
+    }
+
+    pub async fn reload(&mut self) -> Result<(), DbErr> {
+        self.sessions = ListeningHistoryDataLayer::get_recent_sessions(&self.db, HISTORY_LIMIT).await?;
+        if self.sessions.is_empty() {
+            self.list_state.select(None);
+        } else if self.list_state.selected().is_none_or(|s| s >= self.sessions.len()) {
+            self.list_state.select(Some(0));
+        }
+        Ok(())
+    }
+
+    pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
+        let lines: Vec<Line> = self.sessions.iter().map(|s| {
+            let title = s.item.as_ref()
+                .and_then(|i| i.title.clone())
+                .unwrap_or(s.enclosure.clone());
+            let channel = s.channel_title.clone().unwrap_or("-".to_string());
+            let end = match s.end_position {
+                Some(e) => time_to_display(e),
+                None => "…".to_string(),
+            };
+
+            Line::from(vec![
+                Span::styled(format!("{} ", s.started_at.format("%Y-%m-%d %H:%M")), theme.muted),
+                Span::styled(format!("{} - ", channel), theme.accent),
+                Span::styled(title, Style::default()),
+                Span::styled(format!(" [{} → {}]", time_to_display(s.start_position), end), theme.muted),
+            ])
+        }).collect();
+
+        let list = List::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Recently played").title_bottom(keymap::get().hint(Scope::History, &[Action::Select, Action::Refresh])))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ")
+            .repeat_highlight_symbol(true);
+
+        self.area = rect;
+        f.render_stateful_widget(list, rect, &mut self.list_state);
+    }
+
+    pub async fn handle_mouse(&mut self, mouse: Mouse) -> std::io::Result<()> {
+        match mouse.list_action(self.area, &mut self.list_state, self.sessions.len()) {
+            Some(action) => self.handle_action(action).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn handle_action(&mut self, action: Action) -> std::io::Result<()> {
+        match action {
+            Action::Refresh => {
+                self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+            },
+            Action::Select => {
+                let selected = self.list_state.selected().and_then(|i| self.sessions.get(i));
+                if let Some(session) = selected {
+                    if let Some(item) = session.item.clone() {
+                        let time = session.end_position.unwrap_or(session.start_position);
+                        let _ = self.tx.send(AsyncAction::ResumeItem(item, time));
+                    }
+                }
+            },
+            action if list_navigation::is_motion(action) => {
+                list_navigation::navigate(&mut self.list_state, self.sessions.len(), self.area.height.saturating_sub(2) as usize, action, None);
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+}
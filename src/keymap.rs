@@ -0,0 +1,634 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::config;
+
+/// Everything a key can be bound to, tabs decide what an action means for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    NextTab,
+    Tab(usize),
+    ToggleHelp,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    SwitchPane,
+    Select,
+    TogglePause,
+    VolumeUp,
+    VolumeDown,
+    SeekForward,
+    SeekBackward,
+    Add,
+    Edit,
+    Delete,
+    Refresh,
+    Export,
+    Record,
+    RecordInBackground,
+    ToggleSplitRecordings,
+    SearchDirectory,
+    ToggleFavorite,
+    MoveUp,
+    MoveDown,
+    CopyTitle,
+    Bookmark,
+    ToggleEnabled,
+    Palette,
+}
+
+const ACTIONS: &[(Action, &str)] = &[
+    (Action::Quit, "quit"),
+    (Action::NextTab, "next_tab"),
+    (Action::Tab(1), "tab_1"),
+    (Action::Tab(2), "tab_2"),
+    (Action::Tab(3), "tab_3"),
+    (Action::Tab(4), "tab_4"),
+    (Action::Tab(5), "tab_5"),
+    (Action::Tab(6), "tab_6"),
+    (Action::ToggleHelp, "toggle_help"),
+    (Action::Up, "up"),
+    (Action::Down, "down"),
+    (Action::PageUp, "page_up"),
+    (Action::PageDown, "page_down"),
+    (Action::First, "first"),
+    (Action::Last, "last"),
+    (Action::Search, "search"),
+    (Action::SearchNext, "search_next"),
+    (Action::SearchPrevious, "search_previous"),
+    (Action::SwitchPane, "switch_pane"),
+    (Action::Select, "select"),
+    (Action::TogglePause, "toggle_pause"),
+    (Action::VolumeUp, "volume_up"),
+    (Action::VolumeDown, "volume_down"),
+    (Action::SeekForward, "seek_forward"),
+    (Action::SeekBackward, "seek_backward"),
+    (Action::Add, "add"),
+    (Action::Edit, "edit"),
+    (Action::Delete, "delete"),
+    (Action::Refresh, "refresh"),
+    (Action::Export, "export"),
+    (Action::Record, "record"),
+    (Action::RecordInBackground, "record_in_background"),
+    (Action::ToggleSplitRecordings, "toggle_split_recordings"),
+    (Action::SearchDirectory, "search_directory"),
+    (Action::ToggleFavorite, "toggle_favorite"),
+    (Action::MoveUp, "move_up"),
+    (Action::MoveDown, "move_down"),
+    (Action::CopyTitle, "copy_title"),
+    (Action::Bookmark, "bookmark"),
+    (Action::ToggleEnabled, "toggle_enabled"),
+    (Action::Palette, "palette"),
+];
+
+impl Action {
+    /// Name used in the `[keys.<scope>]` config tables
+    pub fn name(&self) -> &'static str {
+        ACTIONS.iter().find(|(a, _)| a == self).map(|(_, n)| *n).unwrap_or_default()
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, n)| *n == name).map(|(a, _)| *a)
+    }
+}
+
+/// Where a binding applies. Global bindings win over the tab ones,
+/// recently heard bindings over radio ones while that list is focused,
+/// directory bindings replace the radio ones while the directory is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Global,
+    Radio,
+    RecentlyHeard,
+    Podcasts,
+    History,
+    Stats,
+    Bookmarks,
+    Schedule,
+    Directory,
+}
+
+const SCOPES: [(Scope, &str); 9] = [
+    (Scope::Global, "global"),
+    (Scope::Radio, "radio"),
+    (Scope::RecentlyHeard, "recently_heard"),
+    (Scope::Podcasts, "podcasts"),
+    (Scope::History, "history"),
+    (Scope::Stats, "stats"),
+    (Scope::Bookmarks, "bookmarks"),
+    (Scope::Schedule, "schedule"),
+    (Scope::Directory, "directory"),
+];
+
+impl Scope {
+    pub fn name(&self) -> &'static str {
+        SCOPES.iter().find(|(s, _)| s == self).map(|(_, n)| *n).unwrap_or_default()
+    }
+
+    /// Scopes which are active together, their bindings must not overlap
+    fn overlaps(&self, other: &Scope) -> bool {
+        self == other || *self == Scope::Global || *other == Scope::Global
+    }
+}
+
+/// (scope, action, keys, help text) of the default bindings,
+/// actions without help text are not listed in help
+const DEFAULT_BINDINGS: &[(Scope, Action, &[&str], &str)] = &[
+    (Scope::Global, Action::Quit, &["q"], "quit"),
+    (Scope::Global, Action::NextTab, &["tab"], "next tab"),
+    (Scope::Global, Action::Tab(1), &["1"], "radio"),
+    (Scope::Global, Action::Tab(2), &["2"], "podcasts"),
+    (Scope::Global, Action::Tab(3), &["3"], "history"),
+    (Scope::Global, Action::Tab(4), &["4"], "stats"),
+    (Scope::Global, Action::Tab(5), &["5"], "bookmarks"),
+    (Scope::Global, Action::Tab(6), &["6"], "schedule"),
+    (Scope::Global, Action::Palette, &[":", "ctrl+p"], "command palette"),
+
+    (Scope::Radio, Action::ToggleHelp, &["h"], "toggle help"),
+    (Scope::Radio, Action::Select, &["enter"], "play stream, open/close folder"),
+    (Scope::Radio, Action::TogglePause, &["space"], "play/pause stream"),
+    (Scope::Radio, Action::VolumeUp, &["+", "="], "volume up"),
+    (Scope::Radio, Action::VolumeDown, &["-"], "volume down"),
+    (Scope::Radio, Action::Add, &["o"], "add stream to collection"),
+    (Scope::Radio, Action::SearchDirectory, &["s"], "search radio directory"),
+    (Scope::Radio, Action::Record, &["r"], "record playing stream"),
+    (Scope::Radio, Action::RecordInBackground, &["R"], "record selected station without playing"),
+    (Scope::Radio, Action::ToggleSplitRecordings, &["t"], "split recordings on title change"),
+    (Scope::Radio, Action::Delete, &["d", "delete"], "remove stream from collection"),
+    (Scope::Radio, Action::Edit, &["e"], "edit station name, url and group"),
+    (Scope::Radio, Action::ToggleFavorite, &["f"], "add/remove favorite"),
+    (Scope::Radio, Action::MoveUp, &["K", "shift+up"], "move station up"),
+    (Scope::Radio, Action::MoveDown, &["J", "shift+down"], "move station down"),
+    (Scope::Radio, Action::SwitchPane, &["left", "right"], "stations/recently heard"),
+    (Scope::Radio, Action::Up, &["up", "k"], "previous row"),
+    (Scope::Radio, Action::Down, &["down", "j"], "next row, with a count like 10j"),
+    (Scope::Radio, Action::PageUp, &["pageup"], "page up"),
+    (Scope::Radio, Action::PageDown, &["pagedown"], "page down"),
+    (Scope::Radio, Action::First, &["home", "g g"], "first row"),
+    (Scope::Radio, Action::Last, &["end", "G"], "last row"),
+    (Scope::Radio, Action::Search, &["/"], "search in list"),
+    (Scope::Radio, Action::SearchNext, &["n"], "next match"),
+    (Scope::Radio, Action::SearchPrevious, &["N"], "previous match"),
+
+    (Scope::RecentlyHeard, Action::CopyTitle, &["c"], "copy selected song title"),
+    (Scope::RecentlyHeard, Action::Export, &["e"], "export recently heard as CSV"),
+    (Scope::RecentlyHeard, Action::Up, &["up"], ""),
+    (Scope::RecentlyHeard, Action::Down, &["down"], ""),
+
+    (Scope::Podcasts, Action::ToggleHelp, &["h"], "toggle help"),
+    (Scope::Podcasts, Action::Select, &["enter"], "open channel, play episode"),
+    (Scope::Podcasts, Action::TogglePause, &["space"], "play/pause episode"),
+    (Scope::Podcasts, Action::VolumeUp, &["+", "="], "volume up"),
+    (Scope::Podcasts, Action::VolumeDown, &["-"], "volume down"),
+    (Scope::Podcasts, Action::SeekForward, &["."], "seek forward"),
+    (Scope::Podcasts, Action::SeekBackward, &[","], "seek backward"),
+    (Scope::Podcasts, Action::Add, &["o"], "add podcast"),
+    (Scope::Podcasts, Action::Refresh, &["r"], "refresh selected channel"),
+    (Scope::Podcasts, Action::Bookmark, &["b"], "bookmark current position"),
+    (Scope::Podcasts, Action::SwitchPane, &["left", "right"], "channels/episodes"),
+    (Scope::Podcasts, Action::Up, &["up", "k"], "previous row"),
+    (Scope::Podcasts, Action::Down, &["down", "j"], "next row, with a count like 10j"),
+    (Scope::Podcasts, Action::PageUp, &["pageup"], "page up"),
+    (Scope::Podcasts, Action::PageDown, &["pagedown"], "page down"),
+    (Scope::Podcasts, Action::First, &["home", "g g"], "first row"),
+    (Scope::Podcasts, Action::Last, &["end", "G"], "last row"),
+    (Scope::Podcasts, Action::Search, &["/"], "search in list"),
+    (Scope::Podcasts, Action::SearchNext, &["n"], "next match"),
+    (Scope::Podcasts, Action::SearchPrevious, &["N"], "previous match"),
+
+    (Scope::History, Action::Select, &["enter"], "resume"),
+    (Scope::History, Action::Refresh, &["r"], "refresh"),
+    (Scope::History, Action::Up, &["up"], ""),
+    (Scope::History, Action::Down, &["down"], ""),
+
+    (Scope::Stats, Action::Export, &["e"], "export CSV/JSON"),
+    (Scope::Stats, Action::Refresh, &["r"], "refresh"),
+
+    (Scope::Bookmarks, Action::Select, &["enter"], "jump to bookmark"),
+    (Scope::Bookmarks, Action::Delete, &["d", "delete"], "delete"),
+    (Scope::Bookmarks, Action::Up, &["up"], ""),
+    (Scope::Bookmarks, Action::Down, &["down"], ""),
+
+    (Scope::Schedule, Action::Add, &["a"], "add"),
+    (Scope::Schedule, Action::Edit, &["enter", "e"], "edit"),
+    (Scope::Schedule, Action::Delete, &["d", "delete"], "delete"),
+    (Scope::Schedule, Action::ToggleEnabled, &["space"], "enable/disable"),
+    (Scope::Schedule, Action::Up, &["up"], ""),
+    (Scope::Schedule, Action::Down, &["down"], ""),
+
+    (Scope::Directory, Action::Select, &["enter", "p"], "preview"),
+    (Scope::Directory, Action::Add, &["a"], "add"),
+    (Scope::Directory, Action::Search, &["/", "s"], "search"),
+    (Scope::Directory, Action::Up, &["up"], ""),
+    (Scope::Directory, Action::Down, &["down"], ""),
+];
+
+/// Key with modifiers, shift is part of the character for character keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if let KeyCode::Char(_) = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        KeyChord { code, modifiers }
+    }
+
+    /// `q`, `K`, `space`, `shift+up`, `ctrl+x`, `f5`
+    fn parse(text: &str) -> Result<Self, String> {
+        let (modifiers, key) = match text {
+            "+" => ("", "+"),
+            _ if text.ends_with("++") => (&text[..text.len() - 2], "+"),
+            _ => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let mut mods = KeyModifiers::NONE;
+        for m in modifiers.split('+').filter(|m| !m.is_empty()) {
+            mods |= match m.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier {:?} in {:?}", m, text)),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if mods.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                f if f.starts_with('f') && f[1..].parse::<u8>().is_ok_and(|n| (1..=12).contains(&n)) => KeyCode::F(f[1..].parse().unwrap_or(1)),
+                _ => return Err(format!("unknown key {:?}", text)),
+            },
+        };
+        Ok(KeyChord::new(code, mods))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        KeyChord::new(key.code, key.modifiers)
+    }
+}
+
+/// Same notation as the hand written hints, e.g. `<shift>+<up>`
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (m, name) in [(KeyModifiers::CONTROL, "<ctrl>+"), (KeyModifiers::ALT, "<alt>+"), (KeyModifiers::SHIFT, "<shift>+")] {
+            if self.modifiers.contains(m) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("<space>"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => f.write_str("<enter>"),
+            KeyCode::Esc => f.write_str("<esc>"),
+            KeyCode::Tab => f.write_str("<tab>"),
+            KeyCode::BackTab => f.write_str("<backtab>"),
+            KeyCode::Backspace => f.write_str("<backspace>"),
+            KeyCode::Delete => f.write_str("<del>"),
+            KeyCode::Insert => f.write_str("<ins>"),
+            KeyCode::Up => f.write_str("<up>"),
+            KeyCode::Down => f.write_str("<down>"),
+            KeyCode::Left => f.write_str("<left>"),
+            KeyCode::Right => f.write_str("<right>"),
+            KeyCode::Home => f.write_str("<home>"),
+            KeyCode::End => f.write_str("<end>"),
+            KeyCode::PageUp => f.write_str("<pgup>"),
+            KeyCode::PageDown => f.write_str("<pgdn>"),
+            KeyCode::F(n) => write!(f, "<f{}>", n),
+            other => write!(f, "<{:?}>", other),
+        }
+    }
+}
+
+/// Keys pressed one after the other, e.g. `g g`
+type KeySequence = Vec<KeyChord>;
+
+/// `g g`, keys separated by spaces
+fn parse_sequence(text: &str) -> Result<KeySequence, String> {
+    let sequence = text.split_whitespace().map(KeyChord::parse).collect::<Result<KeySequence, String>>()?;
+    if sequence.is_empty() { Err("empty key".to_string()) } else { Ok(sequence) }
+}
+
+fn sequence_to_string(sequence: &[KeyChord]) -> String {
+    sequence.iter().map(|k| k.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// Value of an action in a `[keys.<scope>]` config table:
+/// one key sequence, or a list of alternatives (empty list unbinds)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// `[keys.<scope>]` tables of the config file, e.g.
+///
+/// ```toml
+/// [keys.global]
+/// quit = "ctrl+q"
+///
+/// [keys.radio]
+/// record = ["r", "g r"]
+/// toggle_help = ["h", "f1"]
+/// ```
+pub type KeysConfig = HashMap<String, HashMap<String, KeyBinding>>;
+
+struct Binding {
+    scope: Scope,
+    action: Action,
+    keys: Vec<KeySequence>,
+    description: &'static str,
+}
+
+/// Result of feeding a pressed key to the keymap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolved {
+    Action(Action),
+    /// keys so far start a longer binding
+    Pending,
+    Unbound,
+}
+
+/// Active key bindings: defaults with the config file bindings applied
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// Returns all problems of the config bindings, including conflicting keys
+    pub fn new(config: &KeysConfig) -> Result<Keymap, Vec<String>> {
+        let mut problems = vec![];
+        let mut bindings: Vec<Binding> = DEFAULT_BINDINGS.iter()
+            .map(|(scope, action, keys, description)| Binding {
+                scope: *scope,
+                action: *action,
+                keys: keys.iter().map(|k| parse_sequence(k).unwrap()).collect(),
+                description,
+            })
+            .collect();
+
+        let mut config: Vec<_> = config.iter().collect();
+        config.sort_by_key(|(scope, _)| *scope);
+        for (scope_name, actions) in config {
+            let mut actions: Vec<_> = actions.iter().collect();
+            actions.sort_by_key(|(action, _)| *action);
+            let scope = match SCOPES.iter().find(|(_, n)| n == scope_name) {
+                Some((s, _)) => *s,
+                None => {
+                    problems.push(format!("[keys.{}] unknown scope, expected one of {}", scope_name, SCOPES.map(|(_, n)| n).join(", ")));
+                    continue;
+                },
+            };
+            for (action_name, binding) in actions {
+                let action = match Action::from_name(action_name) {
+                    Some(a) => a,
+                    None => {
+                        problems.push(format!("[keys.{}] unknown action {:?}", scope_name, action_name));
+                        continue;
+                    },
+                };
+                let texts = match binding {
+                    KeyBinding::One(k) => vec![k.clone()],
+                    KeyBinding::Many(k) => k.clone(),
+                };
+                let keys: Result<Vec<KeySequence>, String> = texts.iter().map(|t| parse_sequence(t)).collect();
+                let keys = match keys {
+                    Ok(k) => k,
+                    Err(e) => {
+                        problems.push(format!("[keys.{}] {}: {}", scope_name, action_name, e));
+                        continue;
+                    },
+                };
+                match bindings.iter_mut().find(|b| b.scope == scope && b.action == action) {
+                    Some(b) => b.keys = keys,
+                    None => problems.push(format!("[keys.{}] {} is not available in this scope", scope_name, action_name)),
+                }
+            }
+        }
+
+        let keymap = Keymap { bindings };
+        problems.extend(keymap.conflicts());
+        if problems.is_empty() {
+            Ok(keymap)
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Bindings which can't be told apart: same keys, or keys which start another binding
+    fn conflicts(&self) -> Vec<String> {
+        let all: Vec<(&Binding, &KeySequence)> = self.bindings.iter()
+            .flat_map(|b| b.keys.iter().map(move |k| (b, k)))
+            .collect();
+        let mut conflicts = vec![];
+        for (i, (a, ka)) in all.iter().enumerate() {
+            for (b, kb) in all[i + 1..].iter() {
+                if a.scope.overlaps(&b.scope) && (ka.starts_with(kb) || kb.starts_with(ka)) {
+                    conflicts.push(format!("keys {} of {}.{} conflict with {} of {}.{}",
+                        sequence_to_string(ka), a.scope.name(), a.action.name(),
+                        sequence_to_string(kb), b.scope.name(), b.action.name()));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Find the action of the keys pressed so far, `scopes` in order of precedence
+    pub fn resolve(&self, pressed: &[KeyChord], scopes: &[Scope]) -> Resolved {
+        let mut pending = false;
+        for scope in scopes {
+            for b in self.bindings.iter().filter(|b| b.scope == *scope) {
+                if b.keys.iter().any(|k| k.as_slice() == pressed) {
+                    return Resolved::Action(b.action);
+                }
+                pending |= b.keys.iter().any(|k| k.starts_with(pressed));
+            }
+        }
+        if pending { Resolved::Pending } else { Resolved::Unbound }
+    }
+
+    /// Keys of action like `d|<del>`, empty when unbound
+    pub fn keys(&self, scope: Scope, action: Action) -> String {
+        self.bindings.iter()
+            .find(|b| b.scope == scope && b.action == action)
+            .map(|b| b.keys.iter().map(|k| sequence_to_string(k)).collect::<Vec<_>>().join("|"))
+            .unwrap_or_default()
+    }
+
+    /// Help lines `keys - description` of all bound actions of scope,
+    /// tab switching keys are one line
+    pub fn help(&self, scope: Scope) -> Vec<String> {
+        let mut lines: Vec<String> = self.bindings.iter()
+            .filter(|b| b.scope == scope && !b.keys.is_empty() && !b.description.is_empty())
+            .filter(|b| !matches!(b.action, Action::Tab(_) | Action::NextTab))
+            .map(|b| format!("{} - {}", self.keys(scope, b.action), b.description))
+            .collect();
+        if scope == Scope::Global {
+            let tabs: Vec<String> = (1..=6).map(|t| self.keys(scope, Action::Tab(t))).filter(|k| !k.is_empty())
+                .chain([self.keys(scope, Action::NextTab)].into_iter().filter(|k| !k.is_empty()))
+                .collect();
+            lines.push(format!("{} - switch tabs", tabs.join("|")));
+        }
+        lines
+    }
+
+    /// (scope, action, keys, description) of all actions with a description, bound or not
+    pub fn commands(&self) -> Vec<(Scope, Action, String, &'static str)> {
+        self.bindings.iter()
+            .filter(|b| !b.description.is_empty())
+            .map(|b| (b.scope, b.action, self.keys(b.scope, b.action), b.description))
+            .collect()
+    }
+
+    /// One line hint of some actions, e.g. for a block title
+    pub fn hint(&self, scope: Scope, actions: &[Action]) -> String {
+        self.bindings.iter()
+            .filter(|b| b.scope == scope && actions.contains(&b.action) && !b.keys.is_empty() && !b.description.is_empty())
+            .map(|b| format!("{} - {}", self.keys(scope, b.action), b.description))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+/// Keymap of the loaded config, bindings were validated when the config was loaded
+pub fn get() -> &'static Keymap {
+    KEYMAP.get_or_init(|| Keymap::new(&config::get().keys).unwrap_or_else(|_| Keymap::new(&KeysConfig::new()).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord::new(code, modifiers)
+    }
+
+    fn keys(config: &[(&str, &str, &[&str])]) -> KeysConfig {
+        let mut keys = KeysConfig::new();
+        for (scope, action, bound) in config {
+            let binding = KeyBinding::Many(bound.iter().map(|k| k.to_string()).collect());
+            keys.entry(scope.to_string()).or_default().insert(action.to_string(), binding);
+        }
+        keys
+    }
+
+    #[test]
+    fn parse_chords() {
+        assert_eq!(KeyChord::parse("q"), Ok(chord(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("K"), Ok(chord(KeyCode::Char('K'), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("shift+k"), Ok(chord(KeyCode::Char('K'), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("space"), Ok(chord(KeyCode::Char(' '), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("Ctrl+Alt+x"), Ok(chord(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        assert_eq!(KeyChord::parse("shift+up"), Ok(chord(KeyCode::Up, KeyModifiers::SHIFT)));
+        assert_eq!(KeyChord::parse("f5"), Ok(chord(KeyCode::F(5), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("+"), Ok(chord(KeyCode::Char('+'), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("ctrl++"), Ok(chord(KeyCode::Char('+'), KeyModifiers::CONTROL)));
+
+        assert_eq!(KeyChord::parse("f13"), Err("unknown key \"f13\"".to_string()));
+        assert_eq!(KeyChord::parse("hyper+x"), Err("unknown modifier \"hyper\" in \"hyper+x\"".to_string()));
+    }
+
+    #[test]
+    fn chords_match_key_events() {
+        let shifted = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from(shifted), KeyChord::parse("G").unwrap());
+        assert_eq!(KeyChord::parse("ctrl+p").unwrap().to_string(), "<ctrl>+p");
+        assert_eq!(KeyChord::parse("shift+down").unwrap().to_string(), "<shift>+<down>");
+    }
+
+    #[test]
+    fn parse_sequences() {
+        let g = chord(KeyCode::Char('g'), KeyModifiers::NONE);
+        assert_eq!(parse_sequence("g g"), Ok(vec![g, g]));
+        assert_eq!(parse_sequence("  g  "), Ok(vec![g]));
+        assert_eq!(parse_sequence(" "), Err("empty key".to_string()));
+        assert_eq!(parse_sequence("g nope"), Err("unknown key \"nope\"".to_string()));
+        assert_eq!(sequence_to_string(&parse_sequence("g shift+up").unwrap()), "g <shift>+<up>");
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        let keymap = Keymap::new(&KeysConfig::new()).unwrap();
+        assert!(keymap.conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicts_with_the_same_or_a_prefix() {
+        let problems = Keymap::new(&keys(&[("radio", "record", &["g"])])).err().unwrap();
+        assert_eq!(problems, vec!["keys g of radio.record conflict with g g of radio.first".to_string()]);
+
+        let problems = Keymap::new(&keys(&[("global", "quit", &["e"])])).err().unwrap();
+        assert!(problems.contains(&"keys e of global.quit conflict with e of radio.edit".to_string()), "{:?}", problems);
+
+        // scopes which are never active together may share keys
+        assert!(Keymap::new(&keys(&[("history", "refresh", &["d"])])).is_ok());
+    }
+
+    #[test]
+    fn config_problems() {
+        let problems = Keymap::new(&keys(&[("radi", "quit", &["x"]), ("radio", "jump", &["x"]), ("radio", "quit", &["x"]), ("stats", "export", &["ctrl+"])])).err().unwrap();
+        assert_eq!(problems, vec![
+            "[keys.radi] unknown scope, expected one of global, radio, recently_heard, podcasts, history, stats, bookmarks, schedule, directory".to_string(),
+            "[keys.radio] unknown action \"jump\"".to_string(),
+            "[keys.radio] quit is not available in this scope".to_string(),
+            "[keys.stats] export: unknown key \"ctrl+\"".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn resolve_in_scope_order() {
+        let keymap = Keymap::new(&keys(&[("directory", "add", &["o"])])).unwrap();
+        let pressed = |keys: &str| parse_sequence(keys).unwrap();
+
+        assert_eq!(keymap.resolve(&pressed("e"), &[Scope::Global, Scope::Radio]), Resolved::Action(Action::Edit));
+        assert_eq!(keymap.resolve(&pressed("e"), &[Scope::Global, Scope::RecentlyHeard, Scope::Radio]), Resolved::Action(Action::Export));
+        assert_eq!(keymap.resolve(&pressed("g"), &[Scope::Global, Scope::Radio]), Resolved::Pending);
+        assert_eq!(keymap.resolve(&pressed("g g"), &[Scope::Global, Scope::Radio]), Resolved::Action(Action::First));
+        assert_eq!(keymap.resolve(&pressed("q"), &[Scope::Global, Scope::Directory]), Resolved::Action(Action::Quit));
+        assert_eq!(keymap.resolve(&pressed("o"), &[Scope::Global, Scope::Directory]), Resolved::Action(Action::Add));
+        assert_eq!(keymap.resolve(&pressed("a"), &[Scope::Global, Scope::Directory]), Resolved::Unbound);
+        assert_eq!(keymap.resolve(&pressed("x"), &[Scope::Global, Scope::Radio]), Resolved::Unbound);
+    }
+
+    #[test]
+    fn hints_follow_the_bindings() {
+        let keymap = Keymap::new(&keys(&[("recently_heard", "copy_title", &["y"])])).unwrap();
+        assert_eq!(keymap.hint(Scope::RecentlyHeard, &[Action::CopyTitle, Action::Export]), "y - copy selected song title, e - export recently heard as CSV");
+        assert_eq!(keymap.hint(Scope::Directory, &[Action::Select, Action::Add, Action::Search]), "<enter>|p - preview, a - add, /|s - search");
+    }
+}
@@ -0,0 +1,217 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{layout::Rect, text::{Line, Span}, widgets::{ListState, Paragraph}, Frame};
+
+use crate::{keymap::Action, theme};
+
+/// Whether `action` moves the selection of a list, counts apply to these
+pub fn is_motion(action: Action) -> bool {
+    matches!(action, Action::Up | Action::Down | Action::PageUp | Action::PageDown
+        | Action::First | Action::Last | Action::SearchNext | Action::SearchPrevious)
+}
+
+/// Move the selection of a list with `len` rows of which `page` are visible.
+/// A single up/down step wraps around, counted and paged moves stop at the ends,
+/// first/last with a count go to that row like `10G`
+pub fn navigate(state: &mut ListState, len: usize, page: usize, action: Action, count: Option<usize>) {
+    if len == 0 {
+        return;
+    }
+    let selected = state.selected().unwrap_or_default().min(len - 1);
+    let n = count.unwrap_or(1);
+    let page = page.max(1);
+    let selected = match (action, count) {
+        (Action::Down, None) => if selected >= len - 1 { 0 } else { selected + 1 },
+        (Action::Up, None) => if selected == 0 { len - 1 } else { selected - 1 },
+        (Action::Down, Some(_)) => selected.saturating_add(n).min(len - 1),
+        (Action::Up, Some(_)) => selected.saturating_sub(n),
+        (Action::PageDown, _) => selected.saturating_add(n.saturating_mul(page)).min(len - 1),
+        (Action::PageUp, _) => selected.saturating_sub(n.saturating_mul(page)),
+        (Action::First | Action::Last, Some(row)) => row.clamp(1, len) - 1,
+        (Action::First, None) => 0,
+        (Action::Last, None) => len - 1,
+        _ => selected,
+    };
+    state.select(Some(selected));
+}
+
+/// Incremental search in the titles of a list, `/` opens the prompt, `n`/`N` repeat the last query
+#[derive(Default)]
+pub struct ListSearch {
+    /// query being typed, `Some` while the prompt is open
+    input: Option<String>,
+    /// selection when the prompt was opened, <esc> goes back to it
+    origin: Option<usize>,
+    /// last confirmed query
+    query: String,
+    found: bool,
+}
+
+impl ListSearch {
+    pub fn is_active(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn start(&mut self, state: &ListState) {
+        self.input = Some(String::new());
+        self.origin = state.selected();
+        self.found = true;
+    }
+
+    /// Key typed into the open prompt, the selection follows the query as it is typed
+    pub fn handle_key(&mut self, key: KeyEvent, titles: &[String], state: &mut ListState) {
+        let input = match self.input.as_mut() {
+            Some(i) => i,
+            None => return,
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.input = None;
+                state.select(self.origin);
+                return;
+            },
+            KeyCode::Enter => {
+                // empty query repeats the previous one
+                if !input.is_empty() {
+                    self.query = input.clone();
+                }
+                self.input = None;
+                return;
+            },
+            KeyCode::Backspace => { input.pop(); },
+            KeyCode::Char(c) => input.push(c),
+            _ => return,
+        }
+        let input = input.clone();
+        let origin = self.origin.unwrap_or_default();
+        self.found = input.is_empty() || match find(titles, &input, origin, true) {
+            Some(i) => {
+                state.select(Some(i));
+                true
+            },
+            None => false,
+        };
+        if input.is_empty() {
+            state.select(self.origin);
+        }
+    }
+
+    /// Select the `count`th next (or previous) row matching the last query
+    pub fn repeat(&mut self, titles: &[String], state: &mut ListState, forward: bool, count: Option<usize>) {
+        if self.query.is_empty() {
+            return;
+        }
+        for _ in 0..count.unwrap_or(1) {
+            let selected = state.selected().unwrap_or_default();
+            let start = if forward { selected + 1 } else { selected + titles.len().saturating_sub(1) };
+            match find(titles, &self.query, start, forward) {
+                Some(i) => state.select(Some(i)),
+                None => break,
+            }
+        }
+    }
+
+    /// Prompt on the bottom border of the list in `area`
+    pub fn render(&self, area: Rect, f: &mut Frame) {
+        if let Some(input) = self.input.as_ref() {
+            let theme = theme::get();
+            let style = if self.found { theme.highlight } else { theme.error };
+            let prompt = Line::from(vec![Span::styled(format!("/{}", input), style)]);
+            let width = std::cmp::min(area.width.saturating_sub(2), input.chars().count() as u16 + 2);
+            f.render_widget(Paragraph::new(prompt), Rect::new(area.x + 1, area.y + area.height.saturating_sub(1), width, 1));
+            f.set_cursor(area.x + 2 + input.chars().count() as u16, area.y + area.height.saturating_sub(1));
+        }
+    }
+}
+
+/// First row from `start` in direction whose title contains `query` ignoring case, wrapping around
+fn find(titles: &[String], query: &str, start: usize, forward: bool) -> Option<usize> {
+    let len = titles.len();
+    if len == 0 {
+        return None;
+    }
+    let query = query.to_lowercase();
+    (0..len)
+        .map(|i| if forward { (start + i) % len } else { (start % len + len - i) % len })
+        .find(|i| titles[*i].to_lowercase().contains(&query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(selected: usize, len: usize, action: Action, count: Option<usize>) -> Option<usize> {
+        let mut state = ListState::default().with_selected(Some(selected));
+        navigate(&mut state, len, 10, action, count);
+        state.selected()
+    }
+
+    fn titles(titles: &[&str]) -> Vec<String> {
+        titles.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn single_steps_wrap_around() {
+        assert_eq!(moved(3, 5, Action::Down, None), Some(4));
+        assert_eq!(moved(4, 5, Action::Down, None), Some(0));
+        assert_eq!(moved(0, 5, Action::Up, None), Some(4));
+    }
+
+    #[test]
+    fn counted_and_paged_moves_stop_at_the_ends() {
+        assert_eq!(moved(3, 50, Action::Down, Some(10)), Some(13));
+        assert_eq!(moved(45, 50, Action::Down, Some(10)), Some(49));
+        assert_eq!(moved(3, 50, Action::Up, Some(10)), Some(0));
+        assert_eq!(moved(3, 50, Action::PageDown, None), Some(13));
+        assert_eq!(moved(3, 50, Action::PageDown, Some(2)), Some(23));
+        assert_eq!(moved(45, 50, Action::PageDown, None), Some(49));
+        assert_eq!(moved(5, 50, Action::PageUp, None), Some(0));
+    }
+
+    #[test]
+    fn first_and_last_take_a_row() {
+        assert_eq!(moved(3, 50, Action::First, None), Some(0));
+        assert_eq!(moved(3, 50, Action::Last, None), Some(49));
+        assert_eq!(moved(3, 50, Action::Last, Some(10)), Some(9));
+        assert_eq!(moved(3, 50, Action::First, Some(80)), Some(49));
+    }
+
+    #[test]
+    fn selection_beyond_a_shrunk_list_is_clamped() {
+        assert_eq!(moved(9, 5, Action::Up, Some(1)), Some(3));
+        let mut state = ListState::default();
+        navigate(&mut state, 0, 10, Action::Down, None);
+        assert_eq!(state.selected(), None);
+    }
+
+    #[test]
+    fn find_wraps_and_ignores_case() {
+        let list = titles(&["Morning News", "Jazz", "news at noon", "Sports"]);
+        assert_eq!(find(&list, "NEWS", 1, true), Some(2));
+        assert_eq!(find(&list, "news", 3, true), Some(0));
+        assert_eq!(find(&list, "news", 1, false), Some(0));
+        assert_eq!(find(&list, "news", 0, false), Some(0));
+        assert_eq!(find(&list, "weather", 0, true), None);
+        assert_eq!(find(&[], "news", 0, true), None);
+    }
+
+    #[test]
+    fn search_repeats_the_confirmed_query() {
+        let list = titles(&["Morning News", "Jazz", "news at noon", "Sports"]);
+        let mut state = ListState::default().with_selected(Some(0));
+        let mut search = ListSearch::default();
+        search.start(&state);
+        for c in "news".chars() {
+            search.handle_key(KeyEvent::from(KeyCode::Char(c)), &list, &mut state);
+        }
+        assert_eq!(state.selected(), Some(0));
+        search.handle_key(KeyEvent::from(KeyCode::Enter), &list, &mut state);
+        assert!(!search.is_active());
+
+        search.repeat(&list, &mut state, true, None);
+        assert_eq!(state.selected(), Some(2));
+        search.repeat(&list, &mut state, true, Some(2));
+        assert_eq!(state.selected(), Some(2));
+        search.repeat(&list, &mut state, false, None);
+        assert_eq!(state.selected(), Some(0));
+    }
+}
@@ -1,46 +1,96 @@
+mod bookmarks_model;
+mod cli;
+mod control;
+mod csv;
+mod history_model;
+mod keymap;
+mod list_navigation;
+mod mouse;
+mod mpris;
+mod palette;
+mod playback;
 mod player_engine;
+mod radio_directory_model;
 mod radio_model;
+mod schedule_model;
+mod scheduler;
 mod podcasts_model;
 mod config;
 mod entity;
 mod event_handler;
 mod widgets;
 mod data_layer;
+mod stats_model;
+mod streaming;
+mod theme;
 mod ui_models;
 
 use entity::channel;
 use migration::{Migrator, MigratorTrait};
-use ui_models::ChannelItem;
-use std::io::stdout;
+use ui_models::{ChannelItem, DirectoryStation};
+use std::{io::stdout, time::{Duration, Instant}};
 use color_eyre::eyre;
-use crossterm::{terminal::{EnterAlternateScreen, enable_raw_mode, disable_raw_mode, LeaveAlternateScreen}, execute, event::{DisableMouseCapture, KeyCode}, ExecutableCommand};
+use crossterm::{terminal::{EnterAlternateScreen, enable_raw_mode, disable_raw_mode, LeaveAlternateScreen}, execute, event::{DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, KeyModifiers}};
+use bookmarks_model::BookmarksModel;
 use event_handler::Event;
+use history_model::HistoryModel;
+use keymap::{Action, KeyChord, Resolved, Scope};
+use mouse::{ClickTracker, Mouse, MouseInput};
+use palette::{Command, Entry, Outcome, Palette};
+use playback::{Playback, PlaybackSource, PlayerCommand};
 use podcasts_model::PodcastsModel;
-use radio_model::RadioModel;
+use radio_model::{RadioConnection, RadioModel};
+use schedule_model::ScheduleModel;
+use scheduler::{ScheduledConnection, Scheduler};
+use stats_model::StatsModel;
+use streaming::relay::StreamRelay;
+use url2audio::player_engine::Playing;
+use widgets::now_playing::NowPlaying;
 use ratatui::{Terminal, prelude::{CrosstermBackend, Backend, Layout, Direction}, Frame, widgets::{Block, Borders, ListState, Tabs}};
-use ratatui::layout::Constraint;
+use ratatui::layout::{Constraint, Rect};
 use rss::Channel;
 use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, DbErr, EntityTrait};
-use tokio::sync::mpsc::{self, UnboundedReceiver};
-use data_layer::{data_provider::DataProvider, listening_state_data_layer::ListeningStateDataLayer};
+use tokio::sync::{mpsc::{self, UnboundedReceiver}, oneshot};
+use data_layer::{bookmark_data_layer::BookmarkDataLayer, data_provider::DataProvider, listening_history_data_layer::ListeningHistoryDataLayer, listening_state_data_layer::ListeningStateDataLayer, listening_stats_data_layer::ListeningStatsDataLayer, radio_station_data_layer::RadioStationDataLayer};
 
+/// Digits not followed by a motion within this time are taken as keys of their own
+const COUNT_TIMEOUT: Duration = Duration::from_millis(500);
+
+const TABS: [&str; 6] = ["[1] Radio", "[2] Podcasts", "[3] History", "[4] Stats", "[5] Bookmarks", "[6] Schedule"];
 
 pub struct App {
     radio_model: RadioModel,
     podcasts_model: PodcastsModel,
+    history_model: HistoryModel,
+    stats_model: StatsModel,
+    bookmarks_model: BookmarksModel,
+    schedule_model: ScheduleModel,
+    scheduler: Scheduler,
+    playback: Playback,
     active_tab: usize,
+    pending_keys: Vec<KeyChord>,
+    /// digits typed in a list before a motion, like `10` of `10j`
+    count_keys: Vec<KeyEvent>,
+    count_at: Instant,
+    clicks: ClickTracker,
+    /// where the tab bar was rendered, for clicks
+    tabs_area: Rect,
+    /// open command palette, it takes all keys
+    palette: Option<Palette>,
 }
 
 impl App {
     pub fn ui(&mut self, f: &mut Frame) {
         let size = f.size();
+        f.render_widget(Block::default().style(theme::get().text), size);
 
         let vertical_chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Percentage(100)])
+            .constraints([Constraint::Length(3), Constraint::Percentage(100), Constraint::Length(1)])
             .split(size);
 
-        let tabs = Tabs::new(vec!["[1] Radio", "[2] Podcasts"])
+        self.tabs_area = vertical_chunks[0];
+        let tabs = Tabs::new(TABS.to_vec())
             .block(Block::default().title(format!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))).borders(Borders::ALL))
             .select(self.active_tab);
 
@@ -49,44 +99,419 @@ impl App {
         match self.active_tab {
             0 => self.radio_model.ui(vertical_chunks[1], f),
             1 => self.podcasts_model.ui(vertical_chunks[1], f),
+            2 => self.history_model.ui(vertical_chunks[1], f),
+            3 => self.stats_model.ui(vertical_chunks[1], f),
+            4 => self.bookmarks_model.ui(vertical_chunks[1], f),
+            5 => self.schedule_model.ui(vertical_chunks[1], f),
             _ => {}
         }
+
+        f.render_widget(self.now_playing(), vertical_chunks[2]);
+
+        if let Some(palette) = self.palette.as_mut() {
+            palette.ui(size, f);
+        }
+    }
+
+    fn now_playing(&self) -> NowPlaying {
+        let source = self.playback.source();
+        let p = self.playback.player_engine.read().unwrap();
+        let detail = match source.as_ref() {
+            Some(PlaybackSource::Radio { .. }) => p.icy_metadata().and_then(|m| m.stream_title).map(|t| format!("♪ {}", t)),
+            Some(PlaybackSource::Podcast(_)) => Some(format!("{} / {}", p.current_position_display(), p.duration_display())),
+            None => None,
+        };
+        NowPlaying {
+            playing: if source.is_some() { p.is_playing() } else { Playing::Finished },
+            source,
+            detail,
+        }
     }
 
     async fn handle_events(&mut self, event: Event) -> std::io::Result<bool> {
+        if let Event::Tick = event {
+            self.radio_model.on_tick().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.scheduler.tick(&mut self.radio_model);
+            self.schedule_model.running = self.scheduler.running_status();
+        }
+        // checked on renders too, ticks may be far apart
+        if !self.count_keys.is_empty() && self.pending_keys.is_empty() && self.count_at.elapsed() >= COUNT_TIMEOUT && self.flush_count().await? {
+            return Ok(true);
+        }
         if let Event::Key(key) = event {
-        match key.code {
-            KeyCode::Char('q') => {
+            if let Some(palette) = self.palette.as_mut() {
+                return match palette.handle_key(key) {
+                    Some(outcome) => self.palette_outcome(outcome).await,
+                    None => Ok(false),
+                };
+            }
+            let captured = match self.active_tab {
+                0 => self.radio_model.captures_input(),
+                1 => self.podcasts_model.captures_input(),
+                5 => self.schedule_model.captures_input(),
+                _ => false,
+            };
+            if captured {
+                match self.active_tab {
+                    0 => { self.radio_model.handle_events(key).await?; },
+                    1 => { self.podcasts_model.handle_events(key).await?; },
+                    _ => { self.schedule_model.handle_events(key).await?; },
+                }
+                return Ok(false);
+            }
+            return self.handle_key(key).await;
+        }
+        if let Event::Mouse(event) = event {
+            if let Some(mouse) = self.clicks.input(event) {
+                if let Some(palette) = self.palette.as_mut() {
+                    return match palette.handle_mouse(mouse) {
+                        Some(outcome) => self.palette_outcome(outcome).await,
+                        None => Ok(false),
+                    };
+                }
+                self.handle_mouse(mouse).await?;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Clicks on the tab bar switch tabs, everything else goes to the active tab
+    async fn handle_mouse(&mut self, mouse: Mouse) -> std::io::Result<()> {
+        if mouse.is_in(self.tabs_area) {
+            if mouse.input == MouseInput::Click {
+                if let Some(tab) = self.tab_at(mouse.column) {
+                    self.set_active_tab(tab).await;
+                }
+            }
+            return Ok(());
+        }
+        match self.active_tab {
+            0 => self.radio_model.handle_mouse(mouse).await,
+            1 => self.podcasts_model.handle_mouse(mouse).await,
+            2 => self.history_model.handle_mouse(mouse).await,
+            4 => self.bookmarks_model.handle_mouse(mouse).await,
+            5 => self.schedule_model.handle_mouse(mouse).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Tab whose title is at `column`, titles are padded by a space and divided by `│`
+    fn tab_at(&self, column: u16) -> Option<usize> {
+        let mut x = self.tabs_area.x + 1;
+        for (i, title) in TABS.iter().enumerate() {
+            let width = title.chars().count() as u16 + 2;
+            if column >= x && column < x + width {
+                return Some(i);
+            }
+            x += width + 1;
+        }
+        None
+    }
+
+    /// Keymap scopes of the active tab, after the global one
+    fn tab_scopes(&self) -> &'static [Scope] {
+        match self.active_tab {
+            0 => self.radio_model.key_scopes(),
+            1 => &[Scope::Podcasts],
+            2 => &[Scope::History],
+            3 => &[Scope::Stats],
+            4 => &[Scope::Bookmarks],
+            _ => &[Scope::Schedule],
+        }
+    }
+
+    /// Lists of the active tab take count prefixes
+    fn takes_count(&self) -> bool {
+        match self.active_tab {
+            0 => !self.radio_model.show_directory,
+            1 => true,
+            _ => false,
+        }
+    }
+
+    /// Digits before a motion are collected as its count, otherwise keys go to the keymap
+    async fn handle_key(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        if self.pending_keys.is_empty() && self.takes_count() && (key.modifiers - KeyModifiers::SHIFT).is_empty() {
+            if let KeyCode::Char(c @ '0'..='9') = key.code {
+                if c != '0' || !self.count_keys.is_empty() {
+                    self.count_keys.push(key);
+                    self.count_at = Instant::now();
+                    return Ok(false);
+                }
+            }
+        }
+        if self.count_keys.is_empty() {
+            return self.resolve_key(key).await;
+        }
+
+        let mut pressed = self.pending_keys.clone();
+        pressed.push(key.into());
+        match keymap::get().resolve(&pressed, &self.key_scopes()) {
+            Resolved::Pending => self.pending_keys = pressed,
+            Resolved::Action(action) if list_navigation::is_motion(action) => {
+                self.pending_keys.clear();
+                let count: String = self.count_keys.drain(..).filter_map(|k| match k.code {
+                    KeyCode::Char(c) => Some(c),
+                    _ => None,
+                }).collect();
+                let count = count.parse::<usize>().ok();
+                match self.active_tab {
+                    0 => self.radio_model.handle_motion(action, count),
+                    _ => self.podcasts_model.handle_motion(action, count),
+                }
+            },
+            _ => {
+                // no motion follows, keys of a broken off sequence are dropped like without a count
+                self.pending_keys.clear();
+                if self.flush_count().await? {
+                    return Ok(true);
+                }
+                return self.resolve_key(key).await;
+            },
+        }
+        Ok(false)
+    }
+
+    /// Digits which got no motion are keys of their own, e.g. `2` switches tabs
+    async fn flush_count(&mut self) -> std::io::Result<bool> {
+        for key in std::mem::take(&mut self.count_keys) {
+            if self.resolve_key(key).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Global scope first, then those of the active tab
+    fn key_scopes(&self) -> Vec<Scope> {
+        [Scope::Global].iter().chain(self.tab_scopes()).copied().collect()
+    }
+
+    /// Resolve key with the keymap, keys of multi key bindings are collected in `pending_keys`
+    async fn resolve_key(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        let keymap = keymap::get();
+        let scopes = self.key_scopes();
+        self.pending_keys.push(key.into());
+        let mut resolved = keymap.resolve(&self.pending_keys, &scopes);
+        if resolved == Resolved::Unbound && self.pending_keys.len() > 1 {
+            // sequence broken off, the key may start a new one
+            self.pending_keys = vec![key.into()];
+            resolved = keymap.resolve(&self.pending_keys, &scopes);
+        }
+        match resolved {
+            Resolved::Pending => {},
+            Resolved::Action(action) => {
+                self.pending_keys.clear();
+                return self.handle_action(action).await;
+            },
+            Resolved::Unbound => {
+                self.pending_keys.clear();
+                // the radio directory handles its own keys
+                if self.active_tab == 0 {
+                    self.radio_model.handle_events(key).await?;
+                }
+            },
+        }
+        Ok(false)
+    }
+
+    async fn handle_action(&mut self, action: Action) -> std::io::Result<bool> {
+        match action {
+            // pause the shared player also from the other player tab
+            Action::TogglePause if (self.active_tab == 0 && self.playback.is_podcast()) || (self.active_tab == 1 && self.playback.is_radio()) => {
+                self.toggle_pause();
+            },
+            Action::Quit => {
                 self.podcasts_model.on_quit().await;
                 return Ok(true);
             },
-            KeyCode::Char('1') => {
-                self.active_tab = 0;
+            Action::Tab(tab) => {
+                self.set_active_tab(tab.clamp(1, 6) - 1).await;
             },
-            KeyCode::Char('2') => {
-                self.active_tab = 1;
+            Action::NextTab => {
+                self.set_active_tab((self.active_tab + 1) % 6).await;
+            },
+            Action::Palette => {
+                self.open_palette();
             },
-            KeyCode::Tab => {
-                self.active_tab = (self.active_tab + 1) % 2;
-            }
             _ => {
                 match self.active_tab {
-                    0 => { self.radio_model.handle_events(key).await?; },
-                    1 => { self.podcasts_model.handle_events(key).await?; },
+                    0 => self.radio_model.handle_action(action).await?,
+                    1 => self.podcasts_model.handle_action(action).await?,
+                    2 => self.history_model.handle_action(action).await?,
+                    3 => self.stats_model.handle_action(action).await?,
+                    4 => self.bookmarks_model.handle_action(action).await?,
+                    5 => self.schedule_model.handle_action(action).await?,
                     _ => {}
                 }
-            }
+            },
         }
+        Ok(false)
+    }
+
+    /// Palette over actions of all tabs, channels, episodes and stations,
+    /// episodes are added once loaded
+    fn open_palette(&mut self) {
+        let mut entries = palette::action_entries(keymap::get(), self.tab_scopes());
+        entries.extend(self.podcasts_model.podcasts_collection.iter()
+            .map(|c| Entry::channel(c.id, c.title.clone().unwrap_or_default())));
+        entries.extend(self.radio_model.streams_collection.iter()
+            .map(|s| Entry::station(s.clone(), radio_model::station_title(s))));
+        let mut palette = Palette::new(entries);
+        palette.loading = true;
+        self.palette = Some(palette);
+        self.podcasts_model.load_all_items();
+    }
+
+    async fn palette_outcome(&mut self, outcome: Outcome) -> std::io::Result<bool> {
+        self.palette = None;
+        match outcome {
+            Outcome::Close => Ok(false),
+            Outcome::Execute(command) => self.execute(command).await,
+        }
+    }
+
+    /// Run a palette command through the same paths as the keys and lists
+    async fn execute(&mut self, command: Command) -> std::io::Result<bool> {
+        match command {
+            Command::Action(scope, action) => {
+                if let Some(tab) = scope_tab(scope).filter(|t| *t != self.active_tab) {
+                    self.set_active_tab(tab).await;
+                }
+                if matches!(scope, Scope::Radio | Scope::RecentlyHeard) {
+                    self.radio_model.show_directory = false;
+                }
+                if scope == Scope::RecentlyHeard {
+                    self.radio_model.active_list_state = 1;
+                }
+                return self.handle_action(action).await;
+            },
+            Command::Channel(id) => {
+                self.set_active_tab(1).await;
+                if let Some(index) = self.podcasts_model.podcasts_collection.iter().position(|c| c.id == id) {
+                    self.podcasts_model.list_state_channels.select(Some(index));
+                    self.podcasts_model.active_list_state = 0;
+                    self.podcasts_model.handle_action(Action::Select).await?;
+                }
+            },
+            Command::Episode(item) => self.podcasts_model.resume_item(*item),
+            Command::Station(station) => {
+                self.set_active_tab(0).await;
+                self.radio_model.play_station(*station);
+            },
         }
         Ok(false)
     }
+
+    /// Control shared player from outside of the tabs (media keys, MPRIS)
+    /// Why `command` would do nothing, reported to control clients
+    fn check_command(&self, command: &PlayerCommand) -> Result<(), String> {
+        let radio = self.playback.is_radio();
+        let podcast = self.playback.is_podcast();
+        match command {
+            _ if !radio && !podcast => Err("nothing is playing".to_string()),
+            PlayerCommand::Next | PlayerCommand::Previous if !radio => Err("next and previous are only supported for radio".to_string()),
+            PlayerCommand::Seek(_) | PlayerCommand::SetPosition(_) if !podcast => Err("seek is only supported for podcasts".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn player_command(&mut self, command: PlayerCommand) -> std::io::Result<()> {
+        let playing = self.playback.player_engine.read().unwrap().is_playing();
+        match command {
+            PlayerCommand::PlayPause => self.toggle_pause(),
+            PlayerCommand::Play if playing == Playing::Paused => self.toggle_pause(),
+            PlayerCommand::Pause if playing == Playing::Playing => self.toggle_pause(),
+            PlayerCommand::Stop if self.playback.is_radio() => self.radio_model.stop(),
+            // podcasts keep their position
+            PlayerCommand::Stop if playing == Playing::Playing => self.toggle_pause(),
+            PlayerCommand::Seek(offset) if self.playback.is_podcast() => {
+                let p = self.playback.player_engine.read().unwrap();
+                p.seek((p.current_position() + offset).max(0.0));
+            },
+            PlayerCommand::SetPosition(position) if self.playback.is_podcast() => {
+                self.playback.player_engine.read().unwrap().seek(position.max(0.0));
+            },
+            PlayerCommand::Next if self.playback.is_radio() => self.radio_model.play_adjacent(1),
+            PlayerCommand::Previous if self.playback.is_radio() => self.radio_model.play_adjacent(-1),
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn toggle_pause(&mut self) {
+        if self.playback.is_podcast() {
+            self.podcasts_model.toggle_pause();
+        } else if self.playback.is_radio() {
+            self.radio_model.toggle_pause();
+        }
+    }
+
+    async fn refresh_schedule(&mut self) -> Result<(), DbErr> {
+        self.scheduler.reload().await?;
+        self.schedule_model.set_jobs(self.scheduler.jobs.clone(), self.radio_model.streams_collection.clone());
+        Ok(())
+    }
+
+    async fn set_active_tab(&mut self, tab: usize) {
+        self.active_tab = tab;
+        match tab {
+            2 => { let _ = self.history_model.reload().await; },
+            3 => { let _ = self.stats_model.reload().await; },
+            5 => { let _ = self.refresh_schedule().await; },
+            _ => {}
+        }
+    }
+}
+
+/// Tab where the bindings of scope apply, global ones apply everywhere
+fn scope_tab(scope: Scope) -> Option<usize> {
+    match scope {
+        Scope::Global => None,
+        Scope::Radio | Scope::RecentlyHeard | Scope::Directory => Some(0),
+        Scope::Podcasts => Some(1),
+        Scope::History => Some(2),
+        Scope::Stats => Some(3),
+        Scope::Bookmarks => Some(4),
+        Scope::Schedule => Some(5),
+    }
 }
 
 pub enum AsyncAction {
     Channel(Channel), // remove?
     ChannelAdded(i32),
     RefreshChannelsList,
-    WriteListeningState(ChannelItem)
+    WriteListeningState(ChannelItem),
+    /// (item, media position) playback of item started
+    StartListeningSession(ChannelItem, f32),
+    /// (media position) playback of active item stopped
+    EndListeningSession(f32),
+    /// (item, media position) switch to podcasts and continue item
+    ResumeItem(ChannelItem, f32),
+    /// (item, media position, note)
+    AddBookmark(ChannelItem, f32, Option<String>),
+    DeleteBookmark(i32),
+    RefreshBookmarks,
+    /// radio directory search finished
+    DirectoryResults(Result<Vec<DirectoryStation>, String>),
+    /// schedule jobs changed
+    RefreshSchedule,
+    /// (source, media position) source stopped because the other tab took the player
+    PlaybackInterrupted(PlaybackSource, f64),
+    /// media keys / MPRIS / control socket, the control socket waits for the result
+    PlayerCommand(PlayerCommand, Option<oneshot::Sender<Result<(), String>>>),
+    /// reload data which may have been changed outside of the app
+    Refresh,
+    /// feed refresh failed, or some of its episodes could not be stored
+    FeedProblem(String),
+    /// radio stream connected or failed to
+    RadioConnected(RadioConnection),
+    /// (episode, channel title) of all channels for the command palette
+    PaletteEpisodes(Result<Vec<(ChannelItem, String)>, String>),
+    /// (stream url, relay) reconnect attempt of the playing live stream finished
+    StreamReconnected(String, Result<StreamRelay, String>),
+    /// stream of a due schedule job connected or failed to
+    ScheduledJobConnected(Box<ScheduledConnection>),
 }
 
 async fn init_data(db: &DatabaseConnection) -> Result<(), DbErr>{
@@ -124,28 +549,76 @@ async fn init_data(db: &DatabaseConnection) -> Result<(), DbErr>{
 
 #[tokio::main]
 pub async fn main() -> eyre::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (config, args) = config::Config::load(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    config::init(config);
+    if args.first().is_some_and(|a| a == "ctl") {
+        let ok = control::ctl(&args[1..]).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            false
+        });
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+    if args.first().is_some_and(|a| cli::is_command(a)) {
+        std::process::exit(cli::run(&args).await);
+    }
+
     let (action_tx, mut action_rx) = mpsc::unbounded_channel::<AsyncAction>();
 
     let mut list_streams_state = ListState::default();
     list_streams_state.select(Some(0));
 
-    let home = std::env::var("HOME").unwrap();
-    let connection = std::env::var("DATABASE_URL").unwrap_or(format!("sqlite://{}/.librecast.db?mode=rwc", home));
-    let db: DatabaseConnection = Database::connect(connection).await?;
+    let db: DatabaseConnection = Database::connect(config::database_url()).await?;
 
     Migrator::up(&db, None).await?;
     init_data(&db).await?;
+    let playback = Playback::new(action_tx.clone());
     // run tui
     let mut app = App {
         // streams_collection: vec!["https://stream.daskoimladja.com:9000/stream".to_string(), "https://live.radio.fake".to_string(), "test".to_string()],
         active_tab: 0,
-        radio_model: Default::default(),
-        podcasts_model: PodcastsModel::new(db.clone(), action_tx)
+        radio_model: RadioModel::new(db.clone(), action_tx.clone(), playback.clone()),
+        podcasts_model: PodcastsModel::new(db.clone(), action_tx.clone(), playback.clone()),
+        history_model: HistoryModel::new(db.clone(), action_tx.clone()),
+        stats_model: StatsModel::new(db.clone()),
+        bookmarks_model: BookmarksModel::new(action_tx.clone()),
+        schedule_model: ScheduleModel::new(db.clone(), action_tx.clone()),
+        scheduler: Scheduler::new(db.clone(), action_tx.clone()),
+        playback,
+        pending_keys: vec![],
+        count_keys: vec![],
+        count_at: Instant::now(),
+        clicks: Default::default(),
+        tabs_area: Rect::default(),
+        palette: None,
+    };
+    if let Err(e) = RadioStationDataLayer::import_legacy(&db).await {
+        eprintln!("Unable to import legacy radio list: {}", e);
+    }
+    app.radio_model.reload().await?;
+    app.refresh_schedule().await?;
+    // media keys are optional, e.g. there is no session bus over ssh
+    let _mpris = match mpris::start(app.playback.clone(), action_tx.clone()).await {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            eprintln!("MPRIS is not available: {}", e);
+            None
+        },
+    };
+    let _control = match control::ControlServer::start(config::control_socket_path(), app.playback.clone(), action_tx.clone()) {
+        Ok(server) => Some(server),
+        Err(e) => {
+            eprintln!("Control socket is not available: {}", e);
+            None
+        },
     };
-    app.radio_model.streams_collection = config::load()?;
     app.podcasts_model.podcasts_collection = app.podcasts_model.get_channels_from_db().await?;
+    let _ = action_tx.send(AsyncAction::RefreshBookmarks);
 
-    stdout().execute(EnterAlternateScreen)?;
+    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
     enable_raw_mode()?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     terminal.clear()?;
@@ -174,7 +647,8 @@ async fn run_app<B: Backend>(
     db: &DatabaseConnection
 ) -> eyre::Result<()> {
     loop {
-        let mut events = event_handler::EventHandler::new();
+        let ui_config = &config::get().ui;
+        let mut events = event_handler::EventHandler::new(Duration::from_millis(ui_config.tick_rate_ms), Duration::from_millis(ui_config.render_rate_ms));
 
         loop {
             let event = events.next().await?;
@@ -187,45 +661,157 @@ async fn run_app<B: Backend>(
             let res = app.handle_events(event).await;
 
             if let Ok(a) = action_rx.try_recv() {
-                match a {
-                    AsyncAction::Channel(_channel) => {},
-                    AsyncAction::ChannelAdded(id) => {
-                        let mut items = DataProvider::get_items_from_db(id, &db.clone()).await?;
-                        let items_len = items.len();
-                        app.podcasts_model.items_collection.clear();
-                        app.podcasts_model.items_collection.append(&mut items);
-
-                        let select_item = match app.podcasts_model.active_item.as_ref() {
-                            Some(ai) => {
-                                ai.channel_id != id
-                            },
-                            None => true,
-                        };
-
-                        if items_len > 0 && select_item{
-                            app.podcasts_model.list_state_items.select(Some(0));
-                        }
-                        app.podcasts_model.waiting_message = None;
-                    },
-                    AsyncAction::RefreshChannelsList =>{
-                        app.podcasts_model.podcasts_collection = app.podcasts_model.get_channels_from_db().await?;
-                    },
-                    AsyncAction::WriteListeningState(channel_item) => {
-                        match channel_item.listening_state.as_ref() {
-                            Some(ls) => {
-                                let _ = ListeningStateDataLayer::update_current_time_for_item(db.clone(),
-                                    channel_item.enclosure, channel_item.channel_id, ls.time).await;
-                            },
-                            None => {},
-                        }
-                    },
-                }
+                handle_action(app, a, db).await?;
             }
 
             if let Ok(true) = res {
+                // flush pending writes (listening state, sessions) before exit
+                while let Ok(a) = action_rx.try_recv() {
+                    handle_action(app, a, db).await?;
+                }
                 return Ok(());
             }
 
         }
     }
 }
+
+async fn handle_action(app: &mut App, action: AsyncAction, db: &DatabaseConnection) -> eyre::Result<()> {
+    match action {
+        AsyncAction::Channel(_channel) => {},
+        AsyncAction::ChannelAdded(id) => {
+            let mut items = DataProvider::get_items_from_db(id, &db.clone()).await?;
+            let items_len = items.len();
+            app.podcasts_model.items_collection.clear();
+            app.podcasts_model.items_collection.append(&mut items);
+
+            let select_item = match app.podcasts_model.active_item.as_ref() {
+                Some(ai) => {
+                    ai.channel_id != id
+                },
+                None => true,
+            };
+
+            if items_len > 0 && select_item{
+                app.podcasts_model.list_state_items.select(Some(0));
+            }
+            app.podcasts_model.waiting_message = None;
+        },
+        AsyncAction::RefreshChannelsList =>{
+            app.podcasts_model.podcasts_collection = app.podcasts_model.get_channels_from_db().await?;
+        },
+        AsyncAction::WriteListeningState(channel_item) => {
+            match channel_item.listening_state.as_ref() {
+                Some(ls) => {
+                    let _ = ListeningStateDataLayer::update_current_time_for_item(db.clone(),
+                        channel_item.episode_key, channel_item.enclosure, channel_item.channel_id, ls.time).await;
+                },
+                None => {},
+            }
+        },
+        AsyncAction::StartListeningSession(channel_item, position) => {
+            let id = ListeningHistoryDataLayer::start_session(db.clone(),
+                channel_item.episode_key, channel_item.enclosure, channel_item.channel_id, position).await?;
+            app.podcasts_model.listening_session_id = Some(id);
+        },
+        AsyncAction::EndListeningSession(position) => {
+            if let Some(id) = app.podcasts_model.listening_session_id.take() {
+                let session = ListeningHistoryDataLayer::end_session(db.clone(), id, position).await?;
+                if let Some(session) = session {
+                    if let Some(ended_at) = session.ended_at {
+                        ListeningStatsDataLayer::add_listening_time(db.clone(), session.episode_key, session.channel_item_enclosure,
+                            session.channel_id, session.started_at, ended_at).await?;
+                    }
+                }
+            }
+        },
+        AsyncAction::ResumeItem(channel_item, position) => {
+            app.active_tab = 1;
+            if let Some(index) = app.podcasts_model.podcasts_collection.iter().position(|c| c.id == channel_item.channel_id) {
+                app.podcasts_model.list_state_channels.select(Some(index));
+                app.podcasts_model.active_list_state = 1;
+            }
+            let channel_id = channel_item.channel_id;
+            app.podcasts_model.play_item(channel_item, Some(position));
+            let mut items = DataProvider::get_items_from_db(channel_id, db).await?;
+            app.podcasts_model.items_collection.clear();
+            app.podcasts_model.items_collection.append(&mut items);
+            let active = app.podcasts_model.active_item.as_ref().map(|a| a.episode_key.clone());
+            let index = app.podcasts_model.items_collection.iter().position(|i| Some(&i.episode_key) == active.as_ref());
+            app.podcasts_model.list_state_items.select(index.or(Some(0)));
+        },
+        AsyncAction::AddBookmark(channel_item, time, note) => {
+            BookmarkDataLayer::add_bookmark(db.clone(), channel_item.episode_key, channel_item.enclosure, channel_item.channel_id, time, note).await?;
+            refresh_bookmarks(app, db).await?;
+        },
+        AsyncAction::DeleteBookmark(id) => {
+            BookmarkDataLayer::delete_bookmark(db.clone(), id).await?;
+            refresh_bookmarks(app, db).await?;
+        },
+        AsyncAction::RefreshBookmarks => {
+            refresh_bookmarks(app, db).await?;
+        },
+        AsyncAction::DirectoryResults(results) => {
+            app.radio_model.directory.set_results(results);
+        },
+        AsyncAction::RefreshSchedule => {
+            app.refresh_schedule().await?;
+        },
+        AsyncAction::PlaybackInterrupted(source, position) => {
+            // source may have taken the player back meanwhile
+            match source {
+                PlaybackSource::Radio { .. } if !app.playback.is_radio() => app.radio_model.on_interrupted(),
+                PlaybackSource::Podcast(_) if !app.playback.is_podcast() => app.podcasts_model.on_interrupted(position),
+                _ => {},
+            }
+        },
+        AsyncAction::PlayerCommand(command, reply) => {
+            let result = app.check_command(&command);
+            if result.is_ok() {
+                app.player_command(command).await?;
+            }
+            if let Some(reply) = reply {
+                let _ = reply.send(result);
+            }
+        },
+        AsyncAction::FeedProblem(message) => {
+            app.podcasts_model.error = Some(message);
+            app.podcasts_model.waiting_message = None;
+        },
+        AsyncAction::RadioConnected(connection) => {
+            app.radio_model.on_connected(connection).await?;
+        },
+        AsyncAction::PaletteEpisodes(items) => {
+            // only once, the load of a palette closed meanwhile may arrive too
+            if let Some(palette) = app.palette.as_mut().filter(|p| p.loading) {
+                palette.loading = false;
+                match items {
+                    Ok(items) => palette.add_entries(items.into_iter().map(|(item, channel)| Entry::episode(item, channel)).collect()),
+                    Err(e) => palette.error = Some(format!("episodes not loaded: {}", e)),
+                }
+            }
+        },
+        AsyncAction::StreamReconnected(url, result) => {
+            app.playback.player_engine.write().unwrap().finish_reconnect(&url, result);
+        },
+        AsyncAction::ScheduledJobConnected(connection) => {
+            app.scheduler.on_connected(*connection, &mut app.radio_model).await?;
+            app.schedule_model.set_jobs(app.scheduler.jobs.clone(), app.radio_model.streams_collection.clone());
+            app.schedule_model.running = app.scheduler.running_status();
+        },
+        AsyncAction::Refresh => {
+            app.radio_model.reload().await?;
+            app.podcasts_model.podcasts_collection = app.podcasts_model.get_channels_from_db().await?;
+            app.refresh_schedule().await?;
+            refresh_bookmarks(app, db).await?;
+        },
+    }
+    Ok(())
+}
+
+async fn refresh_bookmarks(app: &mut App, db: &DatabaseConnection) -> Result<(), DbErr> {
+    let bookmarks = BookmarkDataLayer::get_bookmarks(db).await?;
+    app.podcasts_model.bookmarks = bookmarks.clone();
+    app.bookmarks_model.set_bookmarks(bookmarks);
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{layout::Rect, widgets::ListState};
+
+use crate::keymap::Action;
+
+/// Second click on the same cell within this time is a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseInput {
+    Click,
+    DoubleClick,
+    /// moved with left button held
+    Drag,
+    /// left button let go
+    Release,
+    ScrollUp,
+    ScrollDown,
+}
+
+/// Mouse input at a terminal cell
+#[derive(Clone, Copy, Debug)]
+pub struct Mouse {
+    pub input: MouseInput,
+    pub column: u16,
+    pub row: u16,
+}
+
+impl Mouse {
+    pub fn is_in(&self, area: Rect) -> bool {
+        self.column >= area.x && self.column < area.x + area.width
+            && self.row >= area.y && self.row < area.y + area.height
+    }
+
+    /// Row of a bordered list under the mouse, `offset` is its first visible row
+    pub fn list_index(&self, area: Rect, offset: usize) -> Option<usize> {
+        let inner = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), area.height.saturating_sub(2));
+        self.is_in(inner).then(|| offset + (self.row - inner.y) as usize)
+    }
+
+    /// Position in `0.0..=1.0` of the mouse along a bordered gauge,
+    /// the column is clamped so dragging past the ends still works
+    pub fn gauge_ratio(&self, area: Rect) -> f64 {
+        let width = area.width.saturating_sub(2).max(1);
+        let x = self.column.clamp(area.x + 1, area.x + width) - area.x - 1;
+        x as f64 / (width - 1).max(1) as f64
+    }
+
+    /// Click selects a row of the bordered list in `area` with `len` rows, wheel moves the selection.
+    /// Returns the action the input stands for: `Select` on double click, `Up`/`Down` when scrolled
+    pub fn list_action(&self, area: Rect, state: &mut ListState, len: usize) -> Option<Action> {
+        if !self.is_in(area) {
+            return None;
+        }
+        match self.input {
+            MouseInput::Click | MouseInput::DoubleClick => {
+                let index = self.list_index(area, state.offset()).filter(|i| *i < len)?;
+                state.select(Some(index));
+                (self.input == MouseInput::DoubleClick).then_some(Action::Select)
+            },
+            MouseInput::ScrollUp if len > 0 => Some(Action::Up),
+            MouseInput::ScrollDown if len > 0 => Some(Action::Down),
+            _ => None,
+        }
+    }
+}
+
+/// Turns terminal mouse events into `Mouse`, telling clicks from double clicks
+#[derive(Default)]
+pub struct ClickTracker {
+    last_click: Option<(Instant, u16, u16)>,
+}
+
+impl ClickTracker {
+    pub fn input(&mut self, event: MouseEvent) -> Option<Mouse> {
+        let (column, row) = (event.column, event.row);
+        let input = match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double = self.last_click.is_some_and(|(at, c, r)| at.elapsed() < DOUBLE_CLICK && c == column && r == row);
+                // a third click starts over
+                self.last_click = if double { None } else { Some((Instant::now(), column, row)) };
+                if double { MouseInput::DoubleClick } else { MouseInput::Click }
+            },
+            MouseEventKind::Drag(MouseButton::Left) => MouseInput::Drag,
+            MouseEventKind::Up(MouseButton::Left) => MouseInput::Release,
+            MouseEventKind::ScrollUp => MouseInput::ScrollUp,
+            MouseEventKind::ScrollDown => MouseInput::ScrollDown,
+            _ => return None,
+        };
+        Some(Mouse { input, column, row })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    fn at(input: MouseInput, column: u16, row: u16) -> Mouse {
+        Mouse { input, column, row }
+    }
+
+    fn event(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE }
+    }
+
+    #[test]
+    fn list_index_is_inside_the_border() {
+        let area = Rect::new(2, 3, 10, 5);
+        assert_eq!(at(MouseInput::Click, 3, 4).list_index(area, 0), Some(0));
+        assert_eq!(at(MouseInput::Click, 10, 6).list_index(area, 7), Some(9));
+        // on the borders
+        assert_eq!(at(MouseInput::Click, 3, 3).list_index(area, 0), None);
+        assert_eq!(at(MouseInput::Click, 3, 7).list_index(area, 0), None);
+        assert_eq!(at(MouseInput::Click, 2, 4).list_index(area, 0), None);
+        assert_eq!(at(MouseInput::Click, 11, 4).list_index(area, 0), None);
+    }
+
+    #[test]
+    fn gauge_ratio_is_clamped_past_the_ends() {
+        let area = Rect::new(10, 0, 13, 3);
+        assert_eq!(at(MouseInput::Click, 11, 1).gauge_ratio(area), 0.0);
+        assert_eq!(at(MouseInput::Click, 16, 1).gauge_ratio(area), 0.5);
+        assert_eq!(at(MouseInput::Click, 20, 1).gauge_ratio(area), 0.9);
+        assert_eq!(at(MouseInput::Drag, 0, 1).gauge_ratio(area), 0.0);
+        assert_eq!(at(MouseInput::Drag, 22, 1).gauge_ratio(area), 1.0);
+        assert_eq!(at(MouseInput::Drag, 200, 1).gauge_ratio(area), 1.0);
+        // too narrow to have a position
+        assert_eq!(at(MouseInput::Drag, 5, 1).gauge_ratio(Rect::new(5, 0, 2, 3)), 0.0);
+    }
+
+    #[test]
+    fn list_action_selects_and_scrolls() {
+        let area = Rect::new(0, 0, 20, 6);
+        let mut state = ListState::default();
+        assert_eq!(at(MouseInput::Click, 5, 2).list_action(area, &mut state, 3), None);
+        assert_eq!(state.selected(), Some(1));
+        assert_eq!(at(MouseInput::DoubleClick, 5, 3).list_action(area, &mut state, 3), Some(Action::Select));
+        assert_eq!(state.selected(), Some(2));
+
+        // below the last row or outside the list
+        assert_eq!(at(MouseInput::DoubleClick, 5, 4).list_action(area, &mut state, 3), None);
+        assert_eq!(at(MouseInput::Click, 30, 2).list_action(area, &mut state, 3), None);
+        assert_eq!(state.selected(), Some(2));
+
+        assert_eq!(at(MouseInput::ScrollUp, 5, 0).list_action(area, &mut state, 3), Some(Action::Up));
+        assert_eq!(at(MouseInput::ScrollDown, 5, 5).list_action(area, &mut state, 3), Some(Action::Down));
+        assert_eq!(at(MouseInput::ScrollDown, 5, 5).list_action(area, &mut state, 0), None);
+        assert_eq!(at(MouseInput::Drag, 5, 2).list_action(area, &mut state, 3), None);
+    }
+
+    #[test]
+    fn second_click_on_the_same_cell_is_a_double_click() {
+        let mut clicks = ClickTracker::default();
+        let down = |column, row| event(MouseEventKind::Down(MouseButton::Left), column, row);
+        let input = |clicks: &mut ClickTracker, e| clicks.input(e).map(|m| m.input);
+
+        assert_eq!(input(&mut clicks, down(1, 1)), Some(MouseInput::Click));
+        assert_eq!(input(&mut clicks, event(MouseEventKind::Up(MouseButton::Left), 1, 1)), Some(MouseInput::Release));
+        assert_eq!(input(&mut clicks, down(1, 1)), Some(MouseInput::DoubleClick));
+        // a third click starts over
+        assert_eq!(input(&mut clicks, down(1, 1)), Some(MouseInput::Click));
+        assert_eq!(input(&mut clicks, down(2, 1)), Some(MouseInput::Click));
+        assert_eq!(input(&mut clicks, down(2, 1)), Some(MouseInput::DoubleClick));
+
+        clicks.last_click = Some((Instant::now() - DOUBLE_CLICK, 3, 3));
+        assert_eq!(input(&mut clicks, down(3, 3)), Some(MouseInput::Click));
+
+        assert_eq!(input(&mut clicks, event(MouseEventKind::Drag(MouseButton::Left), 4, 3)), Some(MouseInput::Drag));
+        assert_eq!(input(&mut clicks, event(MouseEventKind::ScrollUp, 4, 3)), Some(MouseInput::ScrollUp));
+        assert_eq!(input(&mut clicks, event(MouseEventKind::Down(MouseButton::Right), 4, 3)), None);
+        assert_eq!(input(&mut clicks, event(MouseEventKind::Moved, 4, 3)), None);
+    }
+}
@@ -0,0 +1,442 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use tokio::sync::mpsc::UnboundedSender;
+use url2audio::player_engine::Playing;
+use zbus::{interface, zvariant::{ObjectPath, OwnedValue, Value}, Connection, SignalContext};
+
+use crate::{playback::{Playback, PlaybackSource, PlayerCommand}, AsyncAction};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.librecast";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+/// How often player state is compared to send `PropertiesChanged`
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Position jump which is reported as `Seeked`
+const SEEK_TOLERANCE: f64 = 1.5;
+
+/// Register librecast as MPRIS media player on the session bus, so media keys,
+/// `playerctl` and desktop media widgets control the shared player.
+/// The returned connection keeps the service alive
+pub async fn start(playback: Playback, tx: UnboundedSender<AsyncAction>) -> zbus::Result<Connection> {
+    serve(zbus::connection::Builder::session()?, playback, tx).await
+}
+
+async fn serve(builder: zbus::connection::Builder<'_>, playback: Playback, tx: UnboundedSender<AsyncAction>) -> zbus::Result<Connection> {
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, MediaPlayer2)?
+        .serve_at(OBJECT_PATH, MprisPlayer { playback: playback.clone(), tx })?
+        .build()
+        .await?;
+
+    let iface_ref = connection.object_server().interface::<_, MprisPlayer>(OBJECT_PATH).await?;
+    tokio::spawn(async move {
+        let mut last = State::read(&playback);
+        let mut last_at = Instant::now();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let state = State::read(&playback);
+            let ctxt = iface_ref.signal_context();
+            let iface = iface_ref.get().await;
+            let res = async {
+                if state.status != last.status {
+                    iface.playback_status_changed(ctxt).await?;
+                }
+                if state.track != last.track {
+                    iface.metadata_changed(ctxt).await?;
+                }
+                if state.can_seek != last.can_seek {
+                    iface.can_seek_changed(ctxt).await?;
+                }
+                if state.can_go_next != last.can_go_next {
+                    iface.can_go_next_changed(ctxt).await?;
+                    iface.can_go_previous_changed(ctxt).await?;
+                }
+                if state.volume != last.volume {
+                    iface.volume_changed(ctxt).await?;
+                }
+                let expected = match last.status {
+                    "Playing" => last.position + last_at.elapsed().as_secs_f64(),
+                    _ => last.position,
+                };
+                if state.track == last.track && state.can_seek && (state.position - expected).abs() > SEEK_TOLERANCE {
+                    MprisPlayer::seeked(ctxt, micros(state.position)).await?;
+                }
+                zbus::Result::Ok(())
+            }.await;
+            if res.is_err() {
+                break;
+            }
+            last = state;
+            last_at = Instant::now();
+        }
+    });
+
+    Ok(connection)
+}
+
+fn micros(seconds: f64) -> i64 {
+    (seconds * 1_000_000.0) as i64
+}
+
+fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+    // only file descriptors can't be owned
+    value.into().try_into().unwrap()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Track {
+    id: String,
+    title: String,
+    artist: Option<String>,
+    url: Option<String>,
+    length: Option<f64>,
+}
+
+/// Player state as seen over MPRIS
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    status: &'static str,
+    track: Option<Track>,
+    position: f64,
+    can_seek: bool,
+    can_go_next: bool,
+    volume: f64,
+}
+
+impl State {
+    fn read(playback: &Playback) -> Self {
+        let status = playback.status();
+        let track = match status.source.as_ref() {
+            Some(PlaybackSource::Radio { station_id, name }) => Some(Track {
+                id: format!("/org/librecast/track/radio/{}", station_id.unwrap_or_default().unsigned_abs()),
+                title: status.stream_title.clone().unwrap_or(name.clone()),
+                artist: status.stream_title.as_ref().map(|_| name.clone()),
+                url: status.url.clone(),
+                length: None,
+            }),
+            Some(PlaybackSource::Podcast(item)) => Some(Track {
+                id: format!("/org/librecast/track/podcast/{}_{}", item.channel_id.unsigned_abs(), item.ordering.unsigned_abs()),
+                title: item.title.clone().unwrap_or("-".to_string()),
+                artist: None,
+                url: status.url.clone(),
+                length: Some(status.duration).filter(|d| *d > 0.0),
+            }),
+            None => None,
+        };
+        State {
+            status: match status.playing {
+                Playing::Finished => "Stopped",
+                Playing::Playing => "Playing",
+                Playing::Paused => "Paused",
+            },
+            track,
+            position: status.position,
+            can_seek: matches!(status.source, Some(PlaybackSource::Podcast(_))),
+            can_go_next: matches!(status.source, Some(PlaybackSource::Radio { .. })),
+            volume: status.volume as f64,
+        }
+    }
+}
+
+struct MediaPlayer2;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl MediaPlayer2 {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        env!("CARGO_PKG_NAME").to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec![]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Controls are sent to the app as `AsyncAction::PlayerCommand`,
+/// so tabs keep their bookkeeping (e.g. podcast listening sessions)
+struct MprisPlayer {
+    playback: Playback,
+    tx: UnboundedSender<AsyncAction>,
+}
+
+impl MprisPlayer {
+    fn send(&self, command: PlayerCommand) {
+        let _ = self.tx.send(AsyncAction::PlayerCommand(command, None));
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl MprisPlayer {
+    fn next(&self) {
+        self.send(PlayerCommand::Next);
+    }
+
+    fn previous(&self) {
+        self.send(PlayerCommand::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(PlayerCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(PlayerCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(PlayerCommand::Stop);
+    }
+
+    fn play(&self) {
+        self.send(PlayerCommand::Play);
+    }
+
+    /// `offset` in microseconds
+    fn seek(&self, offset: i64) {
+        self.send(PlayerCommand::Seek(offset as f64 / 1_000_000.0));
+    }
+
+    /// Ignored unless `track_id` is the current track
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let state = State::read(&self.playback);
+        if state.track.is_some_and(|t| t.id == track_id.as_str()) {
+            self.send(PlayerCommand::SetPosition(position as f64 / 1_000_000.0));
+        }
+    }
+
+    fn open_uri(&self, _uri: &str) -> zbus::fdo::Result<()> {
+        Err(zbus::fdo::Error::NotSupported("opening uris is not supported".to_string()))
+    }
+
+    #[zbus(signal)]
+    async fn seeked(ctxt: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        State::read(&self.playback).status.to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn set_rate(&self, _rate: f64) {}
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        match State::read(&self.playback).track {
+            Some(track) => {
+                metadata.insert("mpris:trackid".to_string(), owned(ObjectPath::try_from(track.id).unwrap()));
+                metadata.insert("xesam:title".to_string(), owned(track.title));
+                if let Some(artist) = track.artist {
+                    metadata.insert("xesam:artist".to_string(), owned(vec![artist]));
+                }
+                if let Some(url) = track.url {
+                    metadata.insert("xesam:url".to_string(), owned(url));
+                }
+                if let Some(length) = track.length {
+                    metadata.insert("mpris:length".to_string(), owned(micros(length)));
+                }
+            },
+            None => {
+                metadata.insert("mpris:trackid".to_string(), owned(ObjectPath::from_static_str_unchecked(NO_TRACK)));
+            },
+        }
+        metadata
+    }
+
+    /// Applied to live streams, podcasts always play at full volume
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.playback.player_engine.read().unwrap().get_volume() as f64
+    }
+
+    #[zbus(property)]
+    fn set_volume(&self, volume: f64) {
+        self.playback.player_engine.write().unwrap().set_volume(volume as f32);
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        micros(State::read(&self.playback).position)
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        State::read(&self.playback).can_go_next
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        State::read(&self.playback).can_go_next
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        State::read(&self.playback).can_seek
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader}, process::{Child, Command, Stdio}};
+
+    use futures::StreamExt;
+    use tokio::sync::mpsc;
+    use zbus::{fdo::PropertiesProxy, names::InterfaceName, Proxy};
+
+    use super::*;
+    use crate::config;
+
+    const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+    /// Private session bus, stopped on drop
+    struct Bus {
+        daemon: Child,
+        address: String,
+        dir: std::path::PathBuf,
+    }
+
+    impl Bus {
+        /// `None` when `dbus-daemon` isn't installed
+        fn start() -> Option<Bus> {
+            let dir = std::env::temp_dir().join(format!("librecast-dbus-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let config = dir.join("session.conf");
+            std::fs::write(&config, format!(r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path={}</listen>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#, dir.join("bus").display())).unwrap();
+            let mut daemon = Command::new("dbus-daemon")
+                .arg(format!("--config-file={}", config.display()))
+                .args(["--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+            Some(Bus { daemon, address: address.trim().to_string(), dir })
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_playback_status_and_emits_properties_changed() {
+        let bus = match Bus::start() {
+            Some(b) => b,
+            None => {
+                eprintln!("dbus-daemon is not installed, skipped");
+                return;
+            },
+        };
+        config::init(Default::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let playback = Playback::new(tx.clone());
+        let _service = serve(zbus::connection::Builder::address(bus.address.as_str()).unwrap(), playback.clone(), tx).await.unwrap();
+
+        let client = zbus::connection::Builder::address(bus.address.as_str()).unwrap().build().await.unwrap();
+        let player = Proxy::new(&client, BUS_NAME, OBJECT_PATH, PLAYER_INTERFACE).await.unwrap();
+        assert_eq!(player.get_property::<String>("PlaybackStatus").await.unwrap(), "Stopped");
+        assert!(!player.get_property::<bool>("CanGoNext").await.unwrap());
+        assert_eq!(player.get_property::<f64>("Volume").await.unwrap(), 1.0);
+        player.set_property("Volume", 0.5).await.unwrap();
+        assert_eq!(player.get_property::<f64>("Volume").await.unwrap(), 0.5);
+        // out of range values are clamped
+        player.set_property("Volume", 1.5).await.unwrap();
+        assert_eq!(player.get_property::<f64>("Volume").await.unwrap(), 1.0);
+
+        let properties = PropertiesProxy::builder(&client).destination(BUS_NAME).unwrap().path(OBJECT_PATH).unwrap().build().await.unwrap();
+        let mut changes = properties.receive_properties_changed().await.unwrap();
+        playback.begin(PlaybackSource::Radio { station_id: Some(3), name: "Test FM".to_string() });
+
+        let changed = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut changed: HashMap<String, OwnedValue> = HashMap::new();
+            // the proxy caches properties, so wait for all of them to change before reading them
+            while ["PlaybackStatus", "Metadata", "CanGoNext"].iter().any(|p| !changed.contains_key(*p)) {
+                let signal = changes.next().await.unwrap();
+                let args = signal.args().unwrap();
+                assert_eq!(args.interface_name, InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE));
+                for (name, value) in args.changed_properties {
+                    changed.insert(name.to_string(), value.try_to_owned().unwrap());
+                }
+            }
+            changed
+        }).await.expect("no PropertiesChanged for the new source");
+        assert_eq!(String::try_from(changed["PlaybackStatus"].try_clone().unwrap()).unwrap(), "Playing");
+        assert_eq!(player.get_property::<String>("PlaybackStatus").await.unwrap(), "Playing");
+        assert!(player.get_property::<bool>("CanGoNext").await.unwrap());
+
+        player.call_method("Next", &()).await.unwrap();
+        let command = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert!(matches!(command, Some(AsyncAction::PlayerCommand(PlayerCommand::Next, None))));
+    }
+}
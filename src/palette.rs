@@ -0,0 +1,344 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{layout::Rect, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph}, Frame};
+
+use crate::{entity::radio_station::Model as RadioStationModel, keymap::{Action, Keymap, Scope}, mouse::{Mouse, MouseInput}, theme, ui_models::ChannelItem};
+
+/// Width of the kind column
+const KIND_WIDTH: usize = 10;
+
+/// What a palette entry does, executed by the app like the keys would
+#[derive(Debug, Clone)]
+pub enum Command {
+    Action(Scope, Action),
+    /// open channel with id
+    Channel(i32),
+    Episode(Box<ChannelItem>),
+    Station(Box<RadioStationModel>),
+}
+
+pub struct Entry {
+    /// scope of actions, or what the entry is
+    kind: &'static str,
+    label: String,
+    /// keys of actions, channel of episodes, group of stations
+    detail: String,
+    command: Command,
+}
+
+impl Entry {
+    pub fn channel(id: i32, title: String) -> Self {
+        Entry { kind: "channel", label: title, detail: String::new(), command: Command::Channel(id) }
+    }
+
+    pub fn episode(item: ChannelItem, channel: String) -> Self {
+        Entry { kind: "episode", label: item.title.clone().unwrap_or(item.enclosure.clone()), detail: channel, command: Command::Episode(Box::new(item)) }
+    }
+
+    pub fn station(station: RadioStationModel, title: String) -> Self {
+        Entry { kind: "station", label: title, detail: station.group_name.clone().unwrap_or_default(), command: Command::Station(Box::new(station)) }
+    }
+
+    /// Text the query is matched against, the kind is part of it so `radio vol` finds the radio volume
+    fn haystack(&self) -> String {
+        format!("{} {}", self.kind, self.label)
+    }
+}
+
+/// Entries of all described actions, those of `first` scopes (the active tab) come first.
+/// Directory actions need the open directory and are left out
+pub fn action_entries(keymap: &Keymap, first: &[Scope]) -> Vec<Entry> {
+    let mut entries: Vec<(bool, Entry)> = keymap.commands().into_iter()
+        .filter(|(scope, action, _, _)| *action != Action::Palette && *scope != Scope::Directory)
+        .map(|(scope, action, keys, description)| {
+            let label = match action {
+                Action::Tab(_) => format!("go to {}", description),
+                _ => description.to_string(),
+            };
+            (!first.contains(&scope) && scope != Scope::Global, Entry { kind: scope.name(), label, detail: keys, command: Command::Action(scope, action) })
+        })
+        .collect();
+    entries.sort_by_key(|(later, _)| *later);
+    entries.into_iter().map(|(_, e)| e).collect()
+}
+
+pub enum Outcome {
+    Close,
+    Execute(Command),
+}
+
+/// Overlay which fuzzy filters actions, channels, episodes and stations by a typed query
+pub struct Palette {
+    entries: Vec<Entry>,
+    query: String,
+    /// (entry index, matched char positions in its haystack), best first
+    matches: Vec<(usize, Vec<usize>)>,
+    list_state: ListState,
+    area: Rect,
+    /// episodes are still being loaded
+    pub loading: bool,
+    pub error: Option<String>,
+}
+
+impl Palette {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        let mut palette = Palette {
+            entries,
+            query: String::new(),
+            matches: vec![],
+            list_state: ListState::default(),
+            area: Rect::default(),
+            loading: false,
+            error: None,
+        };
+        palette.filter();
+        palette
+    }
+
+    fn filter(&mut self) {
+        let mut matches: Vec<(i32, usize, Vec<usize>)> = self.entries.iter().enumerate()
+            .filter_map(|(i, e)| fuzzy_match(&e.haystack(), &self.query).map(|(score, positions)| (score, i, positions)))
+            .collect();
+        // stable, equal scores keep the entry order
+        matches.sort_by_key(|(score, _, _)| -score);
+        self.matches = matches.into_iter().map(|(_, i, positions)| (i, positions)).collect();
+        self.list_state = ListState::default();
+        self.list_state.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    /// Add entries loaded later, the selected entry stays selected
+    pub fn add_entries(&mut self, entries: Vec<Entry>) {
+        let selected = self.list_state.selected().and_then(|s| self.matches.get(s)).map(|(i, _)| *i);
+        self.entries.extend(entries);
+        self.filter();
+        if let Some(position) = selected.and_then(|i| self.matches.iter().position(|(m, _)| *m == i)) {
+            self.list_state.select(Some(position));
+        }
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or_default();
+        self.list_state.select(Some(if down { (selected + 1) % len } else { (selected + len - 1) % len }));
+    }
+
+    fn execute_selected(&self) -> Option<Outcome> {
+        let (index, _) = self.matches.get(self.list_state.selected()?)?;
+        Some(Outcome::Execute(self.entries[*index].command.clone()))
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Outcome> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(Outcome::Close),
+            KeyCode::Enter => return self.execute_selected(),
+            KeyCode::Up => self.move_selection(false),
+            KeyCode::Down | KeyCode::Tab => self.move_selection(true),
+            KeyCode::Char('p') if ctrl => self.move_selection(false),
+            KeyCode::Char('n') if ctrl => self.move_selection(true),
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.filter();
+            },
+            KeyCode::Backspace => {
+                // backspace on an empty query closes like in vim's command line
+                if self.query.pop().is_none() {
+                    return Some(Outcome::Close);
+                }
+                self.filter();
+            },
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.filter();
+            },
+            _ => {},
+        }
+        None
+    }
+
+    /// Click selects, double click executes, clicking outside closes
+    pub fn handle_mouse(&mut self, mouse: Mouse) -> Option<Outcome> {
+        if !mouse.is_in(self.area) {
+            return (mouse.input == MouseInput::Click).then_some(Outcome::Close);
+        }
+        // list rows are below the query, the rect is taken as bordered by `list_action`
+        let list_area = Rect::new(self.area.x, self.area.y + 1, self.area.width, self.area.height.saturating_sub(1));
+        match mouse.list_action(list_area, &mut self.list_state, self.matches.len())? {
+            Action::Select => self.execute_selected(),
+            action => {
+                self.move_selection(action == Action::Down);
+                None
+            },
+        }
+    }
+
+    pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
+        let w = std::cmp::min(90, rect.width);
+        let h = std::cmp::min(20, rect.height);
+        let area = Rect::new(rect.x + (rect.width - w) / 2, rect.y + rect.height.saturating_sub(h) / 4, w, h);
+        self.area = area;
+        f.render_widget(Clear, area);
+        let title = match (self.loading, self.error.as_ref()) {
+            (true, _) => format!("Commands {}/{} loading episodes...", self.matches.len(), self.entries.len()),
+            (false, Some(e)) => format!("Commands {}/{} {}", self.matches.len(), self.entries.len(), e),
+            (false, None) => format!("Commands {}/{}", self.matches.len(), self.entries.len()),
+        };
+        f.render_widget(Block::default().borders(Borders::ALL).title(title).title_bottom("<enter> - run, <esc> - close").style(theme.popup), area);
+
+        let query_area = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), 1);
+        f.render_widget(Paragraph::new(Line::from(vec![Span::styled(": ", theme.accent), Span::raw(self.query.clone())])), query_area);
+        f.set_cursor(query_area.x + 2 + self.query.chars().count() as u16, query_area.y);
+
+        // only the visible rows are built, the offset keeps the selection in view
+        let list_area = Rect::new(area.x + 1, area.y + 2, area.width.saturating_sub(2), area.height.saturating_sub(3));
+        let rows = list_area.height as usize;
+        let mut offset = self.list_state.offset();
+        if let Some(selected) = self.list_state.selected() {
+            offset = offset.clamp(selected.saturating_sub(rows.saturating_sub(1)), selected);
+        }
+        *self.list_state.offset_mut() = offset;
+
+        let width = area.width.saturating_sub(5) as usize;
+        let items: Vec<ListItem> = self.matches.iter().skip(offset).take(rows).map(|(index, positions)| {
+            let entry = &self.entries[*index];
+            let detail_style = if matches!(entry.command, Command::Action(..)) { theme.accent } else { theme.muted };
+            let detail_width = entry.detail.chars().count();
+            let label_width = width.saturating_sub(KIND_WIDTH + detail_width + 1);
+            let mut spans = highlight(entry.kind, positions, 0, theme.muted, theme.highlight);
+            spans.push(Span::styled(" ".repeat(KIND_WIDTH.saturating_sub(entry.kind.chars().count())), theme.muted));
+            // positions are in `kind label`, label chars start after the space
+            let label: String = entry.label.chars().take(label_width).collect();
+            let shown = label.chars().count();
+            spans.extend(highlight(&label, positions, entry.kind.chars().count() + 1, Style::default(), theme.highlight.add_modifier(Modifier::BOLD)));
+            spans.push(Span::raw(" ".repeat(width.saturating_sub(KIND_WIDTH + shown + detail_width))));
+            spans.push(Span::styled(entry.detail.clone(), detail_style));
+            ListItem::new(Line::from(spans))
+        }).collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        let mut visible_state = ListState::default().with_selected(self.list_state.selected().map(|s| s - offset));
+        f.render_stateful_widget(list, list_area, &mut visible_state);
+    }
+}
+
+/// Spans of `text`, chars whose position plus `offset` is in `positions` are `matched`,
+/// runs of chars with the same style are one span
+fn highlight(text: &str, positions: &[usize], offset: usize, style: Style, matched: Style) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_matched = positions.contains(&(i + offset));
+        if is_matched != run_matched && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), if run_matched { matched } else { style }));
+        }
+        run_matched = is_matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_matched { matched } else { style }));
+    }
+    spans
+}
+
+/// Score of `query` as a subsequence of `text` ignoring case, with the matched char positions.
+/// Consecutive matches and matches at word starts score higher, gaps lower.
+/// An empty query matches everything with the same score
+pub fn fuzzy_match(text: &str, query: &str) -> Option<(i32, Vec<usize>)> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    let word_start = |i: usize| i == 0 || !text[i - 1].is_alphanumeric();
+    let mut positions: Vec<usize> = vec![];
+    let mut score = 0;
+    let mut start = 0;
+    for (n, q) in query.iter().enumerate() {
+        let mut found = (start..text.len()).find(|i| text[*i] == *q)?;
+        // unless it continues the previous match, prefer a later word start if the rest still fits after it
+        if positions.last().map(|l| l + 1) != Some(found) && !word_start(found) {
+            if let Some(w) = (found + 1..text.len()).find(|i| text[*i] == *q && word_start(*i)) {
+                if is_subsequence(&query[n + 1..], &text[w + 1..]) {
+                    found = w;
+                }
+            }
+        }
+        score += match positions.last() {
+            Some(last) if *last + 1 == found => 8,
+            _ if word_start(found) => 6,
+            Some(last) => 1 - std::cmp::min(found - last - 1, 5) as i32,
+            None => 1 - std::cmp::min(found, 5) as i32,
+        };
+        positions.push(found);
+        start = found + 1;
+    }
+    Some((score, positions))
+}
+
+fn is_subsequence(query: &[char], text: &[char]) -> bool {
+    let mut text = text.iter();
+    query.iter().all(|q| text.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(text: &str, query: &str) -> Option<Vec<usize>> {
+        fuzzy_match(text, query).map(|(_, positions)| positions)
+    }
+
+    fn score(text: &str, query: &str) -> i32 {
+        fuzzy_match(text, query).unwrap().0
+    }
+
+    #[test]
+    fn query_is_a_subsequence_ignoring_case() {
+        assert_eq!(positions("Radio Volume up", "RVU"), Some(vec![0, 6, 13]));
+        assert_eq!(positions("podcasts refresh", "pod ref"), Some(vec![0, 1, 2, 9, 10, 11]));
+        assert_eq!(positions("radio", "radios"), None);
+        assert_eq!(positions("radio", "oi"), None);
+    }
+
+    #[test]
+    fn empty_query_matches_everything_alike() {
+        assert_eq!(fuzzy_match("radio volume up", ""), Some((0, vec![])));
+        assert_eq!(fuzzy_match("", " "), Some((0, vec![])));
+    }
+
+    #[test]
+    fn word_starts_are_preferred() {
+        // the `v` of `volume` instead of the one in `previous`
+        assert_eq!(positions("previous volume", "vol"), Some(vec![9, 10, 11]));
+        // unless the rest of the query only fits after the earlier match
+        assert_eq!(positions("previous volume", "vs"), Some(vec![3, 7]));
+    }
+
+    #[test]
+    fn consecutive_and_word_start_matches_score_higher() {
+        assert!(score("radio record", "rec") > score("radio refresh catalog", "rec"));
+        assert!(score("stats export", "exp") > score("next page", "exp"));
+        assert!(score("quit", "q") > score("seek", "k"));
+    }
+
+    #[test]
+    fn added_entries_keep_the_selection() {
+        let mut palette = Palette::new(vec![Entry::channel(1, "News".to_string()), Entry::channel(2, "Jazz".to_string())]);
+        palette.handle_key(KeyEvent::from(KeyCode::Down));
+        palette.add_entries(vec![Entry::channel(3, "Jazz Archive".to_string())]);
+        assert_eq!(palette.matches.len(), 3);
+        assert!(matches!(palette.handle_key(KeyEvent::from(KeyCode::Enter)), Some(Outcome::Execute(Command::Channel(2)))));
+    }
+
+    #[test]
+    fn highlight_joins_runs_of_one_style() {
+        let matched = Style::default().add_modifier(Modifier::BOLD);
+        let spans = highlight("volume", &[2, 3, 4, 7], 2, Style::default(), matched);
+        let runs: Vec<(&str, bool)> = spans.iter().map(|s| (s.content.as_ref(), s.style == matched)).collect();
+        assert_eq!(runs, vec![("vol", true), ("um", false), ("e", true)]);
+        assert!(highlight("", &[0], 0, Style::default(), matched).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::mpsc::UnboundedSender;
+use url2audio::player_engine::Playing;
+
+use crate::{player_engine::PlayerEngine, ui_models::ChannelItem, AsyncAction};
+
+/// What the shared player is playing
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaybackSource {
+    /// `station_id` is `None` for radio directory previews
+    Radio { station_id: Option<i32>, name: String },
+    Podcast(ChannelItem),
+}
+
+/// Snapshot of the shared player for remote controls
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackStatus {
+    pub source: Option<PlaybackSource>,
+    /// `Finished` when nothing is loaded
+    pub playing: Playing,
+    /// ICY title of radio stream
+    pub stream_title: Option<String>,
+    pub url: Option<String>,
+    /// seconds, 0 for radio
+    pub position: f64,
+    /// seconds, 0 when unknown
+    pub duration: f64,
+    pub volume: f32,
+}
+
+/// Remote control of the shared player (media keys, MPRIS)
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    /// relative seek in seconds
+    Seek(f64),
+    /// absolute position in seconds
+    SetPosition(f64),
+    /// next radio station
+    Next,
+    /// previous radio station
+    Previous,
+}
+
+/// App wide player driven by Radio and Podcasts tabs, so only one source plays at a time.
+///
+/// Starting a source of the other kind replaces the current one, its owner is
+/// told with `AsyncAction::PlaybackInterrupted` (e.g. podcasts save position).
+/// Switching between sources of the same kind is left to the owning tab.
+#[derive(Clone)]
+pub struct Playback {
+    pub player_engine: Arc<RwLock<PlayerEngine>>,
+    source: Arc<RwLock<Option<PlaybackSource>>>,
+    tx: UnboundedSender<AsyncAction>,
+}
+
+impl Playback {
+    pub fn new(tx: UnboundedSender<AsyncAction>) -> Self {
+        Playback {
+            player_engine: Default::default(),
+            source: Default::default(),
+            tx,
+        }
+    }
+
+    pub fn source(&self) -> Option<PlaybackSource> {
+        self.source.read().unwrap().clone()
+    }
+
+    pub fn is_radio(&self) -> bool {
+        matches!(*self.source.read().unwrap(), Some(PlaybackSource::Radio { .. }))
+    }
+
+    pub fn is_podcast(&self) -> bool {
+        matches!(*self.source.read().unwrap(), Some(PlaybackSource::Podcast(_)))
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        let source = self.source();
+        let p = self.player_engine.read().unwrap();
+        let podcast = matches!(source, Some(PlaybackSource::Podcast(_)));
+        PlaybackStatus {
+            playing: if source.is_some() { p.is_playing() } else { Playing::Finished },
+            stream_title: p.icy_metadata().and_then(|m| m.stream_title),
+            url: match source.as_ref() {
+                Some(PlaybackSource::Podcast(item)) => Some(item.enclosure.clone()),
+                Some(PlaybackSource::Radio { .. }) => p.stream_addr.clone(),
+                None => None,
+            },
+            position: if podcast { p.current_position() } else { 0.0 },
+            duration: if podcast { p.duration().max(0.0) } else { 0.0 },
+            volume: p.get_volume(),
+            source,
+        }
+    }
+
+    /// Take the player for `source`, called before the source is opened
+    pub fn begin(&self, source: PlaybackSource) {
+        let mut current = self.source.write().unwrap();
+        if let Some(previous) = current.take() {
+            if std::mem::discriminant(&previous) != std::mem::discriminant(&source) {
+                let position = self.player_engine.read().unwrap().current_position();
+                let _ = self.tx.send(AsyncAction::PlaybackInterrupted(previous, position));
+            }
+        }
+        *current = Some(source);
+    }
+
+    pub fn stop(&self) {
+        self.player_engine.write().unwrap().stop();
+        *self.source.write().unwrap() = None;
+    }
+}
@@ -1,8 +1,60 @@
+use std::time::{Duration, Instant};
+
 use url2audio::{player_engine::Playing, Player};
 
+use crate::{config, streaming::{icy::IcyMetadata, relay::StreamRelay}};
+
+/// Wait before the first reconnect, doubled for every further attempt
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Stream without audio from upstream this long is considered stalled
+const STALL_TIMEOUT: Duration = Duration::from_secs(15);
+/// Player state is ignored right after connecting, it still reflects the previous stream
+const CONNECT_GRACE: Duration = Duration::from_secs(5);
+/// Attempts are counted from zero again once a stream played this long
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+const VOLUME_STEP: f32 = 0.1;
+
+/// Connection state of a live stream opened with `open_relay`
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiveStatus {
+    Connected,
+    Reconnecting { attempt: u32, max_attempts: u32, reason: String },
+    Failed(String),
+}
+
+struct Supervision {
+    connected_at: Instant,
+    attempt: u32,
+    next_attempt: Option<Instant>,
+    reason: String,
+    failed: bool,
+    /// attempt is connecting off the UI task
+    reconnecting: bool,
+}
+
+impl Supervision {
+    fn new() -> Self {
+        Supervision {
+            connected_at: Instant::now(),
+            attempt: 0,
+            next_attempt: None,
+            reason: String::new(),
+            failed: false,
+            reconnecting: false,
+        }
+    }
+}
+
 pub struct PlayerEngine {
     pub stream_addr: Option<String>,
     pub player: Player,
+    relay: Option<StreamRelay>,
+    volume: f32,
+    supervision: Option<Supervision>,
+    max_reconnect_attempts: u32,
+    seek_forward_seconds: f64,
+    seek_backward_seconds: f64,
     // playing: bool
 }
 
@@ -18,6 +70,12 @@ impl PlayerEngine {
         PlayerEngine {
             stream_addr: None,
             player,
+            relay: None,
+            volume: 1.0,
+            supervision: None,
+            max_reconnect_attempts: config::reconnect_attempts(),
+            seek_forward_seconds: config::get().player.seek_forward_seconds,
+            seek_backward_seconds: config::get().player.seek_backward_seconds,
             // playing: false
         }
     }
@@ -25,11 +83,141 @@ impl PlayerEngine {
     pub fn open(&mut self, stream_addr: &str) -> Result<(), Box<dyn std::error::Error>> {
         self.player.open(stream_addr);
         self.stream_addr = Some(stream_addr.to_string());
+        self.relay = None;
+        self.supervision = None;
         self.player.play();
         // self.playing = true;
         Ok(())
     }
 
+    /// Open live radio stream through a local relay which reads ICY metadata.
+    /// The relay is started for `stream_addr` by the caller, off the UI task since connecting blocks
+    pub fn open_relay(&mut self, stream_addr: &str, relay: StreamRelay) {
+        relay.set_volume(self.volume);
+        self.player.open(&relay.local_url());
+        self.stream_addr = Some(stream_addr.to_string());
+        self.relay = Some(relay);
+        self.supervision = Some(Supervision::new());
+        self.player.play();
+    }
+
+    /// Watch stream opened with `open_relay` and schedule reconnects with exponential
+    /// backoff when it fails or stalls, called periodically (on tick).
+    /// Due attempts are taken with `start_reconnect`
+    pub fn supervise(&mut self) -> Option<LiveStatus> {
+        let problem = self.live_problem();
+        let max_attempts = self.max_reconnect_attempts;
+        let supervision = self.supervision.as_mut()?;
+        if supervision.failed {
+            return Some(LiveStatus::Failed(supervision.reason.clone()));
+        }
+
+        if supervision.next_attempt.is_none() {
+            let reason = match problem {
+                Some(reason) => reason,
+                None => return Some(LiveStatus::Connected),
+            };
+            if supervision.connected_at.elapsed() >= STABLE_AFTER {
+                supervision.attempt = 0;
+            }
+            supervision.reason = reason;
+            self.schedule_reconnect();
+        }
+
+        self.supervision.as_ref().map(|s| match s.failed {
+            true => LiveStatus::Failed(s.reason.clone()),
+            false => LiveStatus::Reconnecting { attempt: s.attempt, max_attempts, reason: s.reason.clone() },
+        })
+    }
+
+    fn live_problem(&self) -> Option<String> {
+        let relay = self.relay.as_ref()?;
+        let connected_at = self.supervision.as_ref()?.connected_at;
+        if !relay.is_running() {
+            return Some("stream disconnected".to_string());
+        }
+        if relay.idle_time() >= STALL_TIMEOUT {
+            return Some("stream stalled".to_string());
+        }
+        if connected_at.elapsed() < CONNECT_GRACE {
+            return None;
+        }
+        if let Some(e) = self.get_error() {
+            return Some(e);
+        }
+        (self.is_playing() == Playing::Finished).then(|| "stream ended".to_string())
+    }
+
+    fn schedule_reconnect(&mut self) {
+        let max_attempts = self.max_reconnect_attempts;
+        let supervision = match self.supervision.as_mut() {
+            Some(s) => s,
+            None => return,
+        };
+        if supervision.attempt >= max_attempts {
+            supervision.failed = true;
+            supervision.next_attempt = None;
+            supervision.reason = format!("{}, gave up after {} reconnect attempts", supervision.reason, supervision.attempt);
+            self.relay = None;
+            self.player.close();
+            return;
+        }
+        supervision.attempt += 1;
+        let delay = RECONNECT_BASE_DELAY.saturating_mul(1 << (supervision.attempt - 1).min(16)).min(RECONNECT_MAX_DELAY);
+        supervision.next_attempt = Some(Instant::now() + delay);
+    }
+
+    /// Stream url to reconnect when an attempt is due. The caller starts the relay
+    /// off the UI task since connecting blocks, and hands it to `finish_reconnect`
+    pub fn start_reconnect(&mut self) -> Option<String> {
+        let supervision = self.supervision.as_mut()?;
+        if supervision.failed || supervision.reconnecting || supervision.next_attempt.is_none_or(|at| Instant::now() < at) {
+            return None;
+        }
+        supervision.reconnecting = true;
+        self.stream_addr.clone()
+    }
+
+    /// Play relay of a reconnect attempt for `stream_addr`, ignored when another stream was opened meanwhile.
+    /// A running recording continues on the reconnected stream
+    pub fn finish_reconnect(&mut self, stream_addr: &str, result: Result<StreamRelay, String>) {
+        let supervision = match self.supervision.as_mut() {
+            Some(s) if s.reconnecting && self.stream_addr.as_deref() == Some(stream_addr) => s,
+            _ => return,
+        };
+        supervision.reconnecting = false;
+        match result {
+            Ok(relay) => {
+                relay.set_recorder(self.relay.take().and_then(|r| r.take_recorder()));
+                relay.set_volume(self.volume);
+                self.player.open(&relay.local_url());
+                self.player.play();
+                self.relay = Some(relay);
+                if let Some(s) = self.supervision.as_mut() {
+                    s.connected_at = Instant::now();
+                    s.next_attempt = None;
+                }
+            },
+            Err(e) => {
+                // failed relay keeps the recording until a connection succeeds
+                if let Some(s) = self.supervision.as_mut() {
+                    s.reason = e;
+                }
+                self.schedule_reconnect();
+            },
+        }
+    }
+
+    /// Relay of stream opened with `open_relay`
+    pub fn relay(&self) -> Option<&StreamRelay> {
+        self.relay.as_ref()
+    }
+
+    /// Station info and current title of stream opened with `open_relay`
+    pub fn icy_metadata(&self) -> Option<IcyMetadata> {
+        self.relay.as_ref().map(|r| r.metadata())
+    }
+
     // fn play(&mut self) -> Result<(), Box<dyn std::error::Error>> {
     //     if let Some(addr) = self.stream_addr.as_ref() {
     //         self.player.open(addr);
@@ -67,6 +255,16 @@ impl PlayerEngine {
         self.player.buffer_chunks()
     }
 
+    /// Close the stream, the player can open another one afterwards
+    pub fn stop(&mut self) {
+        // closed player can't open again
+        self.player.close();
+        self.player = Player::new();
+        self.stream_addr = None;
+        self.relay = None;
+        self.supervision = None;
+    }
+
     pub fn pause(&mut self) {
         // self.playing = false;
         self.player.pause()
@@ -81,19 +279,28 @@ impl PlayerEngine {
         self.player.seek(time);
     }
 
-    /// seek 30 seconds forward
+    /// seek `[player] seek_forward_seconds` forward
     pub fn seek_forward(&self) {
-        self.player.seek_relative(30.0);
+        self.player.seek_relative(self.seek_forward_seconds);
     }
 
-    /// seek 10 seconds backward
+    /// seek `[player] seek_backward_seconds` backward
     pub fn seek_backward(&self) {
-        self.player.seek_relative(-10.0);
+        self.player.seek_relative(-self.seek_backward_seconds);
     }
 
     pub fn get_volume(&self) -> f32 {
-        // self.sink.volume()
-        1.0
+        self.volume
+    }
+
+    /// Volume is applied by the relay, so it changes live streams.
+    /// Podcasts are played by url2audio directly, which has no gain control
+    pub fn set_volume(&mut self, volume: f32) {
+        // steps of 0.1 stay exact
+        self.volume = ((volume.clamp(0.0, 1.0) * 100.0).round()) / 100.0;
+        if let Some(relay) = self.relay.as_ref() {
+            relay.set_volume(self.volume);
+        }
     }
 
     pub fn get_error(&self) -> Option<String> {
@@ -101,17 +308,17 @@ impl PlayerEngine {
     }
 
     pub fn increase_volume(&mut self) {
-        // if self.get_volume() < 1.0 {
-        //     self.sink.set_volume(self.get_volume() + 0.1);
-        // }
+        self.set_volume(self.volume + VOLUME_STEP);
     }
 
     pub fn decrease_volume(&mut self) {
-        // if self.get_volume() > 0.0 {
-        //     let mut new_val = self.get_volume() - 0.1;
-        //     new_val = if new_val < 0.0 { 0.0 } else { new_val };
-        //     self.sink.set_volume(new_val);
-        // }
+        self.set_volume(self.volume - VOLUME_STEP);
     }
 
 }
+
+impl Drop for PlayerEngine {
+    fn drop(&mut self) {
+        self.player.close();
+    }
+}
@@ -1,16 +1,17 @@
-use std::{borrow::BorrowMut, io::ErrorKind, str::FromStr, sync::{Arc, RwLock}};
+use std::{collections::HashMap, io::ErrorKind, str::FromStr, sync::{Arc, RwLock}};
 
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style, Stylize}, text::{Line, Span}, widgets::{Block, Borders, List, ListState, Paragraph}, Frame};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, List, ListState, Paragraph}, Frame};
 use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
 use tokio::sync::mpsc::UnboundedSender;
 use url2audio::player_engine::Playing;
 
 use std::error::Error;
 use rss::Channel;
-use crate::{data_layer::{data_provider::DataProvider, listening_state_data_layer}, entity::channel::Entity as ChannelEntity, ui_models::{self, ListeningState}, widgets::{item_details::ItemDetails, open_dialog::{OpenDialog, OpenDialogState}, simple_list::SimpleList, timeline::Timeline, waiting_message_dialog::{WaitingMessageDialog, WaitingMessageDialogState}}, AsyncAction};
+use crate::{data_layer::{data_provider::DataProvider, listening_state_data_layer}, keymap::{self, Action, Scope}, entity::channel::Entity as ChannelEntity, list_navigation::{self, ListSearch}, mouse::{Mouse, MouseInput}, theme, ui_models::{self, ListeningState}, widgets::{item_details::ItemDetails, open_dialog::{OpenDialog, OpenDialogState}, simple_list::SimpleList, timeline::Timeline, waiting_message_dialog::{WaitingMessageDialog, WaitingMessageDialogState}}, AsyncAction};
 
 use crate::player_engine::PlayerEngine;
+use crate::playback::{Playback, PlaybackSource};
 use crate::entity::channel::Model as ChannelModel;
 
 pub struct PodcastsModel {
@@ -23,19 +24,39 @@ pub struct PodcastsModel {
     pub list_state_channels: ListState,
     pub list_state_items: ListState,
     pub active_list_state: usize,
+    listening_session_open: bool,
+    pub listening_session_id: Option<i32>,
     pub player_engine: Arc<RwLock<PlayerEngine>>,
+    playback: Playback,
     pub podcasts_collection: Vec<ChannelModel>,
     pub show_open_dialog: bool,
     tx: UnboundedSender<crate::AsyncAction>,
     pub waiting_dialog_state: WaitingMessageDialogState,
     pub waiting_message: Option<String>,
     pub open_dialog_state: OpenDialogState,
+    pub bookmarks: Vec<ui_models::Bookmark>,
+    /// position of the bookmark being added, `Some` while note dialog is open
+    bookmark_time: Option<f32>,
+    bookmark_dialog_state: OpenDialogState,
+    /// where channels, episodes, details and timeline were rendered, for mouse input
+    channels_area: Rect,
+    items_area: Rect,
+    details_area: Rect,
+    timeline_area: Rect,
+    /// lines the description of `details_key` episode is scrolled by
+    details_scroll: u16,
+    details_key: Option<String>,
+    /// left button went down on the timeline and wasn't let go yet, dragging seeks
+    seeking: bool,
+    search: ListSearch,
 }
 
 impl PodcastsModel {
-    pub fn new(db: DatabaseConnection, tx: UnboundedSender<crate::AsyncAction>) -> Self {
+    pub fn new(db: DatabaseConnection, tx: UnboundedSender<crate::AsyncAction>, playback: Playback) -> Self {
         let mut list_state_channels: ListState = Default::default();
         list_state_channels.select(Some(0));
+        let mut bookmark_dialog_state = OpenDialogState::default();
+        bookmark_dialog_state.set_hint("<optional note...>");
         Self {
             active_channel: Default::default(),
             active_item: Default::default(),
@@ -46,13 +67,27 @@ impl PodcastsModel {
             list_state_channels,
             list_state_items: Default::default(),
             active_list_state: 0,
-            player_engine: Default::default(),
+            listening_session_open: false,
+            listening_session_id: None,
+            player_engine: playback.player_engine.clone(),
+            playback,
             podcasts_collection: vec![],
             show_open_dialog: Default::default(),
             tx,
             waiting_dialog_state: Default::default(),
             waiting_message: None,
-            open_dialog_state: Default::default()
+            open_dialog_state: Default::default(),
+            bookmarks: vec![],
+            bookmark_time: None,
+            bookmark_dialog_state,
+            channels_area: Rect::default(),
+            items_area: Rect::default(),
+            details_area: Rect::default(),
+            timeline_area: Rect::default(),
+            details_scroll: 0,
+            details_key: None,
+            seeking: false,
+            search: Default::default(),
         }
     }
 
@@ -68,6 +103,7 @@ impl PodcastsModel {
     }
 
     pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
         let size = rect;
 
         let vertical_chunks = Layout::default()
@@ -86,12 +122,16 @@ impl PodcastsModel {
         let channels_chunk = chunks[0];
         let channel_items_chunk = chunks[1];
         let item_details_chunk = horizontal_chunks[1];
+        self.channels_area = channels_chunk;
+        self.items_area = channel_items_chunk;
+        self.details_area = item_details_chunk;
+        self.timeline_area = vertical_chunks[1];
 
         // list channels
-        let fg_color  = |i: usize| if self.active_list_state == i { ratatui::style::Color::Blue } else { ratatui::style::Color::DarkGray };
+        let focus = |i: usize| theme.focus(self.active_list_state == i);
 
         let list = List::new(self.podcasts_collection.clone().into_iter().map(|i| i.title.unwrap_or("-".to_string())))
-        .fg(fg_color(0))
+        .style(focus(0))
         .block(Block::default().borders(Borders::ALL))
             .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
             .highlight_symbol("> ")
@@ -103,10 +143,11 @@ impl PodcastsModel {
         let simple_list = SimpleList {
             items: &self.items_collection,
             active: &self.active_item,
-            fg_color: fg_color(1),
+            focused: self.active_list_state == 1,
         };
 
         f.render_stateful_widget(simple_list, channel_items_chunk, &mut self.list_state_items);
+        self.search.render(if self.active_list_state == 0 { channels_chunk } else { channel_items_chunk }, f);
 
         // item details
         let selected_episode = match self.list_state_items.selected() {
@@ -116,14 +157,27 @@ impl PodcastsModel {
             None => None,
         };
         let currently_playing = match (self.active_item.as_ref(), selected_episode) {
-            (Some(a), Some(b)) => a.enclosure == b.enclosure,
+            (Some(a), Some(b)) => a.episode_key == b.episode_key,
             (_, _) => false,
         };
 
+        let bookmarks = match selected_episode {
+            Some(e) => self.bookmarks.iter().filter(|b| b.channel_id == e.channel_id && b.episode_key == e.episode_key).collect(),
+            None => vec![],
+        };
+
+        let selected_key = selected_episode.map(|e| e.episode_key.clone());
+        if selected_key != self.details_key {
+            self.details_key = selected_key;
+            self.details_scroll = 0;
+        }
+
         let item_details = ItemDetails {
             currently_playing,
             item: &selected_episode,
-            fg_color: ratatui::style::Color::DarkGray
+            bookmarks,
+            focused: false,
+            scroll: self.details_scroll,
         };
         f.render_widget(item_details, item_details_chunk);
 
@@ -134,24 +188,51 @@ impl PodcastsModel {
             } else { 
                 "".to_string()
             };
+            let bookmarks: Vec<f64> = match self.active_item.as_ref() {
+                Some(a) => self.bookmarks.iter()
+                    .filter(|b| b.channel_id == a.channel_id && b.episode_key == a.episode_key)
+                    .map(|b| b.time as f64)
+                    .collect(),
+                None => vec![],
+            };
             let p = self.player_engine.read().unwrap();
-            let timeline = Timeline {
-                progress: p.current_position(),
-                progress_display: p.current_position_display(),
-                total: p.duration(),
-                total_display: p.duration_display(),
-                playing: p.is_playing(),
-                error: p.get_error(),
-                title,
-                buffer: &p.buffer_chunks()
+            // shared player may be playing radio
+            let timeline = if self.playback.is_podcast() {
+                Timeline {
+                    progress: p.current_position(),
+                    progress_display: p.current_position_display(),
+                    total: p.duration(),
+                    total_display: p.duration_display(),
+                    playing: p.is_playing(),
+                    error: p.get_error().or(self.error.clone()),
+                    title,
+                    buffer: &p.buffer_chunks(),
+                    bookmarks: &bookmarks,
+                }
+            } else {
+                Timeline {
+                    progress: 0.0,
+                    progress_display: String::new(),
+                    total: 0.0,
+                    total_display: String::new(),
+                    playing: Playing::Finished,
+                    error: self.error.clone(),
+                    title,
+                    buffer: &vec![],
+                    bookmarks: &[],
+                }
             };
             f.render_widget(timeline, vertical_chunks[1]);
         }
 
         // handle finished
-        {
+        let finished_at = {
             let p = self.player_engine.read().unwrap();
-            if p.is_playing() == Playing::Finished {
+            if self.playback.is_podcast() && p.is_playing() == Playing::Finished { Some(p.duration()) } else { None }
+        };
+        if let Some(duration) = finished_at {
+            self.end_listening_session(duration as f32);
+            {
                 match self.active_item.as_mut() {
                     Some(ai) => {
                         match ai.listening_state.as_mut() {
@@ -180,174 +261,263 @@ impl PodcastsModel {
             f.render_stateful_widget(open_dialog, size, &mut self.open_dialog_state);
         }
 
+        if self.bookmark_time.is_some() {
+            let bookmark_dialog = OpenDialog::new("Add bookmark".to_string()).enter_label("save bookmark");
+            f.render_stateful_widget(bookmark_dialog, size, &mut self.bookmark_dialog_state);
+        }
+
         if self.help_visible {
-            let w = 50;
-            let h = 10;
+            let keymap = keymap::get();
+            let lines: Vec<Line> = [Scope::Podcasts, Scope::Global].iter()
+                .flat_map(|s| keymap.help(*s))
+                .map(|l| Line::from(vec![Span::styled(l, Style::default())]))
+                .collect();
+            let w = std::cmp::min(50, size.width);
+            let h = std::cmp::min(lines.len() as u16 + 2, size.height);
             let x = (size.width - w) / 2;
             let y = (size.height - h) / 2;
-            let help_block = Block::default().borders(Borders::ALL).title("Help").bg(Color::DarkGray);
-
-            let mut lines = vec![];
-            let line = Line::from(vec![Span::styled("h - toggle help", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("1|2|<tab> - toggle tabs", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("<enter> - open and play stream", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("<space> - play/pause stream", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("+/- - volume up/down", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("o - add stream to collection", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("d|<del> - remove stream from collection", Style::default())]);
-            lines.push(line);
+            let help_block = Block::default().borders(Borders::ALL).title("Help").style(theme.popup);
 
             let help_paragraph = Paragraph::new(lines).block(help_block);
             let help_rect = Rect::new(x, y, w, h);
+            f.render_widget(Clear, help_rect);
             f.render_widget(help_paragraph, help_rect);
         }
     }
 
 
+    /// Keys of dialogs, everything else comes as `Action`
     pub async fn handle_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
         if self.show_open_dialog {
             self.handle_open_dialog_events(key)
+        } else if self.bookmark_time.is_some() {
+            self.handle_bookmark_dialog_events(key)
+        } else if self.search.is_active() {
+            let titles = self.list_titles();
+            let state = if self.active_list_state == 0 { &mut self.list_state_channels } else { &mut self.list_state_items };
+            self.search.handle_key(key, &titles, state);
+            Ok(false)
         } else {
-            match key.code {
-                KeyCode::Char('o') => {
-                    self.open_dialog_state.clear();
-                    self.show_open_dialog = true;
-                },
-                KeyCode::Char('r') => {
-                    self.items_collection.clear();
-                    let tx = self.tx.clone();
-                    let db = self.db.clone();
+            Ok(false)
+        }
+    }
 
-                    if let Some(selected) = self.list_state_channels.selected() {
-                        let selected_channel = self.podcasts_collection[selected].clone();
-                        if let Some(podcast_url) = selected_channel.link {
-                            self.waiting_message = Some("Fetching podcast info...".to_string());
-                            tokio::spawn(async move {
-                                match DataProvider::fetch_data(podcast_url, selected_channel.id, db).await {
-                                    Ok(channel_id) => {
-                                        tx.send(AsyncAction::ChannelAdded(channel_id)).map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string())).unwrap();
-                                        tx.send(AsyncAction::RefreshChannelsList).map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string())).unwrap();
-                                    },
-                                    Err(e) => {
-                                        // handle fetch error
-                                        println!("Err fetching {:#?}", e)
-                                    },
-                                };
-                            });
-                        }
-                    };
-                }
-                KeyCode::Char('.') => {
-                    if self.active_item.is_some() {
-                        let p = self.player_engine.read().unwrap();
-                        p.seek_forward();
-                    }
-                }
-                KeyCode::Char(',') => {
-                    if self.active_item.is_some() {
-                        let p = self.player_engine.read().unwrap();
-                        p.seek_backward();
-                    }
+    /// Click selects in a list, double click also opens the channel or plays the episode,
+    /// the wheel moves the selection or scrolls the details, clicking or dragging on the timeline seeks
+    pub async fn handle_mouse(&mut self, mouse: Mouse) -> std::io::Result<()> {
+        match mouse.input {
+            MouseInput::Click | MouseInput::DoubleClick => self.seeking = mouse.is_in(self.timeline_area),
+            MouseInput::Release => {
+                self.seeking = false;
+                return Ok(());
+            },
+            _ => {},
+        }
+        if self.seeking && mouse.input != MouseInput::ScrollUp && mouse.input != MouseInput::ScrollDown {
+            self.seek_to(mouse.gauge_ratio(self.timeline_area));
+            return Ok(());
+        }
+
+        if mouse.is_in(self.details_area) {
+            match mouse.input {
+                MouseInput::ScrollUp => self.details_scroll = self.details_scroll.saturating_sub(1),
+                MouseInput::ScrollDown => self.details_scroll = std::cmp::min(self.details_scroll + 1, self.description_lines()),
+                _ => {},
+            }
+            return Ok(());
+        }
+
+        let action = if mouse.is_in(self.channels_area) {
+            self.active_list_state = 0;
+            mouse.list_action(self.channels_area, &mut self.list_state_channels, self.podcasts_collection.len())
+        } else if mouse.is_in(self.items_area) {
+            self.active_list_state = 1;
+            mouse.list_action(self.items_area, &mut self.list_state_items, self.items_collection.len())
+        } else {
+            None
+        };
+        if let Some(action) = action {
+            self.handle_action(action).await?;
+        }
+        Ok(())
+    }
+
+    /// Seek the playing episode to `ratio` of its duration
+    fn seek_to(&self, ratio: f64) {
+        if self.active_item.is_some() && self.playback.is_podcast() {
+            let p = self.player_engine.read().unwrap();
+            if p.duration() > 0.0 {
+                p.seek(ratio * p.duration());
+            }
+        }
+    }
+
+    /// Rough number of lines the selected description wraps to in the details pane
+    fn description_lines(&self) -> u16 {
+        let width = self.details_area.width.saturating_sub(2).max(1) as usize;
+        self.list_state_items.selected()
+            .and_then(|i| self.items_collection.get(i))
+            .and_then(|i| i.description.as_ref())
+            .map(|d| d.lines().map(|l| l.chars().count() / width + 1).sum::<usize>())
+            .unwrap_or_default() as u16
+    }
+
+    pub async fn handle_action(&mut self, action: Action) -> std::io::Result<()> {
+        match action {
+            Action::Bookmark => {
+                if self.active_item.is_some() {
+                    let time = self.player_engine.read().unwrap().current_position();
+                    self.bookmark_dialog_state.clear();
+                    self.bookmark_time = Some(time as f32);
                 }
-                KeyCode::Char(' ') => {
-                    if self.active_item.is_some() {
-                        let mut p = self.player_engine.write().unwrap();
-                        if p.is_playing() == Playing::Paused {
-                            p.resume();
-                        } else {
-                            p.pause();
-                            self.write_listening_state(p.current_position() as f32);
-                        }
+            },
+            Action::Add => {
+                self.open_dialog_state.clear();
+                self.show_open_dialog = true;
+            },
+            Action::Refresh => {
+                self.items_collection.clear();
+                self.error = None;
+                let tx = self.tx.clone();
+                let db = self.db.clone();
+
+                if let Some(selected) = self.list_state_channels.selected() {
+                    let selected_channel = self.podcasts_collection[selected].clone();
+                    if let Some(podcast_url) = selected_channel.link {
+                        self.waiting_message = Some("Fetching podcast info...".to_string());
+                        tokio::spawn(async move {
+                            match DataProvider::fetch_data(podcast_url, selected_channel.id, db).await {
+                                Ok(fetched) => {
+                                    tx.send(AsyncAction::ChannelAdded(fetched.channel_id)).map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string())).unwrap();
+                                    tx.send(AsyncAction::RefreshChannelsList).map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string())).unwrap();
+                                    if let Some(first) = fetched.failed_items.first() {
+                                        let _ = tx.send(AsyncAction::FeedProblem(format!("{} episodes not stored, {}", fetched.failed_items.len(), first)));
+                                    }
+                                },
+                                Err(e) => {
+                                    let _ = tx.send(AsyncAction::FeedProblem(format!("unable to refresh: {}", e)));
+                                },
+                            };
+                        });
                     }
+                };
+            }
+            Action::SeekForward => {
+                if self.active_item.is_some() {
+                    let p = self.player_engine.read().unwrap();
+                    p.seek_forward();
                 }
-                KeyCode::Left | KeyCode::Right => { 
-                    self.active_list_state = (self.active_list_state + 1) % 2;
+            }
+            Action::SeekBackward => {
+                if self.active_item.is_some() {
+                    let p = self.player_engine.read().unwrap();
+                    p.seek_backward();
                 }
-                KeyCode::Enter => {
-                    if self.active_list_state == 0 {
-                        // load items then move items list
-                        if let Some(selected) = self.list_state_channels.selected() {
-                            let selected_channel = self.podcasts_collection[selected].clone();
-                            self.tx.send(AsyncAction::ChannelAdded(selected_channel.id)).map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string())).unwrap();
-                        }
-                        self.active_list_state = self.active_list_state + 1;
-                    } else {
-                        let mut p = self.player_engine.write().unwrap();
-                        self.write_listening_state(p.current_position() as f32);
-                        let selected_episode = &self.items_collection[self.list_state_items.selected().unwrap_or_default()];
-                        self.active_item = Some(selected_episode.clone());
-
-                        match p.open(&selected_episode.enclosure) {
-                            Ok(_) => {
-                                self.error = None;
-                                match selected_episode.listening_state.as_ref() {
-                                    Some(ls) => {
-                                        if !ls.finished {
-                                            p.seek(ls.time as f64);
-                                        }
-                                    },
-                                    None => (),
-                                }
-                            },
-                            Err(e) => self.error = Some(e.to_string()),
-                        }
+            }
+            Action::TogglePause => {
+                self.toggle_pause();
+            }
+            Action::SwitchPane => { 
+                self.active_list_state = (self.active_list_state + 1) % 2;
+            }
+            Action::Select => {
+                if self.active_list_state == 0 {
+                    // load items then move items list
+                    if let Some(selected) = self.list_state_channels.selected() {
+                        let selected_channel = self.podcasts_collection[selected].clone();
+                        self.tx.send(AsyncAction::ChannelAdded(selected_channel.id)).map_err(|e| std::io::Error::new(ErrorKind::Other, e.to_string())).unwrap();
                     }
-                },
-                KeyCode::Char('+') | KeyCode::Char('=') => {
-                    let mut p = self.player_engine.write().unwrap();
-                    p.increase_volume();
-                },
-                KeyCode::Char('-') => {
-                    let mut p = self.player_engine.write().unwrap();
-                    p.decrease_volume();
-                },
-                KeyCode::Down => {
-                    let len = self.list_state_len();
-                    let list_state = match self.active_list_state {
-                        0 => self.list_state_channels.borrow_mut(),
-                        _ => self.list_state_items.borrow_mut(),
+                    self.active_list_state = self.active_list_state + 1;
+                } else if let Some(selected_episode) = self.items_collection.get(self.list_state_items.selected().unwrap_or_default()) {
+                    let start_at = match selected_episode.listening_state.as_ref() {
+                        Some(ls) if !ls.finished => Some(ls.time),
+                        _ => None,
                     };
-                    let mut selected = list_state.selected().unwrap_or_default();
-                    selected = if selected >= len - 1 { 0 } else { selected + 1 };
-                    list_state.select(Some(selected));
-                },
-                KeyCode::Up => {
-                    let len = self.list_state_len();
-                    let list_state = match self.active_list_state {
-                        0 => self.list_state_channels.borrow_mut(),
-                        _ => self.list_state_items.borrow_mut(),
+                    self.play_item(selected_episode.clone(), start_at);
+                }
+            },
+            Action::VolumeUp => {
+                let mut p = self.player_engine.write().unwrap();
+                p.increase_volume();
+            },
+            Action::VolumeDown => {
+                let mut p = self.player_engine.write().unwrap();
+                p.decrease_volume();
+            },
+            Action::Search => {
+                self.search.start(if self.active_list_state == 0 { &self.list_state_channels } else { &self.list_state_items });
+            },
+            action if list_navigation::is_motion(action) => {
+                self.handle_motion(action, None);
+            },
+            Action::ToggleHelp => {
+                self.help_visible = !self.help_visible;
+            },
+            _ => {}
+        }
+        Ok(())
+    }
 
-                    };
-                    let mut selected = list_state.selected().unwrap_or_default();
-                    selected = if selected <= 0 { len - 1 } else { selected - 1 };
-                    list_state.select(Some(selected));
-                },
-                KeyCode::Char('d') | KeyCode::Delete => {
-                    // let selected = self.list_state_channels.selected().unwrap_or_default();
-                    // self.podcasts_collection.remove(selected);
-                    // if selected >= self.podcasts_collection.len() {
-                    //     self.list_state_channels.select(Some(selected - 1));
-                    // }
-                    // config::save(self.podcasts_collection.clone())?;
-                },
-                KeyCode::Char('h') => {
-                    self.help_visible = !self.help_visible;
-                },
-                _ => {}
+    pub fn toggle_pause(&mut self) {
+        if self.active_item.is_some() {
+            let player_engine = self.player_engine.clone();
+            let mut p = player_engine.write().unwrap();
+            if p.is_playing() == Playing::Paused {
+                p.resume();
+                self.start_listening_session(p.current_position() as f32);
+            } else {
+                p.pause();
+                self.write_listening_state(p.current_position() as f32);
+                self.end_listening_session(p.current_position() as f32);
             }
-            Ok(false)
+        }
+    }
+
+    /// Radio took over the shared player at `position` of the active item
+    pub fn on_interrupted(&mut self, position: f64) {
+        self.write_listening_state(position as f32);
+        self.end_listening_session(position as f32);
+        self.active_item = None;
+    }
+
+    /// Stop whatever is playing and start `item`,
+    /// optionally seeking to `start_at` seconds
+    pub fn play_item(&mut self, item: ui_models::ChannelItem, start_at: Option<f32>) {
+        self.playback.begin(PlaybackSource::Podcast(item.clone()));
+        let player_engine = self.player_engine.clone();
+        let mut p = player_engine.write().unwrap();
+        if p.is_playing() == Playing::Playing {
+            self.end_listening_session(p.current_position() as f32);
+        }
+        self.write_listening_state(p.current_position() as f32);
+        self.active_item = Some(item.clone());
+
+        match p.open(&item.enclosure) {
+            Ok(_) => {
+                self.error = None;
+                if let Some(time) = start_at {
+                    p.seek(time as f64);
+                }
+                self.start_listening_session(start_at.unwrap_or_default());
+            },
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn start_listening_session(&mut self, position: f32) {
+        if self.listening_session_open {
+            return;
+        }
+        if let Some(active_item) = self.active_item.as_ref() {
+            let _ = self.tx.send(AsyncAction::StartListeningSession(active_item.clone(), position));
+            self.listening_session_open = true;
+        }
+    }
+
+    fn end_listening_session(&mut self, position: f32) {
+        if self.listening_session_open {
+            let _ = self.tx.send(AsyncAction::EndListeningSession(position));
+            self.listening_session_open = false;
         }
     }
 
@@ -403,16 +573,86 @@ impl PodcastsModel {
         Ok(false)
     }
 
-    fn list_state_len(&self) -> usize {
+    fn handle_bookmark_dialog_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) => self.bookmark_time = None,
+            (KeyCode::Enter, _) => {
+                if let (Some(time), Some(active_item)) = (self.bookmark_time.take(), self.active_item.as_ref()) {
+                    let note = self.bookmark_dialog_state.text();
+                    let note = if note.trim().is_empty() { None } else { Some(note) };
+                    let _ = self.tx.send(AsyncAction::AddBookmark(active_item.clone(), time, note));
+                }
+            },
+            (key_code, key_modifiers) => {
+                self.bookmark_dialog_state.handle_events(key_code, key_modifiers);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Move the selection of the focused list, `count` was typed before the keys
+    pub fn handle_motion(&mut self, action: Action, count: Option<usize>) {
+        let titles = self.list_titles();
+        let (state, area) = match self.active_list_state {
+            0 => (&mut self.list_state_channels, self.channels_area),
+            _ => (&mut self.list_state_items, self.items_area),
+        };
+        match action {
+            Action::SearchNext | Action::SearchPrevious => self.search.repeat(&titles, state, action == Action::SearchNext, count),
+            _ => list_navigation::navigate(state, titles.len(), area.height.saturating_sub(2) as usize, action, count),
+        }
+    }
+
+    /// Titles of the focused list, for searching
+    fn list_titles(&self) -> Vec<String> {
         match self.active_list_state {
-            0 => self.podcasts_collection.len(),
-            _ => self.items_collection.len(),
+            0 => self.podcasts_collection.iter().map(|c| c.title.clone().unwrap_or_default()).collect(),
+            _ => self.items_collection.iter().map(|i| i.title.clone().unwrap_or_default()).collect(),
         }
     }
 
-    pub async fn on_quit(&self) {
+    /// Load episodes of all channels with the channel title in the background,
+    /// they come as `AsyncAction::PaletteEpisodes` for the command palette
+    pub fn load_all_items(&self) {
+        let channels: Vec<(i32, String)> = self.podcasts_collection.iter()
+            .map(|c| (c.id, c.title.clone().unwrap_or_default()))
+            .collect();
+        let db = self.db.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let items = DataProvider::get_all_items_from_db(&db).await.map(|items| {
+                let mut by_channel: HashMap<i32, Vec<ui_models::ChannelItem>> = HashMap::new();
+                for item in items {
+                    by_channel.entry(item.channel_id).or_default().push(item);
+                }
+                // in the order of the channels list
+                channels.into_iter()
+                    .flat_map(|(id, title)| by_channel.remove(&id).unwrap_or_default().into_iter().map(move |item| (item, title.clone())))
+                    .collect()
+            });
+            let _ = tx.send(AsyncAction::PaletteEpisodes(items.map_err(|e| e.to_string())));
+        });
+    }
+
+    /// Play episode from where it was left, like selecting it in its channel
+    pub fn resume_item(&self, item: ui_models::ChannelItem) {
+        let time = match item.listening_state.as_ref() {
+            Some(ls) if !ls.finished => ls.time,
+            _ => 0.0,
+        };
+        let _ = self.tx.send(AsyncAction::ResumeItem(item, time));
+    }
+
+    /// Text input is active and keys shouldn't be taken as global shortcuts
+    pub fn captures_input(&self) -> bool {
+        self.show_open_dialog || self.bookmark_time.is_some() || self.search.is_active()
+    }
+
+    pub async fn on_quit(&mut self) {
         let time = self.player_engine.read().unwrap().current_position();
         self.write_listening_state(time as f32);
+        self.end_listening_session(time as f32);
     }
 
 }
@@ -430,3 +670,41 @@ impl PodcastsModel {
 //     println!("-----------");
 //     println!("{:#?}", ext);
 // }
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::Database;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn at(input: MouseInput, column: u16, row: u16) -> Mouse {
+        Mouse { input, column, row }
+    }
+
+    #[tokio::test]
+    async fn seeking_ends_when_the_button_is_let_go() {
+        crate::config::init(Default::default());
+        let (tx, _) = mpsc::unbounded_channel();
+        let mut model = PodcastsModel::new(Database::connect("sqlite::memory:").await.unwrap(), tx.clone(), Playback::new(tx));
+        model.timeline_area = Rect::new(0, 20, 40, 3);
+        model.details_area = Rect::new(0, 0, 40, 20);
+
+        model.handle_mouse(at(MouseInput::Click, 10, 21)).await.unwrap();
+        assert!(model.seeking);
+        model.handle_mouse(at(MouseInput::Drag, 50, 5)).await.unwrap();
+        assert!(model.seeking);
+        model.handle_mouse(at(MouseInput::Release, 50, 5)).await.unwrap();
+        assert!(!model.seeking);
+
+        // moving without pressing the button again doesn't seek
+        model.handle_mouse(at(MouseInput::Drag, 10, 21)).await.unwrap();
+        assert!(!model.seeking);
+        model.handle_mouse(at(MouseInput::ScrollDown, 5, 5)).await.unwrap();
+        assert!(!model.seeking);
+        model.handle_mouse(at(MouseInput::DoubleClick, 10, 21)).await.unwrap();
+        assert!(model.seeking);
+        model.handle_mouse(at(MouseInput::Click, 5, 5)).await.unwrap();
+        assert!(!model.seeking);
+    }
+}
@@ -0,0 +1,188 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, List, ListState, Paragraph}, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_textbox::{Textbox, TextboxState};
+
+use crate::{config, data_layer::radio_directory_data_layer::RadioDirectoryDataLayer, keymap::{self, Action, Scope}, mouse::{Mouse, MouseInput}, theme, ui_models::{DirectorySearch, DirectoryStation}, AsyncAction};
+
+pub enum DirectoryAction {
+    Close,
+    /// play station without adding it
+    Preview(DirectoryStation),
+    /// add station to the collection
+    Add(DirectoryStation),
+}
+
+pub struct RadioDirectoryModel {
+    base_url: String,
+    tx: UnboundedSender<AsyncAction>,
+    pub query_state: TextboxState,
+    /// 0 - search query, 1 - results
+    pub focus: usize,
+    pub stations: Vec<DirectoryStation>,
+    pub list_state: ListState,
+    pub message: Option<String>,
+    /// where search box and results were rendered, for mouse input
+    search_area: Rect,
+    results_area: Rect,
+}
+
+impl RadioDirectoryModel {
+    pub fn new(tx: UnboundedSender<AsyncAction>) -> Self {
+        let mut query_state = TextboxState::default();
+        query_state.hint_text = Some("<name tag:... country:\"...\" language:...>".to_string());
+        Self {
+            base_url: config::radio_directory_url(),
+            tx,
+            query_state,
+            focus: 0,
+            stations: Default::default(),
+            list_state: Default::default(),
+            message: None,
+            search_area: Rect::default(),
+            results_area: Rect::default(),
+        }
+    }
+
+    pub fn set_results(&mut self, results: Result<Vec<DirectoryStation>, String>) {
+        match results {
+            Ok(stations) => {
+                self.message = Some(format!("{} stations found", stations.len()));
+                self.list_state.select(if stations.is_empty() { None } else { Some(0) });
+                self.stations = stations;
+            },
+            Err(e) => self.message = Some(format!("Search failed: {}", e)),
+        }
+    }
+
+    /// Click on the search box focuses it, double click on a result previews it
+    pub fn handle_mouse(&mut self, mouse: Mouse) -> Option<DirectoryAction> {
+        if mouse.is_in(self.search_area) && mouse.input == MouseInput::Click {
+            self.focus = 0;
+            return None;
+        }
+        if mouse.is_in(self.results_area) {
+            self.focus = 1;
+        }
+        match mouse.list_action(self.results_area, &mut self.list_state, self.stations.len())? {
+            Action::Select => self.selected().cloned().map(DirectoryAction::Preview),
+            action => {
+                self.move_selection(action == Action::Down);
+                None
+            },
+        }
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let len = self.stations.len();
+        if len > 0 {
+            let selected = self.list_state.selected().unwrap_or_default();
+            let selected = if down { (selected + 1) % len } else { (selected + len - 1) % len };
+            self.list_state.select(Some(selected));
+        }
+    }
+
+    fn selected(&self) -> Option<&DirectoryStation> {
+        self.list_state.selected().and_then(|i| self.stations.get(i))
+    }
+
+    fn search(&mut self) {
+        let search = DirectorySearch::parse(&self.query_state.text);
+        let base_url = self.base_url.clone();
+        let tx = self.tx.clone();
+        self.message = Some("Searching...".to_string());
+        tokio::task::spawn_blocking(move || {
+            let res = RadioDirectoryDataLayer::search(&base_url, &search).map_err(|e| e.to_string());
+            let _ = tx.send(AsyncAction::DirectoryResults(res));
+        });
+    }
+
+    pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
+        let vertical_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Percentage(100), Constraint::Length(8)])
+            .split(rect);
+        self.search_area = vertical_chunks[0];
+        self.results_area = vertical_chunks[1];
+
+        let focus = |i: usize| theme.focus(self.focus == i);
+
+        // search
+        let search_block = Block::default().borders(Borders::ALL).title("Search directory").style(focus(0));
+        let search_area = search_block.inner(vertical_chunks[0]);
+        f.render_widget(search_block, vertical_chunks[0]);
+        if self.focus == 0 {
+            f.render_stateful_widget(Textbox::default(), search_area, &mut self.query_state);
+        } else {
+            f.render_widget(Paragraph::new(self.query_state.text.clone()), search_area);
+        }
+
+        // results
+        let list = List::new(self.stations.iter().map(|s| {
+            let details = [s.country.clone(), s.language.clone(), s.codec.clone(), s.bitrate.map(|b| format!("{}kbps", b))]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            Line::from(vec![
+                Span::raw(s.name.clone()),
+                Span::styled(format!("  [{}]", details.join(", ")), theme.muted),
+            ])
+        }))
+        .block(Block::default().borders(Borders::ALL).title("Results").style(focus(1))
+            .title_bottom(self.message.clone().unwrap_or(format!("{}, <esc> - close", keymap::get().hint(Scope::Directory, &[Action::Select, Action::Add, Action::Search])))))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ")
+            .repeat_highlight_symbol(true);
+        f.render_stateful_widget(list, vertical_chunks[1], &mut self.list_state);
+
+        // details of selected station
+        let detail = |label: &str, value: Option<String>| Line::from(vec![
+            Span::styled(format!("{:<10}", label), theme.muted),
+            Span::raw(value.unwrap_or("-".to_string())),
+        ]);
+        let lines = match self.selected() {
+            Some(s) => vec![
+                detail("url", Some(s.url.clone())),
+                detail("homepage", s.homepage.clone()),
+                detail("tags", s.tags.clone()),
+                detail("country", s.country.clone()),
+                detail("language", s.language.clone()),
+                detail("votes", Some(s.votes.to_string())),
+            ],
+            None => vec![],
+        };
+        let details = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Station"));
+        f.render_widget(details, vertical_chunks[2]);
+    }
+
+    /// Keys of the search box and closing, the results take `Action`s
+    pub fn handle_events(&mut self, key: KeyEvent) -> Option<DirectoryAction> {
+        if key.code == KeyCode::Esc {
+            return Some(DirectoryAction::Close);
+        }
+
+        if self.focus == 0 {
+            match key.code {
+                KeyCode::Enter => {
+                    self.search();
+                    self.focus = 1;
+                },
+                KeyCode::Down => self.focus = 1,
+                _ => self.query_state.handle_events(key.code, key.modifiers),
+            }
+        }
+        None
+    }
+
+    pub fn handle_action(&mut self, action: Action) -> Option<DirectoryAction> {
+        match action {
+            Action::Search => self.focus = 0,
+            Action::Select => return self.selected().cloned().map(DirectoryAction::Preview),
+            Action::Add => return self.selected().cloned().map(DirectoryAction::Add),
+            Action::Down | Action::Up => self.move_selection(action == Action::Down),
+            _ => {}
+        }
+        None
+    }
+}
@@ -1,27 +1,182 @@
-use std::sync::{Arc, RwLock};
+use std::{collections::{BTreeMap, HashSet}, path::PathBuf, sync::{Arc, RwLock}};
 
+use clipboard::{ClipboardContext, ClipboardProvider};
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style, Stylize}, text::{Line, Span}, widgets::{Block, Borders, List, ListState, Paragraph}, Frame};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, List, ListState, Paragraph}, Frame};
+use sea_orm::{DatabaseConnection, DbErr};
+use tokio::sync::mpsc::UnboundedSender;
 use tui_textbox::{Textbox, TextboxState};
 use url2audio::player_engine::Playing;
 
-use crate::{config, player_engine::PlayerEngine};
+use crate::streaming::{recorder::RecordingStatus, relay::StreamRelay};
+use crate::{config, csv, keymap::{self, Action, Scope}, list_navigation::{self, ListSearch}, mouse::Mouse, data_layer::{radio_directory_data_layer::RadioDirectoryDataLayer, radio_song_data_layer::RadioSongDataLayer, radio_station_data_layer::RadioStationDataLayer}, player_engine::{LiveStatus, PlayerEngine}, playback::{Playback, PlaybackSource}, theme, ui_models::DirectoryStation, AsyncAction};
+use crate::radio_directory_model::{DirectoryAction, RadioDirectoryModel};
+use crate::entity::radio_song::Model as RadioSongModel;
+use crate::entity::radio_station::Model as RadioStationModel;
+
+/// Number of songs shown in the recently heard panel
+const SONGS_LIMIT: u64 = 200;
+/// Title of the folder of favorite stations
+const FAVORITES_TITLE: &str = "★ Favorites";
+
+const FIELD_NAME: usize = 0;
+const FIELD_URL: usize = 1;
+const FIELD_GROUP: usize = 2;
+const FIELDS: usize = 3;
+
+/// Folder of the stations tree, `Favorites` sorts before the groups
+/// and doesn't mix with a group of the same name
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Folder {
+    Favorites,
+    Group(String),
+}
+
+impl Folder {
+    fn title(&self) -> &str {
+        match self {
+            Folder::Favorites => FAVORITES_TITLE,
+            Folder::Group(name) => name,
+        }
+    }
+}
+
+/// Row of the stations tree
+enum StationRow {
+    Folder { folder: Folder, count: usize, collapsed: bool },
+    /// index into `streams_collection`
+    Station(usize),
+}
+
+/// Edit station dialog
+struct StationEditor {
+    station: RadioStationModel,
+    name: TextboxState,
+    url: TextboxState,
+    group: TextboxState,
+    focus: usize,
+    error: Option<String>,
+}
+
+fn textbox(text: String) -> TextboxState {
+    let mut state = TextboxState::default();
+    state.cursor_pos = text.chars().count();
+    state.text = text;
+    state
+}
+
+impl StationEditor {
+    fn new(station: &RadioStationModel) -> Self {
+        StationEditor {
+            station: station.clone(),
+            name: textbox(station.name.clone().unwrap_or_default()),
+            url: textbox(station.url.clone()),
+            group: textbox(station.group_name.clone().unwrap_or_default()),
+            focus: FIELD_NAME,
+            error: None,
+        }
+    }
+
+    fn textbox_mut(&mut self) -> &mut TextboxState {
+        match self.focus {
+            FIELD_URL => &mut self.url,
+            FIELD_GROUP => &mut self.group,
+            _ => &mut self.name,
+        }
+    }
+}
+
+/// Folder of station in the stations tree, `None` for stations outside of folders
+fn station_folder(station: &RadioStationModel) -> Option<Folder> {
+    if station.favorite {
+        return Some(Folder::Favorites);
+    }
+    station.group_name.as_deref().and_then(non_empty).map(Folder::Group)
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    Some(text.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// Station stream to connect off the UI task
+pub enum ConnectTarget {
+    Station(Box<RadioStationModel>),
+    /// directory station of `preview`
+    Preview,
+    BackgroundRecording(Box<RadioStationModel>),
+}
+
+/// Stream connected off the UI task, posted back as `AsyncAction::RadioConnected`
+pub struct RadioConnection {
+    /// only the latest request is used, older ones were replaced or stopped
+    request: u64,
+    target: ConnectTarget,
+    /// (url the relay was started for, relay)
+    result: Result<(String, StreamRelay), String>,
+}
+
+/// Start relay for station from its cached resolved url, or from its url when that fails.
+/// Blocks while connecting
+pub fn connect(resolved_url: Option<String>, url: String) -> Result<(String, StreamRelay), String> {
+    if let Some(resolved_url) = resolved_url {
+        if let Ok(relay) = StreamRelay::start(&resolved_url) {
+            return Ok((resolved_url, relay));
+        }
+    }
+    StreamRelay::start(&url).map(|relay| (url, relay)).map_err(|e| e.to_string())
+}
 
 pub struct RadioModel {
+    db: DatabaseConnection,
+    tx: UnboundedSender<AsyncAction>,
     pub list_streams_state: ListState,
-    pub streams_collection: Vec<String>,
-    pub active_stream: Option<String>,
+    pub streams_collection: Vec<RadioStationModel>,
+    pub active_stream: Option<RadioStationModel>,
     pub error: Option<String>,
     pub show_open_dialog: bool,
     pub textbox_state: TextboxState,
     pub player_engine: Arc<RwLock<PlayerEngine>>,
+    playback: Playback,
     help_visible: bool,
+    /// 0 - stations, 1 - recently heard songs
+    pub active_list_state: usize,
+    pub list_songs_state: ListState,
+    pub songs_collection: Vec<RadioSongModel>,
+    last_song_title: Option<String>,
+    /// kept alive because on X11 clipboard content is served by its owner
+    clipboard: Option<ClipboardContext>,
+    message: Option<String>,
+    pub directory: RadioDirectoryModel,
+    pub show_directory: bool,
+    /// directory station playing without being in the collection
+    preview: Option<DirectoryStation>,
+    /// start a new recording file when stream title changes
+    pub split_recordings: bool,
+    /// station recorded without listening
+    background_recording: Option<(RadioStationModel, StreamRelay)>,
+    /// connection state of the playing stream
+    live_status: Option<LiveStatus>,
+    /// rows of the stations list, `list_streams_state` selects a row
+    station_rows: Vec<StationRow>,
+    collapsed_folders: HashSet<Folder>,
+    station_editor: Option<StationEditor>,
+    /// where stations and recently heard songs were rendered, for mouse input
+    stations_area: Rect,
+    songs_area: Rect,
+    search: ListSearch,
+    /// id of the next connect request
+    next_request: u64,
+    /// stream of `active_stream` or `preview` is being connected, results of other requests are dropped
+    connect_request: Option<u64>,
+    /// (request id, station title) background recording is being connected
+    background_request: Option<(u64, String)>,
 }
 
-
-impl Default for RadioModel {
-    fn default() -> Self {
+impl RadioModel {
+    pub fn new(db: DatabaseConnection, tx: UnboundedSender<AsyncAction>, playback: Playback) -> Self {
         Self {
+            db,
+            tx: tx.clone(),
             list_streams_state: Default::default(),
             streams_collection: Default::default(),
             help_visible: Default::default(),
@@ -29,71 +184,320 @@ impl Default for RadioModel {
             error: Default::default(),
             show_open_dialog: Default::default(),
             textbox_state: Default::default(),
-            player_engine: Default::default()
+            player_engine: playback.player_engine.clone(),
+            playback,
+            active_list_state: 0,
+            list_songs_state: Default::default(),
+            songs_collection: Default::default(),
+            last_song_title: Default::default(),
+            clipboard: None,
+            message: None,
+            directory: RadioDirectoryModel::new(tx),
+            show_directory: false,
+            preview: None,
+            split_recordings: false,
+            background_recording: None,
+            live_status: None,
+            station_rows: vec![],
+            collapsed_folders: HashSet::new(),
+            station_editor: None,
+            stations_area: Rect::default(),
+            songs_area: Rect::default(),
+            search: Default::default(),
+            next_request: 0,
+            connect_request: None,
+            background_request: None,
         }
     }
-}
 
-impl RadioModel {
+    pub async fn reload(&mut self) -> Result<(), DbErr> {
+        let selected_id = self.selected_station().map(|s| s.id);
+        self.streams_collection = RadioStationDataLayer::get_stations(&self.db).await?;
+        self.rebuild_rows(selected_id);
+        self.reload_songs().await
+    }
+
+    /// Group stations into folders, favorites first, then groups by name,
+    /// then stations without group. Station `selected_id` stays selected
+    fn rebuild_rows(&mut self, selected_id: Option<i32>) {
+        let selected_row = self.list_streams_state.selected();
+        let mut folders: BTreeMap<Folder, Vec<usize>> = BTreeMap::new();
+        let mut ungrouped = vec![];
+        for (i, station) in self.streams_collection.iter().enumerate() {
+            match station_folder(station) {
+                Some(folder) => folders.entry(folder).or_default().push(i),
+                None => ungrouped.push(i),
+            }
+        }
+
+        self.station_rows = vec![];
+        for (folder, stations) in folders {
+            let collapsed = self.collapsed_folders.contains(&folder);
+            self.station_rows.push(StationRow::Folder { folder, count: stations.len(), collapsed });
+            if !collapsed {
+                self.station_rows.extend(stations.into_iter().map(StationRow::Station));
+            }
+        }
+        self.station_rows.extend(ungrouped.into_iter().map(StationRow::Station));
+
+        let row_of_selected = self.station_rows.iter().position(|r| match r {
+            StationRow::Station(i) => Some(self.streams_collection[*i].id) == selected_id,
+            StationRow::Folder { .. } => false,
+        });
+        if self.station_rows.is_empty() {
+            self.list_streams_state.select(None);
+        } else if row_of_selected.is_some() {
+            self.list_streams_state.select(row_of_selected);
+        } else if selected_row.is_none_or(|s| s >= self.station_rows.len()) {
+            self.list_streams_state.select(Some(self.station_rows.len() - 1));
+        }
+    }
+
+    /// Station in the selected row, `None` when a folder is selected
+    pub fn selected_station(&self) -> Option<&RadioStationModel> {
+        match self.list_streams_state.selected().and_then(|i| self.station_rows.get(i)) {
+            Some(StationRow::Station(i)) => self.streams_collection.get(*i),
+            _ => None,
+        }
+    }
+
+    fn toggle_selected_folder(&mut self) {
+        if let Some(StationRow::Folder { folder, .. }) = self.list_streams_state.selected().and_then(|i| self.station_rows.get(i)) {
+            let folder = folder.clone();
+            if !self.collapsed_folders.remove(&folder) {
+                self.collapsed_folders.insert(folder);
+            }
+            self.rebuild_rows(None);
+        }
+    }
+
+    /// Move selected station one place up or down within its folder
+    async fn move_selected_station(&mut self, up: bool) -> Result<(), DbErr> {
+        let row = match self.list_streams_state.selected() {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let neighbour_row = if up { row.checked_sub(1) } else { Some(row + 1) };
+        let (station, neighbour) = match (self.station_rows.get(row), neighbour_row.and_then(|r| self.station_rows.get(r))) {
+            (Some(StationRow::Station(a)), Some(StationRow::Station(b))) => (&self.streams_collection[*a], &self.streams_collection[*b]),
+            _ => return Ok(()),
+        };
+        if station_folder(station) != station_folder(neighbour) {
+            return Ok(());
+        }
+        RadioStationDataLayer::swap_positions(&self.db, station, neighbour).await?;
+        self.reload().await
+    }
+
+    pub async fn reload_songs(&mut self) -> Result<(), DbErr> {
+        self.songs_collection = RadioSongDataLayer::get_songs(&self.db, Some(SONGS_LIMIT)).await?;
+        if self.songs_collection.is_empty() {
+            self.list_songs_state.select(None);
+        } else if self.list_songs_state.selected().is_none_or(|s| s >= self.songs_collection.len()) {
+            self.list_songs_state.select(Some(0));
+        }
+        Ok(())
+    }
+
+    /// Reconnect dropped stream and record song title when the playing station announces a new one
+    pub async fn on_tick(&mut self) -> Result<(), DbErr> {
+        let (live_status, reconnect) = {
+            let mut p = self.player_engine.write().unwrap();
+            (p.supervise(), p.start_reconnect())
+        };
+        if let Some(url) = reconnect {
+            let tx = self.tx.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = StreamRelay::start(&url).map_err(|e| e.to_string());
+                let _ = tx.send(AsyncAction::StreamReconnected(url, result));
+            });
+        }
+        if let Some(LiveStatus::Failed(reason)) = live_status.as_ref() {
+            if !matches!(self.live_status, Some(LiveStatus::Failed(_))) {
+                self.error = Some(format!("Connection lost: {}", reason));
+            }
+        }
+        self.live_status = live_status;
+
+        let station = match self.active_stream.as_ref() {
+            Some(s) => s.clone(),
+            None => {
+                self.last_song_title = None;
+                return Ok(());
+            },
+        };
+        let icy = self.now_playing().unwrap_or_default();
+        if icy.stream_title.is_none() || icy.stream_title == self.last_song_title {
+            return Ok(());
+        }
+        self.last_song_title = icy.stream_title.clone();
+        let station_name = station.name.filter(|n| !n.trim().is_empty()).or(icy.name);
+        RadioSongDataLayer::add_song(&self.db, station.id, station_name, icy.stream_title.unwrap_or_default()).await?;
+        self.reload_songs().await
+    }
+
+    /// Text input is active and keys shouldn't be taken as global shortcuts
+    pub fn captures_input(&self) -> bool {
+        self.show_open_dialog || self.station_editor.is_some() || (self.show_directory && self.directory.focus == 0) || self.search.is_active()
+    }
+
+    /// ICY metadata of the playing station
+    pub fn now_playing(&self) -> Option<crate::streaming::icy::IcyMetadata> {
+        self.active_stream.as_ref()?;
+        self.player_engine.read().unwrap().icy_metadata()
+    }
 
     pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
         let size = rect;
 
+        let mut recording_lines = vec![];
+        if let Some(status) = self.player_engine.read().unwrap().relay().and_then(|r| r.recording_status()) {
+            recording_lines.push(recording_line("", &status));
+        }
+        if let Some((station, relay)) = self.background_recording.as_ref() {
+            if let Some(status) = relay.recording_status() {
+                recording_lines.push(recording_line(&format!("{} ", station_title(station)), &status));
+            }
+        }
+        if let Some((_, title)) = self.background_request.as_ref() {
+            recording_lines.push(Line::from(vec![
+                Span::styled("● REC ", theme.error),
+                Span::styled(format!("{} connecting...", title), theme.highlight),
+            ]));
+        }
+
         let vertical_chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(100), Constraint::Min(3)])
+            .constraints([Constraint::Percentage(100), Constraint::Length(4 + recording_lines.len() as u16)])
             .split(size);
 
         let status_block = Block::default().borders(Borders::ALL).title(format!("status"));
 
+        let list_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(vertical_chunks[0]);
+        self.stations_area = list_chunks[0];
+        self.songs_area = list_chunks[1];
+
+        let focus = |i: usize| theme.focus(self.active_list_state == i);
+
         // list
-        let active_stream = match self.active_stream.as_ref() {
-            Some(s) => s.clone(),
-            None => "".to_string(),
-        };
-        let list = List::new(self.streams_collection.clone().into_iter().map(|i| {
-            if i == active_stream {
-                format!("{} 🎵", i)
-            } else {
-                format!("{}", i)
+        let active_stream_id = self.active_stream.as_ref().map(|s| s.id);
+        let list = List::new(self.station_rows.iter().map(|row| {
+            let i = match row {
+                StationRow::Folder { folder, count, collapsed } => {
+                    return Line::from(vec![
+                        Span::styled(format!("{} {}", if *collapsed { "▸" } else { "▾" }, folder.title()), theme.highlight),
+                        Span::styled(format!(" ({})", count), theme.muted),
+                    ]);
+                },
+                StationRow::Station(i) => &self.streams_collection[*i],
+            };
+            let indent = if station_folder(i).is_some() { "  " } else { "" };
+            let mut spans = vec![Span::raw(format!("{}{}", indent, station_title(i)))];
+            let details = [i.genre.clone(), i.country.clone(), i.bitrate.map(|b| format!("{}kbps", b))]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            if !details.is_empty() {
+                spans.push(Span::styled(format!("  [{}]", details.join(", ")), theme.muted));
+            }
+            if Some(i.id) == active_stream_id {
+                spans.push(Span::raw(" 🎵"));
             }
+            Line::from(spans)
         }))
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(Borders::ALL).title("Stations").style(focus(0)))
             .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
             .highlight_symbol(">> ")
             .repeat_highlight_symbol(true);
 
-        f.render_stateful_widget(list, vertical_chunks[0], &mut self.list_streams_state);
+        if self.show_directory {
+            self.directory.ui(vertical_chunks[0], f);
+        } else {
+            f.render_stateful_widget(list, list_chunks[0], &mut self.list_streams_state);
+        }
+
+        // recently heard
+        let songs = List::new(self.songs_collection.iter().map(|s| {
+            Line::from(vec![
+                Span::styled(format!("{} ", s.heard_at.format("%d.%m. %H:%M")), theme.muted),
+                Span::raw(s.title.clone()),
+                Span::styled(format!("  [{}]", s.station_name.clone().unwrap_or("-".to_string())), theme.muted),
+            ])
+        }))
+        .block(Block::default().borders(Borders::ALL).title("Recently heard").style(focus(1))
+            .title_bottom(self.message.clone().unwrap_or(keymap::get().hint(Scope::RecentlyHeard, &[Action::CopyTitle, Action::Export]))))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ")
+            .repeat_highlight_symbol(true);
+
+        if !self.show_directory {
+            f.render_stateful_widget(songs, list_chunks[1], &mut self.list_songs_state);
+            self.search.render(if self.active_list_state == 0 { list_chunks[0] } else { list_chunks[1] }, f);
+        }
 
 
         let status_line = match self.error.as_ref() {
             Some(e) => {
                 Line::from(vec![
-                           Span::styled(format!("Error: {}", e.to_string()), Style::default().fg(ratatui::style::Color::Red)),
+                           Span::styled(format!("Error: {}", e.to_string()), theme.error),
                 ])
             },
             None => {
                 match self.active_stream.as_ref() {
                     Some(s) => {
                         let play_char = if self.player_engine.read().unwrap().is_playing() == Playing::Playing { "▶" } else { "Ⅱ" };
-                        Line::from(vec![
-                                   Span::styled(format!("{} {}", play_char, s), Style::default().fg(ratatui::style::Color::Blue)),
-                        ])
+                        let icy = self.now_playing().unwrap_or_default();
+                        let name = s.name.clone().filter(|n| !n.trim().is_empty()).or(icy.name.clone()).unwrap_or(s.url.clone());
+                        let mut spans = vec![
+                                   Span::styled(format!("{} {}", play_char, name), theme.accent),
+                        ];
+                        let details = [icy.genre, icy.bitrate.map(|b| format!("{}kbps", b))]
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>();
+                        if !details.is_empty() {
+                            spans.push(Span::styled(format!("  [{}]", details.join(", ")), theme.muted));
+                        }
+                        if self.connect_request.is_some() {
+                            spans.push(Span::styled("  connecting...", theme.highlight));
+                        }
+                        if let Some(LiveStatus::Reconnecting { attempt, max_attempts, reason }) = self.live_status.as_ref() {
+                            spans.push(Span::styled(format!("  reconnecting (attempt {}/{}): {}", attempt, max_attempts, reason), theme.highlight));
+                        }
+                        Line::from(spans)
                     },
-                    None => {
-                        Line::from(vec![
-                                   Span::styled(format!("■"), Style::default()),
-                        ])
+                    None => match self.preview.as_ref() {
+                        Some(p) => {
+                            Line::from(vec![
+                                       Span::styled(format!("▶ preview: {}", p.name), theme.accent),
+                                       Span::styled(if self.connect_request.is_some() { "  connecting..." } else { "" }, theme.highlight),
+                            ])
+                        },
+                        None => {
+                            Line::from(vec![
+                                       Span::styled(format!("■"), Style::default()),
+                            ])
+                        },
                     }
                 }
             },
         };
 
-        let status_paragraph = Paragraph::new(vec![status_line]).block(status_block);
+        let mut status_lines = vec![status_line];
+        if let Some(title) = self.now_playing().and_then(|m| m.stream_title) {
+            status_lines.push(Line::from(vec![Span::styled(format!("♪ {}", title), Style::default())]));
+        }
+        status_lines.append(&mut recording_lines);
+
+        let status_paragraph = Paragraph::new(status_lines).block(status_block);
         f.render_widget(status_paragraph, vertical_chunks[1]);
 
         let volume = self.player_engine.read().unwrap().get_volume() * 100.0;
-        let volume_line = Line::from(vec![Span::styled(format!("Volume: {:.0}%", volume), Style::default().fg(ratatui::style::Color::Blue))]);
+        let volume_line = Line::from(vec![Span::styled(format!("Volume: {:.0}%", volume), theme.accent)]);
         let volume_paragraph = Paragraph::new(vec![volume_line]);
 
         let volume_area = Rect::new(vertical_chunks[1].width - 13, vertical_chunks[1].y + 1, 13, 1);
@@ -105,7 +509,7 @@ impl RadioModel {
             let h = 8;
             let x = (size.width - w) / 2;
             let y = (size.height - h) / 3;
-            let open_dialog_block = Block::default().borders(Borders::ALL).title("Open stream").bg(Color::DarkGray);
+            let open_dialog_block = Block::default().borders(Borders::ALL).title("Open stream").style(theme.popup);
 
             let dialog_rect = Rect { width: w, height: h, x, y };
             f.render_widget(open_dialog_block, dialog_rect);
@@ -127,129 +531,489 @@ impl RadioModel {
             f.render_widget(open_dialog_paragraph, open_dialog_rect);
         }
 
-        if self.help_visible {
-            let w = 50;
-            let h = 10;
-            let x = (size.width - w) / 2;
-            let y = (size.height - h) / 2;
-            let help_block = Block::default().borders(Borders::ALL).title("Help").bg(Color::DarkGray);
-
-            let mut lines = vec![];
-            let line = Line::from(vec![Span::styled("h - toggle help", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("1|2|<tab> - toggle tabs", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("<enter> - open and play stream", Style::default())]);
-            lines.push(line);
-
-            let line = Line::from(vec![Span::styled("<space> - play/pause stream", Style::default())]);
-            lines.push(line);
+        if let Some(editor) = self.station_editor.as_mut() {
+            let w = std::cmp::min(70, size.width);
+            let h = 7;
+            let area = Rect::new(size.x + (size.width - w) / 2, size.y + size.height.saturating_sub(h) / 3, w, h);
+            f.render_widget(Clear, area);
+            f.render_widget(Block::default().borders(Borders::ALL).title("Edit station").style(theme.popup), area);
 
-            let line = Line::from(vec![Span::styled("+/- - volume up/down", Style::default())]);
-            lines.push(line);
+            let labels = ["name", "url", "group"];
+            for (i, label) in labels.iter().enumerate() {
+                let y = area.y + 1 + i as u16;
+                let style = if editor.focus == i { theme.highlight } else { Style::default() };
+                f.render_widget(Paragraph::new(Span::styled(format!("{:<7}", label), style)), Rect::new(area.x + 1, y, 7, 1));
+                let value_area = Rect::new(area.x + 9, y, area.width.saturating_sub(10), 1);
+                let state = match i {
+                    FIELD_URL => &mut editor.url,
+                    FIELD_GROUP => &mut editor.group,
+                    _ => &mut editor.name,
+                };
+                if editor.focus == i {
+                    f.render_stateful_widget(Textbox::default(), value_area, state);
+                } else {
+                    f.render_widget(Paragraph::new(state.text.clone()), value_area);
+                }
+            }
 
-            let line = Line::from(vec![Span::styled("o - add stream to collection", Style::default())]);
-            lines.push(line);
+            let help = editor.error.clone().unwrap_or("<up>/<down> - field, <enter> - save, <esc> - cancel".to_string());
+            f.render_widget(Paragraph::new(help), Rect::new(area.x + 1, area.y + 5, area.width.saturating_sub(2), 1));
+        }
 
-            let line = Line::from(vec![Span::styled("d|<del> - remove stream from collection", Style::default())]);
-            lines.push(line);
+        if self.help_visible {
+            let keymap = keymap::get();
+            let lines: Vec<Line> = [Scope::Radio, Scope::RecentlyHeard, Scope::Global].iter()
+                .flat_map(|s| keymap.help(*s))
+                .map(|l| Line::from(vec![Span::styled(l, Style::default())]))
+                .collect();
+            let w = std::cmp::min(50, size.width);
+            let h = std::cmp::min(lines.len() as u16 + 2, size.height);
+            let x = (size.width - w) / 2;
+            let y = (size.height - h) / 2;
+            let help_block = Block::default().borders(Borders::ALL).title("Help").style(theme.popup);
 
             let help_paragraph = Paragraph::new(lines).block(help_block);
             let help_rect = Rect::new(x, y, w, h);
+            f.render_widget(Clear, help_rect);
             f.render_widget(help_paragraph, help_rect);
         }
     }
 
 
+    /// Keys of dialogs and the directory, everything else comes as `Action`
     pub async fn handle_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
         if self.show_open_dialog {
-            self.handle_open_dialog_events(key)
+            self.handle_open_dialog_events(key).await
+        } else if self.station_editor.is_some() {
+            self.handle_editor_events(key).await
+        } else if self.show_directory {
+            self.handle_directory_events(key).await
+        } else if self.search.is_active() {
+            let titles = self.list_titles();
+            let state = if self.active_list_state == 0 { &mut self.list_streams_state } else { &mut self.list_songs_state };
+            self.search.handle_key(key, &titles, state);
+            Ok(false)
         } else {
-            match key.code {
-                KeyCode::Char('o') => {
-                    self.show_open_dialog = true;
-                },
-                KeyCode::Char('q') => return Ok(true),
-                KeyCode::Enter => {
-                    match self.active_stream {
-                        Some(_) => {
-                            self.player_engine = Arc::new(RwLock::new(PlayerEngine::new()));
-                            self.active_stream = None;
-                        },
-                        None => {
-                            if let Some(index) = self.list_streams_state.selected() {
-                                let selected_stream = &self.streams_collection[index];
-                                self.active_stream = Some(selected_stream.clone());
-                                let mut p = self.player_engine.write().unwrap();
-                                match p.open(selected_stream) {
-                                    Ok(_) => {
-                                        self.error = None;
-                                    },
-                                    Err(e) => self.error = Some(e.to_string()),
-                                }
-                            }
-                        },
-                    }
+            Ok(false)
+        }
+    }
+
+    /// Keymap scopes of the focused list or the directory
+    pub fn key_scopes(&self) -> &'static [Scope] {
+        if self.show_directory {
+            &[Scope::Directory]
+        } else if self.active_list_state == 1 {
+            &[Scope::RecentlyHeard, Scope::Radio]
+        } else {
+            &[Scope::Radio]
+        }
+    }
+
+    /// Click selects in a list, double click plays the station or toggles the folder,
+    /// the wheel moves the selection
+    pub async fn handle_mouse(&mut self, mouse: Mouse) -> std::io::Result<()> {
+        if self.show_directory {
+            let action = self.directory.handle_mouse(mouse);
+            return self.handle_directory_action(action).await.map(|_| ());
+        }
+
+        let action = if mouse.is_in(self.stations_area) {
+            self.active_list_state = 0;
+            mouse.list_action(self.stations_area, &mut self.list_streams_state, self.station_rows.len())
+        } else if mouse.is_in(self.songs_area) {
+            self.active_list_state = 1;
+            // songs can't be played
+            mouse.list_action(self.songs_area, &mut self.list_songs_state, self.songs_collection.len()).filter(|a| *a != Action::Select)
+        } else {
+            None
+        };
+        match action {
+            // switch to another station instead of stopping the playing one
+            Some(Action::Select) => match self.selected_station().cloned() {
+                Some(station) if self.active_stream.as_ref().map(|s| s.id) != Some(station.id) => {
+                    self.play_station(station);
+                    Ok(())
                 },
-                KeyCode::Char(' ') => {
-                    if self.active_stream.is_some() {
-                        let mut p = self.player_engine.write().unwrap();
-                        if p.is_playing() != Playing::Playing {
-                            p.resume()
-                        } else {
-                            p.pause()
+                _ => self.handle_action(Action::Select).await,
+            },
+            Some(action) => self.handle_action(action).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn handle_action(&mut self, action: Action) -> std::io::Result<()> {
+        if self.show_directory {
+            let action = self.directory.handle_action(action);
+            return self.handle_directory_action(action).await.map(|_| ());
+        }
+        match action {
+            Action::Add => {
+                self.show_open_dialog = true;
+            },
+            Action::Record => {
+                self.toggle_recording();
+            },
+            Action::RecordInBackground => {
+                self.toggle_background_recording();
+            },
+            Action::ToggleSplitRecordings => {
+                self.split_recordings = !self.split_recordings;
+                self.message = Some(format!("split recordings on title change: {}", if self.split_recordings { "on" } else { "off" }));
+            },
+            Action::SearchDirectory => {
+                self.show_directory = true;
+                self.directory.focus = 0;
+            },
+            Action::SwitchPane => {
+                self.active_list_state = (self.active_list_state + 1) % 2;
+            },
+            Action::CopyTitle if self.active_list_state == 1 => {
+                let selected = self.list_songs_state.selected().and_then(|i| self.songs_collection.get(i));
+                if let Some(title) = selected.map(|s| s.title.clone()) {
+                    self.message = match self.copy_to_clipboard(title) {
+                        Ok(_) => Some("title copied".to_string()),
+                        Err(e) => Some(format!("copy failed: {}", e)),
+                    };
+                }
+            },
+            Action::Export if self.active_list_state == 1 => {
+                self.message = match self.export_songs().await {
+                    Ok(path) => Some(format!("exported to {}", path.display())),
+                    Err(e) => Some(format!("export failed: {}", e)),
+                };
+            },
+            Action::Search => {
+                self.search.start(if self.active_list_state == 0 { &self.list_streams_state } else { &self.list_songs_state });
+            },
+            action if list_navigation::is_motion(action) => {
+                self.handle_motion(action, None);
+            },
+            Action::Select if self.selected_station().is_none() => {
+                self.toggle_selected_folder();
+            },
+            Action::Select => {
+                match self.active_stream {
+                    Some(_) => self.stop(),
+                    None => {
+                        if let Some(station) = self.selected_station().cloned() {
+                            self.play_station(station);
                         }
-                    }
-                },
-                KeyCode::Char('+') | KeyCode::Char('=') => {
-                    let mut p = self.player_engine.write().unwrap();
-                    p.increase_volume();
-                },
-                KeyCode::Char('-') => {
-                    let mut p = self.player_engine.write().unwrap();
-                    p.decrease_volume();
-                },
-                KeyCode::Down => {
-                    let mut selected = self.list_streams_state.selected().unwrap_or_default();
-                    let len = self.streams_collection.len();
-                    if len > 0 {
-                        selected = if selected >= len - 1 { 0 } else { selected + 1 };
-                        self.list_streams_state.select(Some(selected));
-                    }
-                },
-                KeyCode::Up => {
-                    let mut selected = self.list_streams_state.selected().unwrap_or_default();
-                    let len = self.streams_collection.len();
-                    if len > 0 {
-                        selected = if selected <= 0 { len - 1 } else { selected - 1 };
-                        self.list_streams_state.select(Some(selected));
-                    }
-                },
-                KeyCode::Char('d') | KeyCode::Delete => {
-                    let selected = self.list_streams_state.selected().unwrap_or_default();
-                    self.streams_collection.remove(selected);
-                    if selected >= self.streams_collection.len() {
-                        self.list_streams_state.select(Some(selected - 1));
-                    }
-                    config::save(self.streams_collection.clone())?;
-                },
-                KeyCode::Char('h') => {
-                    self.help_visible = !self.help_visible;
-                },
-                _ => {}
+                    },
+                }
+            },
+            Action::Edit => {
+                if let Some(station) = self.selected_station() {
+                    self.station_editor = Some(StationEditor::new(station));
+                }
+            },
+            Action::ToggleFavorite if self.active_list_state == 0 => {
+                if let Some(station) = self.selected_station() {
+                    RadioStationDataLayer::set_favorite(&self.db, station.id, !station.favorite).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                    self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                }
+            },
+            Action::MoveUp | Action::MoveDown if self.active_list_state == 0 => {
+                self.move_selected_station(action == Action::MoveUp).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+            },
+            Action::TogglePause => {
+                self.toggle_pause();
+            },
+            Action::VolumeUp => {
+                let mut p = self.player_engine.write().unwrap();
+                p.increase_volume();
+            },
+            Action::VolumeDown => {
+                let mut p = self.player_engine.write().unwrap();
+                p.decrease_volume();
+            },
+            Action::Delete if self.active_list_state == 0 => {
+                if let Some(station) = self.selected_station() {
+                    RadioStationDataLayer::delete_station(&self.db, station.id).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                    self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                }
+            },
+            Action::ToggleHelp => {
+                self.help_visible = !self.help_visible;
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Move the selection of the focused list, `count` was typed before the keys
+    pub fn handle_motion(&mut self, action: Action, count: Option<usize>) {
+        let titles = self.list_titles();
+        let (state, area) = match self.active_list_state {
+            0 => (&mut self.list_streams_state, self.stations_area),
+            _ => (&mut self.list_songs_state, self.songs_area),
+        };
+        match action {
+            Action::SearchNext | Action::SearchPrevious => self.search.repeat(&titles, state, action == Action::SearchNext, count),
+            _ => list_navigation::navigate(state, titles.len(), area.height.saturating_sub(2) as usize, action, count),
+        }
+    }
+
+    /// Titles of the focused list: folders and stations, or songs
+    fn list_titles(&self) -> Vec<String> {
+        match self.active_list_state {
+            0 => self.station_rows.iter().map(|row| match row {
+                StationRow::Folder { folder, .. } => folder.title().to_string(),
+                StationRow::Station(i) => station_title(&self.streams_collection[*i]),
+            }).collect(),
+            _ => self.songs_collection.iter().map(|s| s.title.clone()).collect(),
+        }
+    }
+
+    /// Play station once its stream connects, the result comes back as `AsyncAction::RadioConnected`
+    pub fn play_station(&mut self, station: RadioStationModel) {
+        self.active_stream = Some(station.clone());
+        self.preview = None;
+        self.playback.begin(PlaybackSource::Radio { station_id: Some(station.id), name: station_title(&station) });
+        self.start_connect(station.resolved_url.clone(), station.url.clone(), ConnectTarget::Station(Box::new(station)));
+    }
+
+    /// Stop the previous stream and connect the new one in a blocking task
+    fn start_connect(&mut self, resolved_url: Option<String>, url: String, target: ConnectTarget) {
+        self.player_engine.write().unwrap().stop();
+        self.live_status = None;
+        self.error = None;
+        // the new station may announce the same title, it is still heard there
+        self.last_song_title = None;
+        self.connect_request = Some(self.spawn_connect(resolved_url, url, target));
+    }
+
+    /// Connect in a blocking task, the result comes back as `AsyncAction::RadioConnected`.
+    /// Returns id of the request
+    fn spawn_connect(&mut self, resolved_url: Option<String>, url: String, target: ConnectTarget) -> u64 {
+        let request = self.next_request;
+        self.next_request += 1;
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = connect(resolved_url, url);
+            let _ = tx.send(AsyncAction::RadioConnected(RadioConnection { request, target, result }));
+        });
+        request
+    }
+
+    /// Play the connected stream and remember the url resolved from station playlist,
+    /// or start the connected background recording
+    pub async fn on_connected(&mut self, connection: RadioConnection) -> Result<(), DbErr> {
+        if let ConnectTarget::BackgroundRecording(station) = connection.target {
+            if self.background_request.as_ref().map(|(r, _)| *r) == Some(connection.request) {
+                self.background_request = None;
+                self.on_background_connected(*station, connection.result);
             }
-            Ok(false)
+            return Ok(());
+        }
+        if self.connect_request != Some(connection.request) {
+            return Ok(());
+        }
+        self.connect_request = None;
+        match (connection.target, connection.result) {
+            (ConnectTarget::Station(station), Ok((url, relay))) => self.play_connected(*station, &url, relay).await?,
+            (_, Ok((url, relay))) => self.player_engine.write().unwrap().open_relay(&url, relay),
+            (_, Err(e)) => {
+                self.error = Some(e);
+                self.stop();
+                self.preview = None;
+            },
+        }
+        Ok(())
+    }
+
+    /// Play station from relay connected with `connect`, e.g. by a scheduled alarm
+    pub async fn play_connected(&mut self, station: RadioStationModel, url: &str, relay: StreamRelay) -> Result<(), DbErr> {
+        if self.active_stream.as_ref().map(|s| s.id) != Some(station.id) {
+            self.playback.begin(PlaybackSource::Radio { station_id: Some(station.id), name: station_title(&station) });
+            self.last_song_title = None;
+        }
+        self.active_stream = Some(station.clone());
+        self.preview = None;
+        self.connect_request = None;
+        self.live_status = None;
+        self.error = None;
+        let resolved_url = Some(relay.resolved_url().to_string()).filter(|r| *r != station.url);
+        self.player_engine.write().unwrap().open_relay(url, relay);
+        if resolved_url != station.resolved_url {
+            RadioStationDataLayer::set_resolved_url(&self.db, station.id, resolved_url).await?;
+            self.reload().await?;
+        }
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.playback.stop();
+        self.active_stream = None;
+        // a pending connect result is dropped
+        self.connect_request = None;
+    }
+
+    /// Play station `step` places from the playing one in the collection
+    pub fn play_adjacent(&mut self, step: isize) {
+        let len = self.streams_collection.len() as isize;
+        let current = match self.active_stream.as_ref().and_then(|a| self.streams_collection.iter().position(|s| s.id == a.id)) {
+            Some(i) => i as isize,
+            None => return,
+        };
+        let station = self.streams_collection[(current + step).rem_euclid(len) as usize].clone();
+        self.play_station(station);
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.active_stream.is_some() || self.preview.is_some() {
+            let mut p = self.player_engine.write().unwrap();
+            if p.is_playing() != Playing::Playing {
+                p.resume()
+            } else {
+                p.pause()
+            }
+        }
+    }
+
+    /// Podcast took over the shared player
+    pub fn on_interrupted(&mut self) {
+        self.active_stream = None;
+        self.preview = None;
+        self.live_status = None;
+        self.connect_request = None;
+    }
+
+    /// Record the playing stream
+    fn toggle_recording(&mut self) {
+        let name = match (self.active_stream.as_ref(), self.preview.as_ref()) {
+            (Some(s), _) => station_title(s),
+            (None, Some(p)) => p.name.clone(),
+            (None, None) => return,
+        };
+        let p = self.player_engine.read().unwrap();
+        if let Some(relay) = p.relay() {
+            if relay.recording_status().is_some() {
+                relay.stop_recording();
+            } else if let Err(e) = relay.start_recording(config::recordings_dir(), &name, self.split_recordings) {
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Record the selected station without listening to it, stops the running or connecting one
+    fn toggle_background_recording(&mut self) {
+        if self.background_recording.take().is_some() || self.background_request.take().is_some() {
+            return;
+        }
+        let station = match self.selected_station() {
+            Some(s) => s.clone(),
+            None => return,
+        };
+        let title = station_title(&station);
+        let request = self.spawn_connect(station.resolved_url.clone(), station.url.clone(), ConnectTarget::BackgroundRecording(Box::new(station)));
+        self.background_request = Some((request, title));
+    }
+
+    fn on_background_connected(&mut self, station: RadioStationModel, result: Result<(String, StreamRelay), String>) {
+        let res = result.and_then(|(_, relay)| {
+            relay.start_recording(config::recordings_dir(), &station_title(&station), self.split_recordings).map_err(|e| e.to_string())?;
+            Ok(relay)
+        });
+        match res {
+            Ok(relay) => self.background_recording = Some((station, relay)),
+            Err(e) => self.error = Some(format!("Recording of {} failed: {}", station_title(&station), e)),
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), Box<dyn std::error::Error>> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(ClipboardProvider::new()?);
+        }
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            clipboard.set_contents(text)?;
+        }
+        Ok(())
+    }
+
+    /// Write the whole song log to `~/librecast-songs.csv`
+    async fn export_songs(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = home::home_dir().unwrap_or("./".into()).join("librecast-songs.csv");
+
+        let mut csv = String::from("heard_at,station_id,station,title\n");
+        RadioSongDataLayer::get_songs(&self.db, None).await?.iter().rev().for_each(|s| {
+            csv.push_str(&format!("{},{},{},{}\n",
+                s.heard_at.to_rfc3339(),
+                s.station_id,
+                csv::field(s.station_name.as_deref().unwrap_or_default()),
+                csv::field(&s.title)));
+        });
+        std::fs::write(&path, csv)?;
+
+        Ok(path)
+    }
+
+    async fn handle_directory_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        let action = self.directory.handle_events(key);
+        self.handle_directory_action(action).await
+    }
+
+    async fn handle_directory_action(&mut self, action: Option<DirectoryAction>) -> std::io::Result<bool> {
+        match action {
+            Some(DirectoryAction::Close) => {
+                self.show_directory = false;
+                if self.preview.take().is_some() {
+                    self.stop();
+                }
+            },
+            Some(DirectoryAction::Preview(station)) => {
+                self.active_stream = None;
+                self.preview = Some(station.clone());
+                self.playback.begin(PlaybackSource::Radio { station_id: None, name: station.name.clone() });
+                self.start_connect(None, station.url.clone(), ConnectTarget::Preview);
+            },
+            Some(DirectoryAction::Add(station)) => {
+                RadioStationDataLayer::add_station(&self.db, RadioDirectoryDataLayer::to_radio_station(&station)).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                self.directory.message = Some(format!("{} added to collection", station.name));
+            },
+            None => {},
+        }
+        Ok(false)
+    }
+
+    async fn handle_editor_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        let editor = match self.station_editor.as_mut() {
+            Some(e) => e,
+            None => return Ok(false),
+        };
+
+        match key.code {
+            KeyCode::Esc => self.station_editor = None,
+            KeyCode::Up => editor.focus = (editor.focus + FIELDS - 1) % FIELDS,
+            KeyCode::Down | KeyCode::Tab => editor.focus = (editor.focus + 1) % FIELDS,
+            KeyCode::Enter => {
+                let url = editor.url.text.trim().to_string();
+                if url.is_empty() {
+                    editor.error = Some("url can't be empty".to_string());
+                    return Ok(false);
+                }
+                RadioStationDataLayer::update_station(&self.db, &editor.station, non_empty(&editor.name.text), url, non_empty(&editor.group.text))
+                    .await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                self.station_editor = None;
+                self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+            },
+            _ => editor.textbox_mut().handle_events(key.code, key.modifiers),
         }
+        Ok(false)
     }
 
-    fn handle_open_dialog_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+    async fn handle_open_dialog_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
         match (key.code, key.modifiers) {
             (KeyCode::Esc, _) => self.show_open_dialog = false,
             (KeyCode::Enter, _) => {
-                self.streams_collection.push(self.textbox_state.text.clone());
-                config::save(self.streams_collection.clone())?;
+                let url = self.textbox_state.text.trim().to_string();
+                if !url.is_empty() {
+                    let station = RadioStationDataLayer::station_from_url(&url);
+                    RadioStationDataLayer::add_station(&self.db, station).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                    self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                }
+                self.textbox_state.text = String::new();
+                self.textbox_state.cursor_pos = 0;
                 self.show_open_dialog = false;
             },
             (key_code, key_modifiers) => {
@@ -261,3 +1025,132 @@ impl RadioModel {
     }
 
 }
+
+fn recording_line(label: &str, status: &RecordingStatus) -> Line<'static> {
+    let theme = theme::get();
+    let seconds = status.duration.as_secs();
+    let file = status.path.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+    if let Some(error) = status.error.as_ref() {
+        return Line::from(vec![
+            Span::styled(format!("● REC failed {}{}: ", label, file), theme.error),
+            Span::styled(error.clone(), theme.error),
+        ]);
+    }
+    Line::from(vec![
+        Span::styled("● REC ", theme.error),
+        Span::raw(format!("{}{:02}:{:02}:{:02}  {:.1} MB  ", label, seconds / 3600, seconds / 60 % 60, seconds % 60, status.bytes as f64 / 1_000_000.0)),
+        Span::styled(file, theme.muted),
+    ])
+}
+
+/// Station name, or url for stations without one
+pub fn station_title(station: &RadioStationModel) -> String {
+    station.name.clone().filter(|n| !n.trim().is_empty()).unwrap_or(station.url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveValue, Database};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    /// Model with stations of `(name, group, favorite)` in this order
+    async fn model(stations: &[(&str, Option<&str>, bool)]) -> RadioModel {
+        config::init(Default::default());
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        for (name, group, favorite) in stations {
+            let mut station = RadioStationDataLayer::station_from_url(&format!("http://radio.example.com/{}", name));
+            station.name = ActiveValue::set(Some(name.to_string()));
+            station.group_name = ActiveValue::set(group.map(str::to_string));
+            station.favorite = ActiveValue::set(*favorite);
+            RadioStationDataLayer::add_station(&db, station).await.unwrap();
+        }
+        let (tx, _) = mpsc::unbounded_channel();
+        let mut model = RadioModel::new(db, tx.clone(), Playback::new(tx));
+        model.reload().await.unwrap();
+        model
+    }
+
+    fn select(model: &mut RadioModel, title: &str) {
+        let row = model.list_titles().iter().position(|t| t == title);
+        model.list_streams_state.select(row);
+    }
+
+    fn selected_title(model: &RadioModel) -> Option<String> {
+        model.list_streams_state.selected().map(|i| model.list_titles()[i].clone())
+    }
+
+    #[tokio::test]
+    async fn favorites_are_pinned_before_groups_and_ungrouped_stations() {
+        let model = model(&[
+            ("Ambient", Some("Chill"), false),
+            ("Loose", None, false),
+            ("Fav", Some("Chill"), true),
+            ("Delta", Some(" Blues "), false),
+            ("Blank", Some(" "), false),
+        ]).await;
+        assert_eq!(model.list_titles(), ["★ Favorites", "Fav", "Blues", "Delta", "Chill", "Ambient", "Loose", "Blank"].map(str::to_string));
+    }
+
+    #[tokio::test]
+    async fn group_named_like_favorites_stays_separate() {
+        let mut model = model(&[("Fav", None, true), ("Grouped", Some(FAVORITES_TITLE), false)]).await;
+        assert_eq!(model.list_titles(), ["★ Favorites", "Fav", "★ Favorites", "Grouped"].map(str::to_string));
+
+        // collapsing the group leaves the favorites open
+        model.list_streams_state.select(Some(2));
+        model.toggle_selected_folder();
+        assert_eq!(model.list_titles(), ["★ Favorites", "Fav", "★ Favorites"].map(str::to_string));
+    }
+
+    #[tokio::test]
+    async fn collapsed_folders_hide_their_stations() {
+        let mut model = model(&[("Jazz 1", Some("Jazz"), false), ("Jazz 2", Some("Jazz"), false), ("Rock 1", Some("Rock"), false)]).await;
+        select(&mut model, "Jazz");
+        model.toggle_selected_folder();
+        assert_eq!(model.list_titles(), ["Jazz", "Rock", "Rock 1"].map(str::to_string));
+        assert!(matches!(model.station_rows[0], StationRow::Folder { count: 2, collapsed: true, .. }));
+        assert_eq!(model.selected_station(), None);
+
+        // stays collapsed on reload, opens again on the next toggle
+        model.reload().await.unwrap();
+        assert_eq!(model.list_titles().len(), 3);
+        model.list_streams_state.select(Some(0));
+        model.toggle_selected_folder();
+        assert_eq!(model.list_titles(), ["Jazz", "Jazz 1", "Jazz 2", "Rock", "Rock 1"].map(str::to_string));
+    }
+
+    #[tokio::test]
+    async fn selection_follows_the_station() {
+        let mut model = model(&[("A", None, false), ("B", None, false), ("C", Some("Group"), false)]).await;
+        select(&mut model, "B");
+        let id = model.selected_station().unwrap().id;
+        RadioStationDataLayer::set_favorite(&model.db, id, true).await.unwrap();
+        model.reload().await.unwrap();
+        assert_eq!(model.list_titles(), ["★ Favorites", "B", "Group", "C", "A"].map(str::to_string));
+        assert_eq!(model.selected_station().map(|s| s.id), Some(id));
+    }
+
+    #[tokio::test]
+    async fn stations_move_only_within_their_folder() {
+        let mut model = model(&[("A", Some("G"), false), ("B", Some("G"), false), ("C", None, false), ("D", None, false)]).await;
+        select(&mut model, "B");
+        model.move_selected_station(true).await.unwrap();
+        assert_eq!(model.list_titles(), ["G", "B", "A", "C", "D"].map(str::to_string));
+        assert_eq!(selected_title(&model).as_deref(), Some("B"));
+
+        // neither onto the folder row nor out of the folder
+        model.move_selected_station(true).await.unwrap();
+        select(&mut model, "A");
+        model.move_selected_station(false).await.unwrap();
+        assert_eq!(model.list_titles(), ["G", "B", "A", "C", "D"].map(str::to_string));
+
+        select(&mut model, "D");
+        model.move_selected_station(true).await.unwrap();
+        assert_eq!(model.list_titles(), ["G", "B", "A", "D", "C"].map(str::to_string));
+        assert_eq!(selected_title(&model).as_deref(), Some("D"));
+    }
+}
@@ -0,0 +1,301 @@
+use chrono::{Local, NaiveDate, NaiveTime};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{layout::Rect, style::{Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, List, ListState, Paragraph}, Frame};
+use sea_orm::{ActiveValue, DatabaseConnection};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_textbox::{Textbox, TextboxState};
+
+use crate::{data_layer::schedule_data_layer::ScheduleDataLayer, keymap::{self, Action, Scope}, mouse::Mouse, radio_model::station_title, scheduler::{self, RunningJobStatus, KIND_ALARM, KIND_RECORD}, streaming::recorder::RecordingStatus, theme, AsyncAction};
+use crate::entity::radio_station::Model as RadioStationModel;
+use crate::entity::schedule_job::{ActiveModel as ScheduleJobActiveModel, Model as ScheduleJobModel};
+
+const FIELD_STATION: usize = 0;
+const FIELD_KIND: usize = 1;
+const FIELD_START: usize = 2;
+const FIELD_DURATION: usize = 3;
+const FIELD_RECURRENCE: usize = 4;
+const FIELD_DATE: usize = 5;
+const FIELDS: usize = 6;
+
+/// Add / edit job dialog
+struct JobEditor {
+    id: Option<i32>,
+    station_index: usize,
+    alarm: bool,
+    start: TextboxState,
+    duration: TextboxState,
+    recurrence: TextboxState,
+    date: TextboxState,
+    focus: usize,
+    error: Option<String>,
+}
+
+fn textbox(text: String) -> TextboxState {
+    let mut state = TextboxState::default();
+    state.cursor_pos = text.chars().count();
+    state.text = text;
+    state
+}
+
+impl JobEditor {
+    fn new(job: Option<&ScheduleJobModel>, stations: &[RadioStationModel]) -> Self {
+        let today = Local::now().date_naive();
+        JobEditor {
+            id: job.map(|j| j.id),
+            station_index: job.and_then(|j| stations.iter().position(|s| s.id == j.station_id)).unwrap_or_default(),
+            alarm: job.is_some_and(|j| j.kind == KIND_ALARM),
+            start: textbox(job.map(|j| j.start_time.format("%H:%M").to_string()).unwrap_or("07:00".to_string())),
+            duration: textbox(job.map(|j| j.duration_minutes.to_string()).unwrap_or("60".to_string())),
+            recurrence: textbox(job.map(|j| j.recurrence.clone()).unwrap_or("weekdays".to_string())),
+            date: textbox(job.and_then(|j| j.date).unwrap_or(today).to_string()),
+            focus: FIELD_STATION,
+            error: None,
+        }
+    }
+
+    fn textbox_mut(&mut self) -> Option<&mut TextboxState> {
+        match self.focus {
+            FIELD_START => Some(&mut self.start),
+            FIELD_DURATION => Some(&mut self.duration),
+            FIELD_RECURRENCE => Some(&mut self.recurrence),
+            FIELD_DATE => Some(&mut self.date),
+            _ => None,
+        }
+    }
+
+    fn to_active_model(&self, stations: &[RadioStationModel]) -> Result<ScheduleJobActiveModel, String> {
+        let station = stations.get(self.station_index).ok_or("add a radio station first")?;
+        let start_time = NaiveTime::parse_from_str(self.start.text.trim(), "%H:%M").map_err(|_| "start time must be HH:MM")?;
+        let duration: i32 = self.duration.text.trim().parse().ok().filter(|d| *d > 0).ok_or("duration must be minutes > 0")?;
+        let recurrence = self.recurrence.text.trim().to_lowercase();
+        if !scheduler::is_valid_recurrence(&recurrence) {
+            return Err("recurrence must be once, daily, weekdays, weekends or days like mon,wed".to_string());
+        }
+        let date = if recurrence == "once" {
+            Some(NaiveDate::parse_from_str(self.date.text.trim(), "%Y-%m-%d").map_err(|_| "date must be YYYY-MM-DD")?)
+        } else {
+            None
+        };
+
+        Ok(ScheduleJobActiveModel {
+            id: match self.id {
+                Some(id) => ActiveValue::unchanged(id),
+                None => ActiveValue::NotSet,
+            },
+            station_id: ActiveValue::set(station.id),
+            kind: ActiveValue::set(if self.alarm { KIND_ALARM } else { KIND_RECORD }.to_string()),
+            start_time: ActiveValue::set(start_time),
+            duration_minutes: ActiveValue::set(duration),
+            recurrence: ActiveValue::set(recurrence),
+            date: ActiveValue::set(date),
+            enabled: ActiveValue::NotSet,
+            last_run_at: ActiveValue::NotSet,
+        })
+    }
+}
+
+pub struct ScheduleModel {
+    db: DatabaseConnection,
+    tx: UnboundedSender<AsyncAction>,
+    pub jobs: Vec<ScheduleJobModel>,
+    pub stations: Vec<RadioStationModel>,
+    pub running: Vec<RunningJobStatus>,
+    pub list_state: ListState,
+    /// where the list was rendered, for mouse input
+    area: Rect,
+    editor: Option<JobEditor>,
+}
+
+impl ScheduleModel {
+    pub fn new(db: DatabaseConnection, tx: UnboundedSender<AsyncAction>) -> Self {
+        Self {
+            db,
+            tx,
+            jobs: vec![],
+            stations: vec![],
+            running: vec![],
+            list_state: Default::default(),
+            area: Rect::default(),
+            editor: None,
+        }
+    }
+
+    pub fn set_jobs(&mut self, jobs: Vec<ScheduleJobModel>, stations: Vec<RadioStationModel>) {
+        self.jobs = jobs;
+        self.stations = stations;
+        let selected = match self.list_state.selected() {
+            _ if self.jobs.is_empty() => None,
+            Some(s) => Some(std::cmp::min(s, self.jobs.len() - 1)),
+            None => Some(0),
+        };
+        self.list_state.select(selected);
+    }
+
+    /// Job editor takes all keys
+    pub fn captures_input(&self) -> bool {
+        self.editor.is_some()
+    }
+
+    fn station_name(&self, station_id: i32) -> String {
+        self.stations.iter().find(|s| s.id == station_id).map(station_title).unwrap_or("-".to_string())
+    }
+
+    pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
+        let now = Local::now();
+        let lines: Vec<Line> = self.jobs.iter().map(|j| {
+            let running = self.running.iter().find(|r| r.job_id == j.id);
+            let state = match running {
+                Some(RunningJobStatus { recording: Some(RecordingStatus { error: Some(e), .. }), .. }) => format!("● recording failed: {}", e),
+                Some(RunningJobStatus { recording: Some(r), .. }) => format!("● recording {}m {:.1} MB", r.duration.as_secs() / 60, r.bytes as f64 / 1_000_000.0),
+                Some(_) => "● playing".to_string(),
+                None => scheduler::next_occurrence(j, now).map(|n| format!("next {}", n.format("%a %d.%m. %H:%M"))).unwrap_or("-".to_string()),
+            };
+            Line::from(vec![
+                Span::raw(format!("[{}] ", if j.enabled { "x" } else { " " })),
+                Span::styled(format!("{} {:>4}m ", j.start_time.format("%H:%M"), j.duration_minutes), theme.highlight),
+                Span::styled(format!("{:<6} ", j.kind), if j.kind == KIND_ALARM { theme.alarm } else { theme.error }),
+                Span::raw(format!("{} ", self.station_name(j.station_id))),
+                Span::styled(format!("{}{}  ", j.recurrence, j.date.map(|d| format!(" {}", d)).unwrap_or_default()), theme.muted),
+                Span::styled(state, theme.accent),
+            ])
+        }).collect();
+
+        let list = List::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Schedule").title_bottom(keymap::get().hint(Scope::Schedule, &[Action::Add, Action::Edit, Action::Delete, Action::ToggleEnabled])))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ")
+            .repeat_highlight_symbol(true);
+
+        self.area = rect;
+        f.render_stateful_widget(list, rect, &mut self.list_state);
+
+        if let Some(editor) = self.editor.as_mut() {
+            let w = std::cmp::min(70, rect.width);
+            let h = 11;
+            let area = Rect::new(rect.x + (rect.width - w) / 2, rect.y + rect.height.saturating_sub(h) / 3, w, h);
+            f.render_widget(Clear, area);
+            let title = if editor.id.is_some() { "Edit job" } else { "Add job" };
+            f.render_widget(Block::default().borders(Borders::ALL).title(title).style(theme.popup), area);
+
+            let station = self.stations.get(editor.station_index).map(station_title).unwrap_or("-".to_string());
+            let labels = ["station", "mode", "start", "minutes", "repeat", "date"];
+            for (i, label) in labels.iter().enumerate() {
+                let y = area.y + 1 + i as u16;
+                let style = if editor.focus == i { theme.highlight } else { Style::default() };
+                f.render_widget(Paragraph::new(Span::styled(format!("{:<9}", label), style)), Rect::new(area.x + 1, y, 9, 1));
+                let value_area = Rect::new(area.x + 11, y, area.width.saturating_sub(12), 1);
+                match i {
+                    FIELD_STATION => f.render_widget(Paragraph::new(format!("< {} >", station)), value_area),
+                    FIELD_KIND => f.render_widget(Paragraph::new(format!("< {} >", if editor.alarm { "alarm (play)" } else { "record" })), value_area),
+                    _ => {
+                        let state = match i {
+                            FIELD_START => &mut editor.start,
+                            FIELD_DURATION => &mut editor.duration,
+                            FIELD_RECURRENCE => &mut editor.recurrence,
+                            _ => &mut editor.date,
+                        };
+                        if editor.focus == i {
+                            f.render_stateful_widget(Textbox::default(), value_area, state);
+                        } else {
+                            f.render_widget(Paragraph::new(state.text.clone()), value_area);
+                        }
+                    },
+                }
+            }
+
+            let help = editor.error.clone().unwrap_or("<up>/<down> - field, <left>/<right> - change, <enter> - save, <esc> - cancel".to_string());
+            f.render_widget(Paragraph::new(help), Rect::new(area.x + 1, area.y + 8, area.width.saturating_sub(2), 2));
+        }
+    }
+
+    /// Keys of the job editor, everything else comes as `Action`
+    pub async fn handle_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        self.handle_editor_events(key).await
+    }
+
+    /// Double click edits the job
+    pub async fn handle_mouse(&mut self, mouse: Mouse) -> std::io::Result<()> {
+        match mouse.list_action(self.area, &mut self.list_state, self.jobs.len()) {
+            Some(Action::Select) => self.handle_action(Action::Edit).await,
+            Some(action) => self.handle_action(action).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn handle_action(&mut self, action: Action) -> std::io::Result<()> {
+        let selected = self.list_state.selected().and_then(|i| self.jobs.get(i)).cloned();
+        match action {
+            Action::Add => {
+                self.editor = Some(JobEditor::new(None, &self.stations));
+            },
+            Action::Edit => {
+                if let Some(job) = selected {
+                    self.editor = Some(JobEditor::new(Some(&job), &self.stations));
+                }
+            },
+            Action::Delete => {
+                if let Some(job) = selected {
+                    ScheduleDataLayer::delete_job(&self.db, job.id).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                    let _ = self.tx.send(AsyncAction::RefreshSchedule);
+                }
+            },
+            Action::ToggleEnabled => {
+                if let Some(job) = selected {
+                    ScheduleDataLayer::set_enabled(&self.db, job.id, !job.enabled).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                    let _ = self.tx.send(AsyncAction::RefreshSchedule);
+                }
+            },
+            Action::Down | Action::Up => {
+                let len = self.jobs.len();
+                if len > 0 {
+                    let selected = self.list_state.selected().unwrap_or_default();
+                    let selected = if action == Action::Down { (selected + 1) % len } else { (selected + len - 1) % len };
+                    self.list_state.select(Some(selected));
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_editor_events(&mut self, key: KeyEvent) -> std::io::Result<bool> {
+        let stations_len = self.stations.len();
+        let editor = match self.editor.as_mut() {
+            Some(e) => e,
+            None => return Ok(false),
+        };
+
+        match key.code {
+            KeyCode::Esc => self.editor = None,
+            KeyCode::Up => editor.focus = (editor.focus + FIELDS - 1) % FIELDS,
+            KeyCode::Down | KeyCode::Tab => editor.focus = (editor.focus + 1) % FIELDS,
+            KeyCode::Left | KeyCode::Right if editor.focus == FIELD_STATION => {
+                if stations_len > 0 {
+                    editor.station_index = if key.code == KeyCode::Right {
+                        (editor.station_index + 1) % stations_len
+                    } else {
+                        (editor.station_index + stations_len - 1) % stations_len
+                    };
+                }
+            },
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') if editor.focus == FIELD_KIND => editor.alarm = !editor.alarm,
+            KeyCode::Enter => {
+                match editor.to_active_model(&self.stations) {
+                    Ok(job) => {
+                        ScheduleDataLayer::save_job(&self.db, job).await.map_err(|e| std::io::Error::other(e.to_string()))?;
+                        self.editor = None;
+                        let _ = self.tx.send(AsyncAction::RefreshSchedule);
+                    },
+                    Err(e) => editor.error = Some(e),
+                }
+            },
+            _ => {
+                if let Some(state) = editor.textbox_mut() {
+                    state.handle_events(key.code, key.modifiers);
+                }
+            },
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,412 @@
+use std::{collections::HashMap, time::Instant};
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
+use sea_orm::{DatabaseConnection, DbErr};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{config, data_layer::schedule_data_layer::ScheduleDataLayer, radio_model::{self, station_title, RadioModel}, streaming::{recorder::RecordingStatus, relay::StreamRelay}, AsyncAction};
+use crate::entity::radio_station::Model as RadioStationModel;
+use crate::entity::schedule_job::Model as ScheduleJobModel;
+
+pub const KIND_RECORD: &str = "record";
+pub const KIND_ALARM: &str = "alarm";
+
+/// Wait after a failed start before trying again while the occurrence lasts
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+/// Alarm volume goes from `ALARM_START_VOLUME` of the volume set before to all of it in this long
+const ALARM_RAMP: std::time::Duration = std::time::Duration::from_secs(60);
+const ALARM_START_VOLUME: f32 = 0.1;
+
+enum RunningKind {
+    Record(StreamRelay),
+    Alarm(AlarmRamp),
+}
+
+struct AlarmRamp {
+    started: Instant,
+    /// volume before the alarm, which the ramp ends at
+    volume: f32,
+    /// last volume set by the ramp, None once the ramp is over
+    /// or the volume was changed otherwise (e.g. by the user)
+    applied: Option<f32>,
+}
+
+struct RunningJob {
+    job_id: i32,
+    station_id: i32,
+    kind: RunningKind,
+    ends_at: DateTime<Local>,
+}
+
+/// State of a running job shown in the schedule view
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningJobStatus {
+    pub job_id: i32,
+    pub recording: Option<RecordingStatus>,
+}
+
+/// Stream of a due job connected off the UI task, posted back as `AsyncAction::ScheduledJobConnected`
+pub struct ScheduledConnection {
+    job: ScheduleJobModel,
+    station: RadioStationModel,
+    /// start of the occurrence
+    start: DateTime<Local>,
+    /// (url the relay was started for, relay)
+    result: Result<(String, StreamRelay), String>,
+}
+
+/// Starts scheduled recordings and alarms at their time and stops them after their duration.
+///
+/// A job counts as run (`last_run_at`) once its stream started, failed starts
+/// are retried every `RETRY_DELAY` until the occurrence is over.
+pub struct Scheduler {
+    db: DatabaseConnection,
+    tx: UnboundedSender<AsyncAction>,
+    pub jobs: Vec<ScheduleJobModel>,
+    running: Vec<RunningJob>,
+    /// ids of jobs being connected
+    starting: Vec<i32>,
+    /// job id -> no new attempt before, after a failed start
+    retry_at: HashMap<i32, Instant>,
+}
+
+impl Scheduler {
+    pub fn new(db: DatabaseConnection, tx: UnboundedSender<AsyncAction>) -> Self {
+        Self {
+            db,
+            tx,
+            jobs: Default::default(),
+            running: Default::default(),
+            starting: Default::default(),
+            retry_at: Default::default(),
+        }
+    }
+
+    pub async fn reload(&mut self) -> Result<(), DbErr> {
+        self.jobs = ScheduleDataLayer::get_jobs(&self.db).await?;
+        Ok(())
+    }
+
+    pub fn running_status(&self) -> Vec<RunningJobStatus> {
+        self.running.iter().map(|r| RunningJobStatus {
+            job_id: r.job_id,
+            recording: match &r.kind {
+                RunningKind::Record(relay) => relay.recording_status(),
+                RunningKind::Alarm(_) => None,
+            },
+        }).collect()
+    }
+
+    pub fn tick(&mut self, radio: &mut RadioModel) {
+        let now = Local::now();
+
+        let (finished, running): (Vec<RunningJob>, Vec<RunningJob>) = self.running.drain(..).partition(|r| now >= r.ends_at);
+        self.running = running;
+        for job in finished {
+            // recording stops when its relay is dropped
+            if let RunningKind::Alarm(ramp) = job.kind {
+                if radio.active_stream.as_ref().map(|s| s.id) == Some(job.station_id) {
+                    radio.stop();
+                }
+                let mut player_engine = radio.player_engine.write().unwrap();
+                if ramp.applied == Some(player_engine.get_volume()) {
+                    player_engine.set_volume(ramp.volume);
+                }
+            }
+        }
+        for job in self.running.iter_mut() {
+            if let RunningKind::Alarm(ramp) = &mut job.kind {
+                let mut player_engine = radio.player_engine.write().unwrap();
+                if ramp.applied.is_none_or(|v| v != player_engine.get_volume()) {
+                    ramp.applied = None;
+                    continue;
+                }
+                // volume goes back when the alarm was stopped before the ramp ended
+                let playing = radio.active_stream.as_ref().map(|s| s.id) == Some(job.station_id);
+                let elapsed = ramp.started.elapsed();
+                player_engine.set_volume(if playing { ramp_volume(elapsed, ramp.volume) } else { ramp.volume });
+                ramp.applied = (playing && elapsed < ALARM_RAMP).then(|| player_engine.get_volume());
+            }
+        }
+        self.retry_at.retain(|_, at| Instant::now() < *at);
+
+        let due: Vec<(ScheduleJobModel, DateTime<Local>)> = self.jobs.iter()
+            .filter(|j| j.enabled && !self.running.iter().any(|r| r.job_id == j.id))
+            .filter(|j| !self.starting.contains(&j.id) && !self.retry_at.contains_key(&j.id))
+            .filter_map(|j| current_occurrence(j, now).map(|start| (j.clone(), start)))
+            .filter(|(j, start)| j.last_run_at.is_none_or(|l| l < start.fixed_offset()))
+            .collect();
+
+        for (job, start) in due {
+            let station = match radio.streams_collection.iter().find(|s| s.id == job.station_id) {
+                Some(s) => s.clone(),
+                None => continue,
+            };
+            self.starting.push(job.id);
+            let tx = self.tx.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = radio_model::connect(station.resolved_url.clone(), station.url.clone());
+                let _ = tx.send(AsyncAction::ScheduledJobConnected(Box::new(ScheduledConnection { job, station, start, result })));
+            });
+        }
+    }
+
+    /// Play or record the connected stream of a due job and mark the job as run
+    pub async fn on_connected(&mut self, connection: ScheduledConnection, radio: &mut RadioModel) -> Result<(), DbErr> {
+        let ScheduledConnection { job, station, start, result } = connection;
+        self.starting.retain(|id| *id != job.id);
+        // job may have been disabled or deleted meanwhile
+        if !self.jobs.iter().any(|j| j.id == job.id && j.enabled) {
+            return Ok(());
+        }
+
+        let kind = match result {
+            Ok((url, relay)) if job.kind == KIND_ALARM => {
+                let ramp = {
+                    let mut player_engine = radio.player_engine.write().unwrap();
+                    let volume = player_engine.get_volume();
+                    player_engine.set_volume(ramp_volume(std::time::Duration::ZERO, volume));
+                    AlarmRamp { started: Instant::now(), volume, applied: Some(player_engine.get_volume()) }
+                };
+                radio.play_connected(station.clone(), &url, relay).await?;
+                Ok(RunningKind::Alarm(ramp))
+            },
+            Ok((_, relay)) => relay.start_recording(config::recordings_dir(), &station_title(&station), radio.split_recordings)
+                .map(|_| RunningKind::Record(relay))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        match kind {
+            Ok(kind) => {
+                let ends_at = start + Duration::minutes(job.duration_minutes as i64);
+                self.running.push(RunningJob { job_id: job.id, station_id: station.id, kind, ends_at });
+                ScheduleDataLayer::set_last_run(&self.db, job.id).await?;
+                self.reload().await?;
+            },
+            Err(e) => {
+                radio.error = Some(format!("Scheduled {} of {} failed, retrying: {}", job.kind, station_title(&station), e));
+                self.retry_at.insert(job.id, Instant::now() + RETRY_DELAY);
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Alarm volume `elapsed` after it started, going up to `volume`
+fn ramp_volume(elapsed: std::time::Duration, volume: f32) -> f32 {
+    let progress = (elapsed.as_secs_f32() / ALARM_RAMP.as_secs_f32()).min(1.0);
+    volume * (ALARM_START_VOLUME + (1.0 - ALARM_START_VOLUME) * progress)
+}
+
+/// Whether job has an occurrence starting on `day`
+pub fn runs_on(job: &ScheduleJobModel, day: NaiveDate) -> bool {
+    match job.recurrence.trim().to_lowercase().as_str() {
+        "once" => job.date == Some(day),
+        "daily" => true,
+        "weekdays" => day.weekday().num_days_from_monday() < 5,
+        "weekends" => day.weekday().num_days_from_monday() >= 5,
+        days => days.split(',').filter_map(|d| d.trim().parse::<Weekday>().ok()).any(|d| d == day.weekday()),
+    }
+}
+
+/// `once`, `daily`, `weekdays`, `weekends` or comma separated days like `mon,wed,fri`
+pub fn is_valid_recurrence(recurrence: &str) -> bool {
+    match recurrence.trim().to_lowercase().as_str() {
+        "once" | "daily" | "weekdays" | "weekends" => true,
+        days => days.split(',').all(|d| d.trim().parse::<Weekday>().is_ok()),
+    }
+}
+
+/// Occurrence start times in a DST gap move by the length of the gap (an hour)
+fn occurrence_start<Tz: TimeZone>(job: &ScheduleJobModel, day: NaiveDate, tz: &Tz) -> Option<DateTime<Tz>> {
+    if !runs_on(job, day) {
+        return None;
+    }
+    let start = day.and_time(job.start_time);
+    tz.from_local_datetime(&start).earliest()
+        .or_else(|| tz.from_local_datetime(&(start + Duration::hours(1))).earliest())
+}
+
+/// Start of the job occurrence which is going on at `now`,
+/// occurrences started the day before can run over midnight
+pub fn current_occurrence<Tz: TimeZone>(job: &ScheduleJobModel, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let today = now.date_naive();
+    [Some(today), today.pred_opt()].into_iter()
+        .flatten()
+        .filter_map(|d| occurrence_start(job, d, &now.timezone()))
+        .find(|start| *start <= now && now < start.clone() + Duration::minutes(job.duration_minutes as i64))
+}
+
+/// Start of the next job occurrence after `now`
+pub fn next_occurrence<Tz: TimeZone>(job: &ScheduleJobModel, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+    if job.recurrence.trim().eq_ignore_ascii_case("once") {
+        return job.date.and_then(|d| occurrence_start(job, d, &now.timezone())).filter(|start| *start > now);
+    }
+    let today = now.date_naive();
+    (0..8).filter_map(|d| today.checked_add_signed(Duration::days(d)))
+        .filter_map(|d| occurrence_start(job, d, &now.timezone()))
+        .find(|start| *start > now)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, MappedLocalTime, NaiveDateTime, NaiveTime};
+
+    use super::*;
+
+    /// Central European time of 2026, summer time from 29.3. 02:00 to 25.10. 03:00
+    #[derive(Debug, Clone, Copy)]
+    struct Cet2026;
+
+    const WINTER: i32 = 3600;
+    const SUMMER: i32 = 7200;
+
+    fn offset(seconds: i32) -> FixedOffset {
+        FixedOffset::east_opt(seconds).unwrap()
+    }
+
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    impl TimeZone for Cet2026 {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Cet2026
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local_time: &NaiveDateTime) -> MappedLocalTime<FixedOffset> {
+            match *local_time {
+                t if t < local("2026-03-29 02:00") => MappedLocalTime::Single(offset(WINTER)),
+                t if t < local("2026-03-29 03:00") => MappedLocalTime::None,
+                t if t < local("2026-10-25 02:00") => MappedLocalTime::Single(offset(SUMMER)),
+                t if t < local("2026-10-25 03:00") => MappedLocalTime::Ambiguous(offset(SUMMER), offset(WINTER)),
+                _ => MappedLocalTime::Single(offset(WINTER)),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            if *utc >= local("2026-03-29 01:00") && *utc < local("2026-10-25 01:00") { offset(SUMMER) } else { offset(WINTER) }
+        }
+    }
+
+    fn at(text: &str) -> DateTime<Cet2026> {
+        Cet2026.from_local_datetime(&local(text)).earliest().unwrap()
+    }
+
+    fn job(start: &str, duration_minutes: i32, recurrence: &str, date: Option<&str>) -> ScheduleJobModel {
+        ScheduleJobModel {
+            id: 1,
+            station_id: 1,
+            kind: KIND_RECORD.to_string(),
+            start_time: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            duration_minutes,
+            recurrence: recurrence.to_string(),
+            date: date.map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap()),
+            enabled: true,
+            last_run_at: None,
+        }
+    }
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn alarm_volume_ramps_up_to_the_volume_set_before() {
+        let second = std::time::Duration::from_secs(1);
+        assert!((ramp_volume(std::time::Duration::ZERO, 0.8) - 0.08).abs() < 1e-6);
+        assert!((ramp_volume(ALARM_RAMP / 2, 0.8) - 0.44).abs() < 1e-6);
+        assert_eq!(ramp_volume(ALARM_RAMP, 0.8), 0.8);
+        assert_eq!(ramp_volume(ALARM_RAMP * 3, 0.8), 0.8);
+        assert!(ramp_volume(10 * second, 1.0) < ramp_volume(11 * second, 1.0));
+    }
+
+    #[test]
+    fn recurrence_validation() {
+        for valid in ["once", "Daily", " weekdays ", "weekends", "mon", "mon,wed,fri", "Tue, Thu", "monday,sunday"] {
+            assert!(is_valid_recurrence(valid), "{}", valid);
+        }
+        for invalid in ["", "weekly", "mon,,fri", "mon;fri", "someday"] {
+            assert!(!is_valid_recurrence(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn runs_on_recurrences() {
+        // 1.6.2026 is a monday
+        let (monday, friday, saturday) = (day("2026-06-01"), day("2026-06-05"), day("2026-06-06"));
+        assert!(runs_on(&job("08:00", 60, "daily", None), saturday));
+        assert!(runs_on(&job("08:00", 60, "weekdays", None), friday));
+        assert!(!runs_on(&job("08:00", 60, "weekdays", None), saturday));
+        assert!(runs_on(&job("08:00", 60, "weekends", None), saturday));
+        assert!(!runs_on(&job("08:00", 60, "weekends", None), monday));
+        assert!(runs_on(&job("08:00", 60, "mon, fri", None), friday));
+        assert!(!runs_on(&job("08:00", 60, "mon,fri", None), saturday));
+        assert!(runs_on(&job("08:00", 60, "once", Some("2026-06-05")), friday));
+        assert!(!runs_on(&job("08:00", 60, "once", Some("2026-06-05")), monday));
+        assert!(!runs_on(&job("08:00", 60, "once", None), monday));
+    }
+
+    #[test]
+    fn current_occurrence_runs_over_midnight() {
+        // friday 23:30 for two hours
+        let job = job("23:30", 120, "fri", None);
+        assert_eq!(current_occurrence(&job, at("2026-06-05 23:29")), None);
+        assert_eq!(current_occurrence(&job, at("2026-06-05 23:30")), Some(at("2026-06-05 23:30")));
+        assert_eq!(current_occurrence(&job, at("2026-06-06 01:29")), Some(at("2026-06-05 23:30")));
+        assert_eq!(current_occurrence(&job, at("2026-06-06 01:30")), None);
+        // saturday isn't a day of the job
+        assert_eq!(current_occurrence(&job, at("2026-06-06 23:45")), None);
+    }
+
+    #[test]
+    fn once_job_runs_only_on_its_date() {
+        let job = job("10:00", 30, "once", Some("2026-06-05"));
+        assert_eq!(next_occurrence(&job, at("2026-06-01 12:00")), Some(at("2026-06-05 10:00")));
+        assert_eq!(current_occurrence(&job, at("2026-06-05 10:15")), Some(at("2026-06-05 10:00")));
+        assert_eq!(next_occurrence(&job, at("2026-06-05 10:15")), None);
+        // far ahead dates are found too, other recurrences look a week ahead
+        let later = ScheduleJobModel { date: Some(day("2026-09-01")), ..job };
+        assert_eq!(next_occurrence(&later, at("2026-06-01 12:00")), Some(at("2026-09-01 10:00")));
+    }
+
+    #[test]
+    fn next_occurrence_follows_day_list() {
+        let mon_wed = job("07:00", 60, "mon,wed", None);
+        assert_eq!(next_occurrence(&mon_wed, at("2026-06-01 06:59")), Some(at("2026-06-01 07:00")));
+        assert_eq!(next_occurrence(&mon_wed, at("2026-06-01 07:00")), Some(at("2026-06-03 07:00")));
+        assert_eq!(next_occurrence(&mon_wed, at("2026-06-04 12:00")), Some(at("2026-06-08 07:00")));
+        assert_eq!(next_occurrence(&job("07:00", 60, "daily", None), at("2026-06-06 08:00")), Some(at("2026-06-07 07:00")));
+    }
+
+    #[test]
+    fn occurrence_in_dst_gap_moves_after_it() {
+        // 02:30 doesn't exist on 29.3.2026
+        let job = job("02:30", 60, "daily", None);
+        let moved = at("2026-03-29 03:30");
+        assert_eq!(moved.offset().local_minus_utc(), SUMMER);
+        assert_eq!(next_occurrence(&job, at("2026-03-28 12:00")), Some(moved));
+        assert_eq!(current_occurrence(&job, at("2026-03-29 04:00")), Some(moved));
+        assert_eq!(next_occurrence(&job, at("2026-03-29 04:00")), Some(at("2026-03-30 02:30")));
+    }
+
+    #[test]
+    fn ambiguous_occurrence_runs_once() {
+        // 02:30 happens twice on 25.10.2026, the earlier one is used
+        let job = job("02:30", 20, "daily", None);
+        let start = next_occurrence(&job, at("2026-10-25 00:00")).unwrap();
+        assert_eq!(start.offset().local_minus_utc(), SUMMER);
+        let second = Cet2026.from_local_datetime(&local("2026-10-25 02:40")).latest().unwrap();
+        assert_eq!(current_occurrence(&job, second), None);
+    }
+}
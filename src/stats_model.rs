@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, text::{Line, Span}, widgets::{Bar, BarChart, BarGroup, Block, Borders, Paragraph}, Frame};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::json;
+
+use crate::{csv, data_layer::listening_stats_data_layer::ListeningStatsDataLayer, keymap::{self, Action, Scope}, theme, ui_models::ListeningStats};
+
+pub struct StatsModel {
+    db: DatabaseConnection,
+    pub stats: ListeningStats,
+    pub message: Option<String>,
+}
+
+impl StatsModel {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            stats: Default::default(),
+            message: None,
+        }
+    }
+
+    pub async fn reload(&mut self) -> Result<(), DbErr> {
+        self.stats = ListeningStatsDataLayer::get_stats(&self.db).await?;
+        Ok(())
+    }
+
+    pub fn ui(&mut self, rect: Rect, f: &mut Frame) {
+        let theme = theme::get();
+        let vertical_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rect);
+
+        // summary
+        let s = &self.stats;
+        let summary = vec![
+            Line::from(vec![
+                Span::styled("total: ", theme.muted),
+                Span::styled(duration_to_display(s.total_seconds), theme.accent),
+                Span::styled("   finished: ", theme.muted),
+                Span::styled(format!("{}/{}", s.episodes_finished, s.episodes_started), theme.accent),
+                Span::styled("   completion rate: ", theme.muted),
+                Span::styled(format!("{:.0}%", s.completion_rate * 100.0), theme.accent),
+                Span::styled("   longest streak: ", theme.muted),
+                Span::styled(format!("{} days", s.longest_streak), theme.accent),
+            ]),
+            Line::from(Span::styled(self.message.clone().unwrap_or_default(), theme.muted)),
+        ];
+        let summary_paragraph = Paragraph::new(summary)
+            .block(Block::default().borders(Borders::ALL).title("Listening statistics").title_bottom(keymap::get().hint(Scope::Stats, &[Action::Export, Action::Refresh])));
+        f.render_widget(summary_paragraph, vertical_chunks[0]);
+
+        // per week
+        let week_bars: Vec<Bar> = s.per_week.iter().map(|(label, seconds)| {
+            Bar::default()
+                .label(Line::from(label.clone()))
+                .value((seconds / 60.0) as u64)
+                .text_value(duration_to_display(*seconds))
+        }).collect();
+        let week_chart = BarChart::default()
+            .block(Block::default().borders(Borders::ALL).title("Per week"))
+            .data(BarGroup::default().bars(&week_bars))
+            .bar_width(7)
+            .bar_gap(1)
+            .bar_style(theme.bar)
+            .value_style(theme.bar_value);
+        f.render_widget(week_chart, vertical_chunks[1]);
+
+        // per channel
+        let channel_bars: Vec<Bar> = s.per_channel.iter().map(|(_, title, seconds)| {
+            Bar::default()
+                .label(Line::from(title.clone()))
+                .value((seconds / 60.0) as u64)
+                .text_value(duration_to_display(*seconds))
+        }).collect();
+        let channel_chart = BarChart::default()
+            .block(Block::default().borders(Borders::ALL).title("Per channel"))
+            .direction(Direction::Horizontal)
+            .data(BarGroup::default().bars(&channel_bars))
+            .bar_width(1)
+            .bar_gap(0)
+            .bar_style(theme.bar)
+            .value_style(theme.bar_value);
+        f.render_widget(channel_chart, vertical_chunks[2]);
+    }
+
+    pub async fn handle_action(&mut self, action: Action) -> std::io::Result<()> {
+        match action {
+            Action::Refresh => {
+                self.reload().await.map_err(|e| std::io::Error::other(e.to_string()))?;
+            },
+            Action::Export => {
+                self.message = match self.export() {
+                    Ok((csv, json)) => Some(format!("exported to {} and {}", csv.display(), json.display())),
+                    Err(e) => Some(format!("export failed: {}", e)),
+                };
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Write raw per day numbers to `~/librecast-stats.csv` and `~/librecast-stats.json`
+    fn export(&self) -> Result<(PathBuf, PathBuf), std::io::Error> {
+        let dir = home::home_dir().unwrap_or("./".into());
+        let csv_path = dir.join("librecast-stats.csv");
+        let json_path = dir.join("librecast-stats.json");
+
+        let mut csv = String::from("day,channel_id,channel,episode,enclosure,seconds\n");
+        self.stats.daily.iter().for_each(|d| {
+            csv.push_str(&format!("{},{},{},{},{},{:.1}\n",
+                d.day,
+                d.channel_id,
+                csv::field(d.channel_title.as_deref().unwrap_or_default()),
+                csv::field(d.item_title.as_deref().unwrap_or_default()),
+                csv::field(&d.enclosure),
+                d.seconds));
+        });
+        std::fs::write(&csv_path, csv)?;
+
+        let s = &self.stats;
+        let content = json!({
+            "total_seconds": s.total_seconds,
+            "episodes_started": s.episodes_started,
+            "episodes_finished": s.episodes_finished,
+            "completion_rate": s.completion_rate,
+            "longest_streak": s.longest_streak,
+            "per_channel": s.per_channel.iter().map(|(id, c, secs)| json!({ "channel_id": id, "channel": c, "seconds": secs })).collect::<Vec<_>>(),
+            "per_week": s.per_week.iter().map(|(w, secs)| json!({ "week": w, "seconds": secs })).collect::<Vec<_>>(),
+            "daily": s.daily.iter().map(|d| json!({
+                "day": d.day.to_string(),
+                "channel_id": d.channel_id,
+                "channel": d.channel_title,
+                "episode": d.item_title,
+                "enclosure": d.enclosure,
+                "seconds": d.seconds,
+            })).collect::<Vec<_>>(),
+        });
+        std::fs::write(&json_path, serde_json::to_string_pretty(&content)?)?;
+
+        Ok((csv_path, json_path))
+    }
+}
+
+fn duration_to_display(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).round() as i64;
+    format!("{}h{:0>2}m", minutes / 60, minutes % 60)
+}
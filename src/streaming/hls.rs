@@ -0,0 +1,602 @@
+use std::{collections::{HashMap, VecDeque}, error::Error, io::Read, thread, time::{Duration, Instant}};
+
+use crate::streaming::playlist::join_url;
+
+/// Live playlists start this many segments before the end
+const LIVE_START_SEGMENTS: usize = 3;
+const TS_PACKET_SIZE: usize = 188;
+
+struct Segment {
+    sequence: u64,
+    url: String,
+    /// `#EXT-X-MAP` init section of fMP4 segments
+    init: Option<String>,
+}
+
+struct MediaPlaylist {
+    target_duration: f64,
+    media_sequence: u64,
+    segments: Vec<Segment>,
+    ended: bool,
+}
+
+/// Reader of HLS stream audio. Picks an audio variant from master playlist,
+/// fetches media segments in order and follows live playlist updates.
+///
+/// MPEG-TS segments are demuxed to the audio elementary stream (ADTS AAC, MP3),
+/// packed audio segments get ID3 headers removed. fMP4 segments follow their
+/// init section, which is fetched once and read again only when it changes.
+pub struct HlsReader {
+    agent: ureq::Agent,
+    playlist_url: String,
+    target_duration: f64,
+    ended: bool,
+    last_reload: Instant,
+    /// of the last loaded playlist
+    media_sequence: Option<u64>,
+    /// sequence number of the next segment to queue
+    next_sequence: Option<u64>,
+    queue: VecDeque<Segment>,
+    /// url of the init section read last
+    init: Option<String>,
+    demuxer: TsDemuxer,
+    /// of the demuxed audio, detected from the first segment
+    content_type: &'static str,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl HlsReader {
+    /// `text` is content of playlist already downloaded from `url`
+    pub fn new(agent: ureq::Agent, url: &str, text: &str) -> Result<Self, Box<dyn Error>> {
+        let (playlist_url, text) = if is_master_playlist(text) {
+            let media_url = select_variant(text, url).ok_or("HLS playlist has no playable variant")?;
+            let media_text = agent.get(&media_url).call()?.into_string()?;
+            (media_url, media_text)
+        } else {
+            (url.to_string(), text.to_string())
+        };
+
+        let mut reader = Self::for_playlist(agent, playlist_url);
+        reader.enqueue(parse_media_playlist(&text, &reader.playlist_url)?);
+        let first = reader.next_segment()?.ok_or("HLS playlist has no segments")?;
+        reader.content_type = audio_content_type(&first).ok_or("HLS stream has no supported audio (AAC, MP3 or fMP4)")?;
+        reader.buffer = first;
+        Ok(reader)
+    }
+
+    fn for_playlist(agent: ureq::Agent, playlist_url: String) -> Self {
+        HlsReader {
+            agent,
+            playlist_url,
+            target_duration: 0.0,
+            ended: false,
+            last_reload: Instant::now(),
+            media_sequence: None,
+            next_sequence: None,
+            queue: Default::default(),
+            init: None,
+            demuxer: Default::default(),
+            content_type: "",
+            buffer: vec![],
+            position: 0,
+        }
+    }
+
+    /// Content type of the audio read from the stream
+    pub fn content_type(&self) -> &'static str {
+        self.content_type
+    }
+
+    fn enqueue(&mut self, playlist: MediaPlaylist) {
+        self.target_duration = playlist.target_duration;
+        self.ended = playlist.ended;
+        // media sequence only goes back when the encoder restarted and numbers segments from the start again,
+        // queued segments are kept and the new ones are taken like from a new playlist
+        if self.media_sequence.is_some_and(|s| playlist.media_sequence < s) {
+            self.next_sequence = None;
+        }
+        self.media_sequence = Some(playlist.media_sequence);
+        let skip = match self.next_sequence {
+            Some(_) => 0,
+            None if playlist.ended => 0,
+            None => playlist.segments.len().saturating_sub(LIVE_START_SEGMENTS),
+        };
+        for segment in playlist.segments.into_iter().skip(skip) {
+            if self.next_sequence.is_none_or(|s| segment.sequence >= s) {
+                self.next_sequence = Some(segment.sequence + 1);
+                self.queue.push_back(segment);
+            }
+        }
+    }
+
+    /// Wait for and load new segments of live playlist
+    fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        let interval = Duration::from_secs_f64(self.target_duration.max(1.0) / 2.0);
+        let elapsed = self.last_reload.elapsed();
+        if elapsed < interval {
+            thread::sleep(interval - elapsed);
+        }
+        self.last_reload = Instant::now();
+        let text = self.agent.get(&self.playlist_url).call()?.into_string()?;
+        self.enqueue(parse_media_playlist(&text, &self.playlist_url)?);
+        Ok(())
+    }
+
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut content = vec![];
+        self.agent.get(url).call()?.into_reader().read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    fn next_segment(&mut self) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        while self.queue.is_empty() {
+            if self.ended {
+                return Ok(None);
+            }
+            self.reload()?;
+        }
+
+        let segment = self.queue.pop_front().unwrap();
+        let mut out = vec![];
+        if segment.init != self.init {
+            if let Some(init) = segment.init.as_ref() {
+                out = self.fetch(init)?;
+            }
+            self.init = segment.init;
+        }
+        let content = self.fetch(&segment.url)?;
+        if content.first() == Some(&0x47) && content.len() % TS_PACKET_SIZE == 0 {
+            self.demuxer.push(&content, &mut out);
+            self.demuxer.flush(&mut out);
+        } else {
+            out.extend_from_slice(strip_id3(&content));
+        }
+        Ok(Some(out))
+    }
+}
+
+impl Read for HlsReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            match self.next_segment().map_err(|e| std::io::Error::other(e.to_string()))? {
+                Some(data) => {
+                    self.buffer = data;
+                    self.position = 0;
+                },
+                None => return Ok(0),
+            }
+        }
+
+        let len = std::cmp::min(buf.len(), self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+pub fn is_master_playlist(text: &str) -> bool {
+    text.lines().any(|l| l.starts_with("#EXT-X-STREAM-INF"))
+}
+
+/// Audio only variant with the highest bandwidth, then audio rendition,
+/// then the variant with the lowest bandwidth (audio is demuxed from it)
+fn select_variant(text: &str, base_url: &str) -> Option<String> {
+    let mut variants: Vec<(u64, bool, String)> = vec![];
+    let mut renditions: Vec<(bool, String)> = vec![];
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            let attributes = parse_attributes(attributes);
+            let bandwidth = attributes.get("BANDWIDTH").and_then(|b| b.parse().ok()).unwrap_or_default();
+            let audio_only = attributes.get("CODECS").is_some_and(|c| c.split(',').all(|c| is_audio_codec(c.trim())));
+            if let Some(uri) = lines.find(|l| !l.is_empty() && !l.starts_with('#')) {
+                variants.push((bandwidth, audio_only, join_url(base_url, uri)));
+            }
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MEDIA:") {
+            let attributes = parse_attributes(attributes);
+            if let (Some("AUDIO"), Some(uri)) = (attributes.get("TYPE").map(String::as_str), attributes.get("URI")) {
+                renditions.push((attributes.get("DEFAULT").map(String::as_str) == Some("YES"), join_url(base_url, uri)));
+            }
+        }
+    }
+
+    variants.iter()
+        .filter(|v| v.1)
+        .max_by_key(|v| v.0)
+        .map(|v| v.2.clone())
+        .or_else(|| renditions.iter().find(|r| r.0).or(renditions.first()).map(|r| r.1.clone()))
+        .or_else(|| variants.iter().min_by_key(|v| v.0).map(|v| v.2.clone()))
+}
+
+fn is_audio_codec(codec: &str) -> bool {
+    ["mp4a", "ac-3", "ec-3", "mp3", "opus", "flac"].iter().any(|a| codec.starts_with(a))
+}
+
+fn parse_media_playlist(text: &str, base_url: &str) -> Result<MediaPlaylist, Box<dyn Error>> {
+    let mut playlist = MediaPlaylist {
+        target_duration: 0.0,
+        media_sequence: 0,
+        segments: vec![],
+        ended: false,
+    };
+    let mut sequence = None;
+    let mut in_segment = false;
+    let mut init = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            playlist.target_duration = value.trim().parse().unwrap_or_default();
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            playlist.media_sequence = value.trim().parse().unwrap_or_default();
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            if parse_attributes(attributes).get("METHOD").is_some_and(|m| m != "NONE") {
+                return Err("encrypted HLS streams are not supported".into());
+            }
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+            init = parse_attributes(attributes).get("URI").map(|uri| join_url(base_url, uri));
+        } else if line.starts_with("#EXTINF") {
+            in_segment = true;
+        } else if line.starts_with("#EXT-X-ENDLIST") {
+            playlist.ended = true;
+        } else if !line.starts_with('#') && in_segment {
+            let s = sequence.unwrap_or(playlist.media_sequence);
+            playlist.segments.push(Segment {
+                sequence: s,
+                url: join_url(base_url, line),
+                init: init.clone(),
+            });
+            sequence = Some(s + 1);
+            in_segment = false;
+        }
+    }
+    Ok(playlist)
+}
+
+/// Attribute list like `BANDWIDTH=128000,CODECS="mp4a.40.2,avc1.4d401e"`
+fn parse_attributes(text: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = text.trim();
+    while let Some((name, value)) = rest.split_once('=') {
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or_default())
+            },
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        attributes.insert(name.trim().to_string(), value.to_string());
+        rest = next.trim_start_matches(',').trim();
+    }
+    attributes
+}
+
+/// Content type of audio elementary stream from its first frame, or of fMP4 from its init section
+fn audio_content_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("audio/mp4"),
+        // ADTS sync word with layer 0
+        [0xff, b, ..] if b & 0xf6 == 0xf0 => Some("audio/aac"),
+        // MPEG audio frame sync with layer I-III
+        [0xff, b, ..] if b & 0xe0 == 0xe0 && b & 0x06 != 0 => Some("audio/mpeg"),
+        _ => None,
+    }
+}
+
+/// Packed audio segments start with ID3 tag holding the timestamp
+fn strip_id3(data: &[u8]) -> &[u8] {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return data;
+    }
+    let size = data[6..10].iter().fold(0usize, |s, b| (s << 7) | (*b & 0x7f) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    data.get(10 + size + footer..).unwrap_or_default()
+}
+
+/// Extracts the first audio elementary stream from MPEG-TS packets
+#[derive(Default)]
+struct TsDemuxer {
+    pmt_pid: Option<u16>,
+    audio_pid: Option<u16>,
+    pes: Vec<u8>,
+}
+
+impl TsDemuxer {
+    fn push(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        for packet in data.chunks_exact(TS_PACKET_SIZE).filter(|p| p[0] == 0x47) {
+            let unit_start = packet[1] & 0x40 != 0;
+            let pid = (((packet[1] & 0x1f) as u16) << 8) | packet[2] as u16;
+            let adaptation = (packet[3] >> 4) & 0x03;
+            if adaptation & 0x01 == 0 {
+                continue;
+            }
+            let offset = if adaptation & 0x02 != 0 { 5 + packet[4] as usize } else { 4 };
+            let payload = match packet.get(offset..) {
+                Some(p) if !p.is_empty() => p,
+                _ => continue,
+            };
+
+            if pid == 0 {
+                self.parse_pat(payload);
+            } else if Some(pid) == self.pmt_pid {
+                self.parse_pmt(payload);
+            } else if Some(pid) == self.audio_pid {
+                if unit_start {
+                    self.flush(out);
+                }
+                self.pes.extend_from_slice(payload);
+            }
+        }
+    }
+
+    /// Write payload of the collected PES packet
+    fn flush(&mut self, out: &mut Vec<u8>) {
+        if self.pes.len() > 9 && self.pes[..3] == [0, 0, 1] {
+            let start = 9 + self.pes[8] as usize;
+            out.extend_from_slice(self.pes.get(start..).unwrap_or_default());
+        }
+        self.pes.clear();
+    }
+
+    fn section(payload: &[u8]) -> Option<&[u8]> {
+        let pointer = *payload.first()? as usize;
+        let section = payload.get(1 + pointer..)?;
+        let length = (((*section.get(1)? & 0x0f) as usize) << 8) | *section.get(2)? as usize;
+        // without CRC
+        section.get(..(3 + length).checked_sub(4)?)
+    }
+
+    fn parse_pat(&mut self, payload: &[u8]) {
+        let programs = match Self::section(payload).and_then(|s| s.get(8..)) {
+            Some(p) => p,
+            None => return,
+        };
+        self.pmt_pid = programs.chunks_exact(4)
+            .find(|p| p[0] != 0 || p[1] != 0)
+            .map(|p| (((p[2] & 0x1f) as u16) << 8) | p[3] as u16);
+    }
+
+    fn parse_pmt(&mut self, payload: &[u8]) {
+        let section = match Self::section(payload) {
+            Some(s) if s.len() >= 12 => s,
+            _ => return,
+        };
+        let program_info_length = (((section[10] & 0x0f) as usize) << 8) | section[11] as usize;
+        let mut i = 12 + program_info_length;
+        while i + 5 <= section.len() {
+            let stream_type = section[i];
+            let pid = (((section[i + 1] & 0x1f) as u16) << 8) | section[i + 2] as u16;
+            let info_length = (((section[i + 3] & 0x0f) as usize) << 8) | section[i + 4] as usize;
+            // ADTS AAC, MPEG-1 and MPEG-2 audio
+            if matches!(stream_type, 0x0f | 0x03 | 0x04) {
+                self.audio_pid = Some(pid);
+                return;
+            }
+            i += 5 + info_length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader, Write}, net::TcpListener, sync::{Arc, Mutex}};
+
+    use super::*;
+
+    const AUDIO_PID: u16 = 0x101;
+
+    /// TS packet with `payload`, padded by adaptation field stuffing
+    fn packet(pid: u16, unit_start: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x47, ((unit_start as u8) << 6) | (pid >> 8) as u8, pid as u8, 0x10];
+        let stuffing = TS_PACKET_SIZE - 4 - payload.len();
+        if stuffing > 0 {
+            packet[3] = 0x30;
+            packet.push((stuffing - 1) as u8);
+            if stuffing > 1 {
+                packet.push(0);
+                packet.extend(std::iter::repeat_n(0xff, stuffing - 2));
+            }
+        }
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn pat() -> Vec<u8> {
+        // pointer, table id, length 13, stream id, version, section numbers, program 1 on pid 0x100, crc
+        packet(0, true, &[0, 0x00, 0xb0, 13, 0, 1, 0xc1, 0, 0, 0, 1, 0xe1, 0x00, 0, 0, 0, 0])
+    }
+
+    fn pmt() -> Vec<u8> {
+        // video on 0x102 comes before ADTS AAC on AUDIO_PID
+        packet(0x100, true, &[0, 0x02, 0xb0, 23, 0, 1, 0xc1, 0, 0, 0xe1, 0x02, 0xf0, 0,
+            0x1b, 0xe1, 0x02, 0xf0, 0,
+            0x0f, 0xe1, 0x01, 0xf0, 0,
+            0, 0, 0, 0])
+    }
+
+    /// PES header with PTS in front of `data`
+    fn pes(data: &[u8]) -> Vec<u8> {
+        let mut pes = vec![0, 0, 1, 0xc0, 0, (8 + data.len()) as u8, 0x80, 0x80, 5, 0x21, 0, 1, 0, 1];
+        pes.extend_from_slice(data);
+        pes
+    }
+
+    #[test]
+    fn ts_demuxer_extracts_audio_stream() {
+        let first = pes(&[0xff, 0xf1, 1, 2, 3]);
+        let mut data = [pat(), pmt()].concat();
+        data.extend(packet(AUDIO_PID, true, &first[..8]));
+        data.extend(packet(0x102, true, &[0xde, 0xad]));
+        data.extend(packet(AUDIO_PID, false, &first[8..]));
+        data.extend(packet(AUDIO_PID, true, &pes(&[4, 5])));
+
+        let mut demuxer = TsDemuxer::default();
+        let mut out = vec![];
+        demuxer.push(&data, &mut out);
+        assert_eq!(out, vec![0xff, 0xf1, 1, 2, 3]);
+        demuxer.flush(&mut out);
+        assert_eq!(out, vec![0xff, 0xf1, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn ts_demuxer_skips_audio_before_program_tables() {
+        let mut data = packet(AUDIO_PID, true, &pes(&[9, 9]));
+        data.extend([pat(), pmt()].concat());
+        data.extend(packet(AUDIO_PID, true, &pes(&[1])));
+
+        let mut demuxer = TsDemuxer::default();
+        let mut out = vec![];
+        demuxer.push(&data, &mut out);
+        demuxer.flush(&mut out);
+        assert_eq!(out, vec![1]);
+    }
+
+    #[test]
+    fn parse_media_playlist_numbers_and_resolves_segments() {
+        let text = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:41\n\
+            #EXTINF:6.0,\nseg41.ts\n#EXT-X-KEY:METHOD=NONE\n#EXTINF:6.0,\n/live/seg42.ts?token=a\n\
+            #EXTINF:6.0,\nhttp://cdn.example.com/seg43.aac\n#EXT-X-ENDLIST\n";
+        let playlist = parse_media_playlist(text, "http://example.com/live/index.m3u8").unwrap();
+        assert_eq!(playlist.target_duration, 6.0);
+        assert_eq!(playlist.media_sequence, 41);
+        assert!(playlist.ended);
+        let segments: Vec<(u64, &str)> = playlist.segments.iter().map(|s| (s.sequence, s.url.as_str())).collect();
+        assert_eq!(segments, vec![
+            (41, "http://example.com/live/seg41.ts"),
+            (42, "http://example.com/live/seg42.ts?token=a"),
+            (43, "http://cdn.example.com/seg43.aac"),
+        ]);
+    }
+
+    #[test]
+    fn parse_media_playlist_rejects_encrypted() {
+        let encrypted = "#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n#EXTINF:6.0,\nseg.ts\n";
+        assert!(parse_media_playlist(encrypted, "http://example.com/a.m3u8").is_err());
+    }
+
+    #[test]
+    fn parse_media_playlist_maps_fmp4_segments_to_init_section() {
+        let text = "#EXTM3U\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:6.0,\nseg1.m4s\n#EXTINF:6.0,\nseg2.m4s\n\
+            #EXT-X-DISCONTINUITY\n#EXT-X-MAP:URI=\"/v2/init.mp4\"\n#EXTINF:6.0,\nseg3.m4s\n";
+        let playlist = parse_media_playlist(text, "http://example.com/live/a.m3u8").unwrap();
+        let inits: Vec<Option<&str>> = playlist.segments.iter().map(|s| s.init.as_deref()).collect();
+        assert_eq!(inits, vec![
+            Some("http://example.com/live/init.mp4"),
+            Some("http://example.com/live/init.mp4"),
+            Some("http://example.com/v2/init.mp4"),
+        ]);
+        let ts = parse_media_playlist("#EXTM3U\n#EXTINF:6.0,\nseg.ts\n", "http://example.com/a.m3u8").unwrap();
+        assert_eq!(ts.segments[0].init, None);
+    }
+
+    /// HTTP server answering with `files` by path, returns base url and requested paths
+    fn file_server(files: &'static [(&'static str, &'static [u8])]) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<String>>> = Default::default();
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let path = request_line.split(' ').nth(1).unwrap_or_default().to_string();
+                let body = files.iter().find(|f| f.0 == path).map(|f| f.1).unwrap_or_default();
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+                stream.write_all(body).unwrap();
+                log.lock().unwrap().push(path);
+            }
+        });
+        (base_url, requests)
+    }
+
+    #[test]
+    fn fmp4_segments_follow_init_section_fetched_once() {
+        const INIT: &[u8] = b"\x00\x00\x00\x10ftypiso6\x00\x00\x00\x00moov";
+        let (base_url, requests) = file_server(&[("/init.mp4", INIT), ("/seg1.m4s", b"moof1mdat1"), ("/seg2.m4s", b"moof2mdat2")]);
+        let text = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:6.0,\nseg1.m4s\n#EXTINF:6.0,\nseg2.m4s\n#EXT-X-ENDLIST\n";
+
+        let mut reader = HlsReader::new(ureq::Agent::new(), &format!("{}/live.m3u8", base_url), text).unwrap();
+        assert_eq!(reader.content_type(), "audio/mp4");
+        let mut audio = vec![];
+        reader.read_to_end(&mut audio).unwrap();
+        assert_eq!(audio, [INIT, b"moof1mdat1", b"moof2mdat2"].concat());
+        assert_eq!(*requests.lock().unwrap(), vec!["/init.mp4", "/seg1.m4s", "/seg2.m4s"]);
+    }
+
+    fn live_playlist(first: u64, count: u64) -> MediaPlaylist {
+        MediaPlaylist {
+            target_duration: 6.0,
+            media_sequence: first,
+            segments: (first..first + count).map(|s| Segment { sequence: s, url: format!("seg{}.ts", s), init: None }).collect(),
+            ended: false,
+        }
+    }
+
+    fn queued(reader: &mut HlsReader) -> Vec<u64> {
+        reader.queue.drain(..).map(|s| s.sequence).collect()
+    }
+
+    #[test]
+    fn enqueue_takes_new_segments_of_live_playlist() {
+        let mut reader = HlsReader::for_playlist(ureq::Agent::new(), "http://example.com/live.m3u8".to_string());
+        reader.enqueue(live_playlist(41, 5));
+        assert_eq!(queued(&mut reader), vec![43, 44, 45]);
+        reader.enqueue(live_playlist(42, 5));
+        assert_eq!(queued(&mut reader), vec![46]);
+        reader.enqueue(live_playlist(42, 5));
+        assert_eq!(queued(&mut reader), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn enqueue_starts_over_when_media_sequence_restarts() {
+        let mut reader = HlsReader::for_playlist(ureq::Agent::new(), "http://example.com/live.m3u8".to_string());
+        reader.enqueue(live_playlist(1000, 5));
+        assert_eq!(queued(&mut reader), vec![1002, 1003, 1004]);
+        // encoder restarted, its segments are below the next expected sequence
+        reader.enqueue(live_playlist(0, 2));
+        assert_eq!(queued(&mut reader), vec![0, 1]);
+        reader.enqueue(live_playlist(0, 3));
+        assert_eq!(queued(&mut reader), vec![2]);
+    }
+
+    #[test]
+    fn select_variant_prefers_audio_only_with_highest_bandwidth() {
+        let text = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.5\"\nlow.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=900000,CODECS=\"mp4a.40.2,avc1.4d401e\"\nvideo.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\"\nhigh.m3u8\n";
+        assert_eq!(select_variant(text, "http://example.com/master.m3u8").as_deref(), Some("http://example.com/high.m3u8"));
+    }
+
+    #[test]
+    fn select_variant_falls_back_to_rendition_then_lowest_bandwidth() {
+        let renditions = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"a\",NAME=\"en\",URI=\"en.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"a\",NAME=\"de\",DEFAULT=YES,URI=\"de.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=900000,CODECS=\"mp4a.40.2,avc1.4d401e\",AUDIO=\"a\"\nvideo.m3u8\n";
+        assert_eq!(select_variant(renditions, "http://example.com/master.m3u8").as_deref(), Some("http://example.com/de.m3u8"));
+
+        let video = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=900000\nhd.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=300000\nsd.m3u8\n";
+        assert_eq!(select_variant(video, "http://example.com/master.m3u8").as_deref(), Some("http://example.com/sd.m3u8"));
+        assert_eq!(select_variant("#EXTM3U\n", "http://example.com/master.m3u8"), None);
+    }
+
+    #[test]
+    fn audio_content_type_detects_adts_mpeg_and_fmp4() {
+        assert_eq!(audio_content_type(&[0xff, 0xf1, 0x50]), Some("audio/aac"));
+        assert_eq!(audio_content_type(&[0xff, 0xfb, 0x90]), Some("audio/mpeg"));
+        assert_eq!(audio_content_type(strip_id3(b"ID3\x04\x00\x00\x00\x00\x00\x02ab\xff\xf9")), Some("audio/aac"));
+        assert_eq!(audio_content_type(b"\x00\x00\x00\x18ftypmp42"), Some("audio/mp4"));
+        assert_eq!(audio_content_type(b"\x00\x00\x00\x18moofmp42"), None);
+        assert_eq!(audio_content_type(&[]), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{io::Read, sync::{Arc, RwLock}};
+
+/// Station info from `icy-*` response headers
+/// and the current title from in-stream metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IcyMetadata {
+    pub name: Option<String>,
+    pub genre: Option<String>,
+    pub bitrate: Option<i32>,
+    pub url: Option<String>,
+    pub stream_title: Option<String>,
+}
+
+impl IcyMetadata {
+    pub fn from_response(response: &ureq::Response) -> Self {
+        let header = |name: &str| response.header(name)
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty());
+        IcyMetadata {
+            name: header("icy-name"),
+            genre: header("icy-genre"),
+            bitrate: header("icy-br").and_then(|b| b.split(',').next().and_then(|b| b.trim().parse().ok())),
+            url: header("icy-url"),
+            stream_title: None,
+        }
+    }
+}
+
+/// Reader which removes ICY metadata blocks interleaved in the stream
+/// every `metaint` bytes, and stores parsed `StreamTitle` in `metadata`
+pub struct IcyReader<R: Read> {
+    inner: R,
+    metaint: Option<usize>,
+    until_meta: usize,
+    metadata: Arc<RwLock<IcyMetadata>>,
+}
+
+impl<R: Read> IcyReader<R> {
+    pub fn new(inner: R, metaint: Option<usize>, metadata: Arc<RwLock<IcyMetadata>>) -> Self {
+        IcyReader {
+            inner,
+            metaint,
+            until_meta: metaint.unwrap_or_default(),
+            metadata,
+        }
+    }
+
+    fn read_metadata_block(&mut self) -> std::io::Result<()> {
+        let mut len = [0u8; 1];
+        self.inner.read_exact(&mut len)?;
+        let len = len[0] as usize * 16;
+        if len > 0 {
+            let mut block = vec![0u8; len];
+            self.inner.read_exact(&mut block)?;
+            // empty title clears the previous song, blocks without title keep it
+            if let Some(title) = parse_stream_title(&block) {
+                self.metadata.write().unwrap().stream_title = Some(title).filter(|t| !t.is_empty());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let metaint = match self.metaint {
+            Some(m) if m > 0 => m,
+            _ => return self.inner.read(buf),
+        };
+
+        if self.until_meta == 0 {
+            self.read_metadata_block()?;
+            self.until_meta = metaint;
+        }
+
+        let len = std::cmp::min(buf.len(), self.until_meta);
+        let n = self.inner.read(&mut buf[..len])?;
+        self.until_meta -= n;
+        Ok(n)
+    }
+}
+
+/// Extract `StreamTitle` from metadata block like
+/// `StreamTitle='Artist - Song';StreamUrl='';` padded with zeros.
+/// Empty when the station cleared the title, None when the block has no title
+pub fn parse_stream_title(block: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(block);
+    let text = text.trim_end_matches('\0');
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+    let end = rest.find("';").unwrap_or(rest.len());
+    Some(rest[..end].trim_end_matches('\'').trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader returning at most `max` bytes per read
+    struct ShortReads<'a> {
+        data: &'a [u8],
+        max: usize,
+    }
+
+    impl Read for ShortReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.max.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    /// Metadata block with length byte, padded with zeros to 16 bytes
+    fn block(text: &str) -> Vec<u8> {
+        let len = text.len().div_ceil(16);
+        let mut block = vec![len as u8];
+        block.extend_from_slice(text.as_bytes());
+        block.resize(1 + len * 16, 0);
+        block
+    }
+
+    fn read_all(reader: &mut impl Read, chunk: usize) -> Vec<u8> {
+        let mut audio = vec![];
+        let mut buf = vec![0u8; chunk];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => return audio,
+                n => audio.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_stream_title_handles_apostrophes_padding_and_missing_title() {
+        assert_eq!(parse_stream_title(b"StreamTitle='Guns N' Roses - Don't Cry';StreamUrl='';\0\0\0").as_deref(), Some("Guns N' Roses - Don't Cry"));
+        assert_eq!(parse_stream_title(b"StreamTitle=' Artist - Song ';\0\0\0\0\0\0").as_deref(), Some("Artist - Song"));
+        // unterminated title runs to the end of the block
+        assert_eq!(parse_stream_title(b"StreamTitle='Artist - Song'\0\0").as_deref(), Some("Artist - Song"));
+        assert_eq!(parse_stream_title(b"StreamTitle='';\0\0\0\0").as_deref(), Some(""));
+        assert_eq!(parse_stream_title(b"StreamUrl='http://example.com';\0"), None);
+        assert_eq!(parse_stream_title(&[0; 16]), None);
+    }
+
+    #[test]
+    fn reader_strips_metadata_across_short_reads() {
+        let mut data = b"abcde".to_vec();
+        data.extend(block("StreamTitle='First';"));
+        data.extend_from_slice(b"fghij");
+        data.extend([0]);
+        data.extend_from_slice(b"klmno");
+        data.extend(block("StreamTitle='Second';"));
+        data.extend_from_slice(b"pq");
+
+        for max in [1, 2, 3, 7, 64] {
+            let metadata: Arc<RwLock<IcyMetadata>> = Default::default();
+            let mut reader = IcyReader::new(ShortReads { data: &data, max }, Some(5), metadata.clone());
+            assert_eq!(read_all(&mut reader, 4), b"abcdefghijklmnopq", "{}", max);
+            assert_eq!(metadata.read().unwrap().stream_title.as_deref(), Some("Second"), "{}", max);
+        }
+    }
+
+    #[test]
+    fn empty_title_clears_and_missing_title_keeps_song() {
+        let mut data = b"ab".to_vec();
+        data.extend(block("StreamTitle='Song';"));
+        data.extend_from_slice(b"cd");
+        data.extend(block("StreamUrl='http://example.com';"));
+        data.extend_from_slice(b"e");
+        let metadata: Arc<RwLock<IcyMetadata>> = Default::default();
+        let mut reader = IcyReader::new(ShortReads { data: &data, max: 64 }, Some(2), metadata.clone());
+        assert_eq!(read_all(&mut reader, 64), b"abcde");
+        assert_eq!(metadata.read().unwrap().stream_title.as_deref(), Some("Song"));
+
+        let cleared = [b"gh".to_vec(), block("StreamTitle='';"), b"i".to_vec()].concat();
+        let mut reader = IcyReader::new(ShortReads { data: &cleared, max: 64 }, Some(2), metadata.clone());
+        read_all(&mut reader, 64);
+        assert_eq!(metadata.read().unwrap().stream_title, None);
+    }
+
+    #[test]
+    fn reader_without_metaint_passes_audio_through() {
+        let metadata: Arc<RwLock<IcyMetadata>> = Default::default();
+        let data = b"StreamTitle='Not metadata';";
+        let mut reader = IcyReader::new(ShortReads { data, max: 3 }, None, metadata.clone());
+        assert_eq!(read_all(&mut reader, 8), data);
+        assert_eq!(metadata.read().unwrap().stream_title, None);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod hls;
+pub mod icy;
+pub mod pcm;
+pub mod playlist;
+pub mod recorder;
+pub mod relay;
@@ -0,0 +1,206 @@
+use std::{io::{Cursor, Read}, sync::{atomic::{AtomicU32, Ordering}, Arc}};
+
+use symphonia::core::{audio::{SampleBuffer, SignalSpec}, codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL}, errors::Error, formats::{FormatOptions, FormatReader}, io::{MediaSourceStream, ReadOnlySource}, meta::MetadataOptions, probe::Hint};
+
+const WAV_HEADER_SIZE: usize = 44;
+
+/// Volume applied to decoded audio, 0.0 - 1.0, shared with the thread decoding it
+#[derive(Debug, Clone)]
+pub struct Gain(Arc<AtomicU32>);
+
+impl Default for Gain {
+    fn default() -> Self {
+        Gain(Arc::new(AtomicU32::new(1.0f32.to_bits())))
+    }
+}
+
+impl Gain {
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, volume: f32) {
+        self.0.store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Decodes compressed stream audio to 16 bit samples with `Gain` applied,
+/// which are served to the player as endless WAV since url2audio has no volume control.
+///
+/// The source isn't seekable, so only formats which can be read
+/// front to back are decoded (MP3, ADTS AAC, Ogg, FLAC, fMP4, WAV).
+pub struct PcmStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    gain: Gain,
+    /// gain the last sample got, changes are ramped over a packet so they don't click
+    applied: f32,
+    spec: Option<SignalSpec>,
+}
+
+impl PcmStream {
+    pub fn new(source: impl Read + Send + Sync + 'static, gain: Gain) -> Result<Self, Error> {
+        let (format, decoder, track_id) = open(source)?;
+        Ok(PcmStream {
+            format,
+            decoder,
+            track_id,
+            applied: gain.get(),
+            gain,
+            spec: None,
+        })
+    }
+
+    /// Whether audio starting with `prefix` can be decoded
+    pub fn can_decode(prefix: &[u8]) -> bool {
+        open(Cursor::new(prefix.to_vec())).is_ok()
+    }
+
+    /// Header of an endless WAV stream with the decoded audio, known after the first `next_samples`
+    pub fn wav_header(&self) -> Option<Vec<u8>> {
+        self.spec.map(|s| wav_header(s.rate, s.channels.count() as u16))
+    }
+
+    /// Next decoded packet as interleaved 16 bit little endian samples.
+    /// None when the stream ended, or changed its format which needs a new WAV header
+    pub fn next_samples(&mut self) -> Option<Vec<u8>> {
+        loop {
+            let packet = self.format.next_packet().ok()?;
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(d) => d,
+                // damaged packets are skipped
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => return None,
+            };
+            let spec = *decoded.spec();
+            if self.spec.is_some_and(|s| s != spec) {
+                return None;
+            }
+            self.spec = Some(spec);
+
+            let mut samples = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+            samples.copy_interleaved_ref(decoded);
+            let target = self.gain.get();
+            let bytes = apply_gain(samples.samples(), spec.channels.count(), self.applied, target);
+            self.applied = target;
+            return Some(bytes);
+        }
+    }
+}
+
+type Opened = (Box<dyn FormatReader>, Box<dyn Decoder>, u32);
+
+fn open(source: impl Read + Send + Sync + 'static) -> Result<Opened, Error> {
+    let stream = MediaSourceStream::new(Box::new(ReadOnlySource::new(source)), Default::default());
+    let probed = symphonia::default::get_probe().format(&Hint::new(), stream, &FormatOptions::default(), &MetadataOptions::default())?;
+    let track = probed.format.tracks().iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(Error::Unsupported("no audio track"))?;
+    let decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let track_id = track.id;
+    Ok((probed.format, decoder, track_id))
+}
+
+/// Interleaved `samples` as little endian bytes, with gain going from `from` to `to` over the frames
+fn apply_gain(samples: &[i16], channels: usize, from: f32, to: f32) -> Vec<u8> {
+    let channels = channels.max(1);
+    let step = (to - from) / (samples.len() / channels).max(1) as f32;
+    let mut bytes = Vec::with_capacity(samples.len() * 2);
+    for (i, frame) in samples.chunks(channels).enumerate() {
+        let gain = from + step * (i + 1) as f32;
+        for sample in frame {
+            bytes.extend_from_slice(&((*sample as f32 * gain) as i16).to_le_bytes());
+        }
+    }
+    bytes
+}
+
+/// RIFF and data chunk lengths are the maximum, as ffmpeg writes them for streams of unknown length
+fn wav_header(rate: u32, channels: u16) -> Vec<u8> {
+    let block_align = channels * 2;
+    let mut header = Vec::with_capacity(WAV_HEADER_SIZE);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&rate.to_le_bytes());
+    header.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(bytes: &[u8]) -> Vec<i16> {
+        bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect()
+    }
+
+    /// Endless WAV with `frames` stereo frames of a constant sample
+    fn wav(frames: usize, sample: i16) -> Vec<u8> {
+        let mut wav = wav_header(8000, 2);
+        for _ in 0..frames * 2 {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn gain_is_clamped() {
+        let gain = Gain::default();
+        assert_eq!(gain.get(), 1.0);
+        gain.set(1.5);
+        assert_eq!(gain.get(), 1.0);
+        gain.set(-0.2);
+        assert_eq!(gain.get(), 0.0);
+        gain.clone().set(0.25);
+        assert_eq!(gain.get(), 0.25);
+    }
+
+    #[test]
+    fn apply_gain_scales_and_ramps_per_frame() {
+        assert_eq!(samples(&apply_gain(&[1000, -1000, 300], 1, 1.0, 1.0)), vec![1000, -1000, 300]);
+        assert_eq!(samples(&apply_gain(&[1000, -1000], 2, 0.5, 0.5)), vec![500, -500]);
+        // both channels of a frame get the same gain, the last frame reaches the target
+        let ramped = samples(&apply_gain(&[1000, 1000, 1000, 1000, 1000, 1000, 1000, 1000], 2, 0.0, 1.0));
+        assert_eq!(ramped, vec![250, 250, 500, 500, 750, 750, 1000, 1000]);
+    }
+
+    #[test]
+    fn endless_wav_header_is_readable() {
+        assert_eq!(wav_header(44100, 2).len(), WAV_HEADER_SIZE);
+        assert!(PcmStream::can_decode(&wav(16, 0)));
+        assert!(!PcmStream::can_decode(b"<html><body>not audio</body></html>"));
+    }
+
+    #[test]
+    fn pcm_stream_applies_gain_and_keeps_format() {
+        let gain = Gain::default();
+        gain.set(0.5);
+        let mut stream = PcmStream::new(Cursor::new(wav(4096, 1000)), gain.clone()).unwrap();
+        assert_eq!(stream.wav_header(), None);
+
+        let first = samples(&stream.next_samples().unwrap());
+        assert!(!first.is_empty());
+        assert!(first.iter().all(|s| *s == 500));
+        assert_eq!(stream.wav_header(), Some(wav_header(8000, 2)));
+
+        // new gain is reached at the end of the next packet
+        gain.set(1.0);
+        let second = samples(&stream.next_samples().unwrap());
+        let middle = second[second.len() / 2];
+        assert!(second[0] < middle && middle < 1000);
+        assert_eq!(second.last(), Some(&1000));
+    }
+}
@@ -0,0 +1,206 @@
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistKind {
+    M3u,
+    Pls,
+    Xspf,
+    Asx,
+}
+
+/// Detect playlist from content start, falling back to Content-Type and url extension
+pub fn detect(url: &str, content_type: &str, head: &[u8]) -> Option<PlaylistKind> {
+    sniff(head)
+        .or_else(|| from_content_type(content_type))
+        .or_else(|| from_extension(url))
+}
+
+fn sniff(head: &[u8]) -> Option<PlaylistKind> {
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start().to_lowercase();
+    if text.starts_with("#extm3u") {
+        Some(PlaylistKind::M3u)
+    } else if text.starts_with("[playlist]") {
+        Some(PlaylistKind::Pls)
+    } else if text.starts_with("<asx") {
+        Some(PlaylistKind::Asx)
+    } else if text.starts_with("<?xml") || text.starts_with("<playlist") {
+        if text.contains("xspf.org") || text.contains("<playlist") {
+            Some(PlaylistKind::Xspf)
+        } else if text.contains("<asx") {
+            Some(PlaylistKind::Asx)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+fn from_content_type(content_type: &str) -> Option<PlaylistKind> {
+    match content_type.trim().to_lowercase().as_str() {
+        "audio/x-mpegurl" | "audio/mpegurl" | "application/x-mpegurl" | "application/vnd.apple.mpegurl" => Some(PlaylistKind::M3u),
+        "audio/x-scpls" | "application/pls+xml" => Some(PlaylistKind::Pls),
+        "application/xspf+xml" => Some(PlaylistKind::Xspf),
+        "video/x-ms-asx" | "audio/x-ms-wax" | "video/x-ms-wvx" => Some(PlaylistKind::Asx),
+        _ => None,
+    }
+}
+
+fn from_extension(url: &str) -> Option<PlaylistKind> {
+    let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    let extension = path.rsplit_once('.').map(|(_, e)| e).unwrap_or_default();
+    match extension {
+        "m3u" | "m3u8" => Some(PlaylistKind::M3u),
+        "pls" => Some(PlaylistKind::Pls),
+        "xspf" => Some(PlaylistKind::Xspf),
+        "asx" | "wax" | "wvx" => Some(PlaylistKind::Asx),
+        _ => None,
+    }
+}
+
+/// HLS playlists are m3u files with `#EXT-X-` tags
+pub fn is_hls(text: &str) -> bool {
+    text.lines().any(|l| l.trim_start().starts_with("#EXT-X-"))
+}
+
+/// Stream urls from playlist in playlist order (`FileN` order for PLS),
+/// relative urls are resolved against `base_url`
+pub fn parse(kind: PlaylistKind, text: &str, base_url: &str) -> Vec<String> {
+    let entries: Vec<String> = match kind {
+        PlaylistKind::M3u => text.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        PlaylistKind::Pls => {
+            let mut files: Vec<(u32, String)> = text.lines()
+                .filter_map(|l| l.split_once('='))
+                .filter_map(|(k, v)| {
+                    let number = k.trim().to_lowercase().strip_prefix("file")?.parse().unwrap_or(u32::MAX);
+                    Some((number, v.trim().to_string()))
+                })
+                .collect();
+            files.sort_by_key(|f| f.0);
+            files.into_iter().map(|f| f.1).collect()
+        },
+        // only XML playlists escape urls
+        PlaylistKind::Xspf => captures(r"(?is)<location>\s*(.*?)\s*</location>", text).iter().map(|e| unescape_xml(e)).collect(),
+        PlaylistKind::Asx => captures(r#"(?i)<ref\s+href\s*=\s*["']([^"']+)["']"#, text).iter().map(|e| unescape_xml(e)).collect(),
+    };
+
+    entries.iter()
+        .filter(|e| !e.is_empty())
+        .map(|e| join_url(base_url, e))
+        .collect()
+}
+
+fn captures(pattern: &str, text: &str) -> Vec<String> {
+    let re = Regex::new(pattern).unwrap();
+    re.captures_iter(text).map(|c| c[1].to_string()).collect()
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+}
+
+/// Resolve playlist entry relative to playlist url
+pub fn join_url(base_url: &str, entry: &str) -> String {
+    if entry.contains("://") {
+        return entry.to_string();
+    }
+    let (scheme, rest) = base_url.split_once("://").unwrap_or(("http", base_url));
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if let Some(path) = entry.strip_prefix("//") {
+        format!("{}://{}", scheme, path)
+    } else if entry.starts_with('/') {
+        format!("{}://{}{}", scheme, host, entry)
+    } else {
+        let base = base_url.split(['?', '#']).next().unwrap_or_default();
+        let dir = match base.rsplit_once('/') {
+            Some((dir, _)) if dir.len() > scheme.len() + 2 => dir.to_string(),
+            _ => format!("{}://{}", scheme, host),
+        };
+        format!("{}/{}", dir, entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "http://example.com/radio/list.pls?x=1";
+
+    #[test]
+    fn sniff_recognizes_content_of_all_kinds() {
+        assert_eq!(sniff(b"\xef\xbb\xbf  #EXTM3U\nstream.mp3"), Some(PlaylistKind::M3u));
+        assert_eq!(sniff(b"[Playlist]\nFile1=a"), Some(PlaylistKind::Pls));
+        assert_eq!(sniff(b"<ASX version=\"3.0\">"), Some(PlaylistKind::Asx));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><playlist xmlns=\"http://xspf.org/ns/0/\">"), Some(PlaylistKind::Xspf));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>\n<asx version=\"3.0\">"), Some(PlaylistKind::Asx));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><rss>"), None);
+        assert_eq!(sniff(b"\xff\xfb\x90\x00"), None);
+    }
+
+    #[test]
+    fn detect_prefers_content_then_content_type_then_extension() {
+        // content wins over a wrong content type and extension
+        assert_eq!(detect("http://example.com/a.asx", "audio/x-scpls", b"#EXTM3U\n"), Some(PlaylistKind::M3u));
+        assert_eq!(detect("http://example.com/a.asx", "Audio/X-ScPls ", b"http://example.com/stream\n"), Some(PlaylistKind::Pls));
+        assert_eq!(detect("http://example.com/list.XSPF?token=a.mp3#b", "text/plain", b"http://example.com/stream\n"), Some(PlaylistKind::Xspf));
+        assert_eq!(detect("http://example.com/stream.mp3", "audio/mpeg", b"\xff\xfb\x90\x00"), None);
+    }
+
+    #[test]
+    fn parse_m3u_keeps_urls_unescaped() {
+        let text = "#EXTM3U\n#EXTINF:-1,Test FM\nhttp://example.com/stream?a=1&amp;b=2\n\n  relative.mp3  \n";
+        assert_eq!(parse(PlaylistKind::M3u, text, BASE), vec![
+            "http://example.com/stream?a=1&amp;b=2",
+            "http://example.com/radio/relative.mp3",
+        ]);
+    }
+
+    #[test]
+    fn parse_pls_orders_by_file_number() {
+        let text = "[playlist]\nNumberOfEntries=3\nFile10=http://example.com/ten\nTitle10=Ten\n\
+            File2 = http://example.com/two?a&amp;b\nfile1=/one\nLength1=-1\nVersion=2\n";
+        assert_eq!(parse(PlaylistKind::Pls, text, BASE), vec![
+            "http://example.com/one",
+            "http://example.com/two?a&amp;b",
+            "http://example.com/ten",
+        ]);
+    }
+
+    #[test]
+    fn parse_xspf_and_asx_unescape_xml() {
+        let xspf = "<?xml version=\"1.0\"?><playlist xmlns=\"http://xspf.org/ns/0/\"><trackList>\
+            <track><location>\n  http://example.com/a?x=1&amp;y=2 </location></track>\
+            <track><location>b.ogg</location></track><track><location></location></track></trackList></playlist>";
+        assert_eq!(parse(PlaylistKind::Xspf, xspf, BASE), vec![
+            "http://example.com/a?x=1&y=2",
+            "http://example.com/radio/b.ogg",
+        ]);
+
+        let asx = "<asx version=\"3.0\"><entry><REF HREF=\"mms://example.com/live?a&amp;b\"/></entry>\
+            <entry><ref href='//cdn.example.com/backup'/></entry></asx>";
+        assert_eq!(parse(PlaylistKind::Asx, asx, BASE), vec![
+            "mms://example.com/live?a&b",
+            "http://cdn.example.com/backup",
+        ]);
+    }
+
+    #[test]
+    fn join_url_resolves_against_playlist_url() {
+        assert_eq!(join_url(BASE, "https://other.example.com/s"), "https://other.example.com/s");
+        assert_eq!(join_url("https://example.com/a/list.m3u", "//cdn.example.com/s"), "https://cdn.example.com/s");
+        assert_eq!(join_url(BASE, "/live/s.mp3"), "http://example.com/live/s.mp3");
+        assert_eq!(join_url(BASE, "s.mp3"), "http://example.com/radio/s.mp3");
+        assert_eq!(join_url("http://example.com", "s.mp3"), "http://example.com/s.mp3");
+        assert_eq!(join_url("http://example.com?x=a/b", "s.mp3"), "http://example.com/s.mp3");
+        assert_eq!(join_url("http://example.com:8000", "/s.mp3"), "http://example.com:8000/s.mp3");
+    }
+}
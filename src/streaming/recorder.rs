@@ -0,0 +1,137 @@
+use std::{fs::File, io::Write, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use chrono::Local;
+
+/// Progress of a running recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingStatus {
+    /// file being written
+    pub path: PathBuf,
+    pub duration: Duration,
+    /// bytes written to all files of the recording
+    pub bytes: u64,
+    pub files: usize,
+    /// writing failed, nothing is written after it
+    pub error: Option<String>,
+}
+
+/// Writes stream audio to files named from station name and start time,
+/// optionally starting a new file whenever the stream title changes
+pub struct Recorder {
+    dir: PathBuf,
+    station: String,
+    extension: &'static str,
+    split_on_title: bool,
+    file: File,
+    path: PathBuf,
+    title: Option<String>,
+    started: Instant,
+    bytes: u64,
+    files: usize,
+    error: Option<String>,
+}
+
+impl Recorder {
+    pub fn new(dir: PathBuf, station: &str, content_type: &str, split_on_title: bool, title: Option<String>) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let extension = extension(content_type);
+        let path = file_path(&dir, station, title.as_deref().filter(|_| split_on_title), extension);
+        Ok(Recorder {
+            file: File::create(&path)?,
+            dir,
+            station: station.to_string(),
+            extension,
+            split_on_title,
+            path,
+            title,
+            started: Instant::now(),
+            bytes: 0,
+            files: 1,
+            error: None,
+        })
+    }
+
+    /// Write audio which plays while `title` is the stream title.
+    /// After an error the recording stops and the error is kept for its status
+    pub fn write(&mut self, data: &[u8], title: Option<&str>) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.write_file(data, title) {
+            self.error = Some(e.to_string());
+        }
+    }
+
+    fn write_file(&mut self, data: &[u8], title: Option<&str>) -> std::io::Result<()> {
+        if self.split_on_title && title.is_some() && title != self.title.as_deref() {
+            self.path = file_path(&self.dir, &self.station, title, self.extension);
+            self.file = File::create(&self.path)?;
+            self.files += 1;
+        }
+        self.title = title.map(str::to_string);
+        self.file.write_all(data)?;
+        self.bytes += data.len() as u64;
+        Ok(())
+    }
+
+    pub fn status(&self) -> RecordingStatus {
+        RecordingStatus {
+            path: self.path.clone(),
+            duration: self.started.elapsed(),
+            bytes: self.bytes,
+            files: self.files,
+            error: self.error.clone(),
+        }
+    }
+}
+
+fn extension(content_type: &str) -> &'static str {
+    match content_type.trim().to_lowercase().as_str() {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => "aac",
+        "audio/ogg" | "application/ogg" | "audio/opus" => "ogg",
+        "audio/flac" | "audio/x-flac" => "flac",
+        "audio/mp4" => "m4a",
+        _ => "audio",
+    }
+}
+
+fn file_path(dir: &Path, station: &str, title: Option<&str>, extension: &str) -> PathBuf {
+    let mut name = format!("{}-{}", sanitize(station), Local::now().format("%Y%m%d-%H%M%S"));
+    if let Some(title) = title {
+        name.push('-');
+        name.push_str(&sanitize(title));
+    }
+    dir.join(format!("{}.{}", name, extension))
+}
+
+/// Keep file names portable
+fn sanitize(text: &str) -> String {
+    let name: String = text.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    name.trim_matches('_').chars().take(80).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_error_stops_recording_and_is_reported() {
+        let dir = std::env::temp_dir().join(format!("librecast-recorder-{}", std::process::id()));
+        let mut recorder = Recorder::new(dir.clone(), "Test FM", "audio/mpeg", false, None).unwrap();
+        recorder.write(b"abc", None);
+        assert_eq!(recorder.status().bytes, 3);
+        assert_eq!(recorder.status().error, None);
+
+        // read only file fails to write
+        recorder.file = File::open(&recorder.path).unwrap();
+        recorder.write(b"def", None);
+        let status = recorder.status();
+        assert_eq!(status.bytes, 3);
+        assert!(status.error.is_some());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
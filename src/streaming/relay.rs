@@ -0,0 +1,336 @@
+use std::{error::Error, io::{BufRead, BufReader, Cursor, Read, Write}, net::{SocketAddr, TcpListener, TcpStream}, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError}, Arc, Mutex, RwLock}, thread, time::{Duration, Instant}};
+
+use crate::streaming::{hls::HlsReader, icy::{IcyMetadata, IcyReader}, pcm::{Gain, PcmStream}, playlist, recorder::{Recorder, RecordingStatus}};
+
+/// Chunks buffered for a client which doesn't read (e.g. paused player),
+/// newer chunks are dropped after that
+const CLIENT_BUFFER_CHUNKS: usize = 256;
+const CHUNK_SIZE: usize = 16 * 1024;
+/// Playlists pointing to playlists are followed this deep
+const MAX_PLAYLIST_DEPTH: usize = 3;
+const MAX_PLAYLIST_SIZE: u64 = 256 * 1024;
+/// Upstream reads fail when the server sends nothing this long
+const READ_TIMEOUT: Duration = Duration::from_secs(20);
+/// Audio read before deciding whether it can be decoded
+const PROBE_SIZE: u64 = 8 * 1024;
+
+type SharedClients = Arc<Mutex<Clients>>;
+type SharedRecorder = Arc<Mutex<Option<Recorder>>>;
+
+#[derive(Default)]
+struct Clients {
+    /// response head (and WAV header) every client gets first, known once audio is read
+    head: Option<Arc<[u8]>>,
+    senders: Vec<SyncSender<Arc<[u8]>>>,
+}
+
+impl Clients {
+    fn add(&mut self, sender: SyncSender<Arc<[u8]>>) {
+        if let Some(head) = self.head.as_ref() {
+            let _ = sender.try_send(head.clone());
+        }
+        self.senders.push(sender);
+    }
+
+    fn set_head(&mut self, content_type: &str, body_start: &[u8]) {
+        let mut head = format!("HTTP/1.0 200 OK\r\nContent-Type: {}\r\nConnection: close\r\n\r\n", content_type).into_bytes();
+        head.extend_from_slice(body_start);
+        let head: Arc<[u8]> = Arc::from(head);
+        self.send(head.clone());
+        self.head = Some(head);
+    }
+
+    fn send(&mut self, chunk: Arc<[u8]>) {
+        self.senders.retain(|c| {
+            !matches!(c.try_send(chunk.clone()), Err(TrySendError::Disconnected(_)))
+        });
+    }
+}
+
+/// Upstream audio, which is written to the running recording as it's read
+struct Upstream<R: Read> {
+    inner: R,
+    running: Arc<AtomicBool>,
+    recorder: SharedRecorder,
+    metadata: Arc<RwLock<IcyMetadata>>,
+    last_data: Arc<Mutex<Instant>>,
+}
+
+impl<R: Read> Read for Upstream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if !self.running.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            *self.last_data.lock().unwrap() = Instant::now();
+            // a failed recording stays until stopped, so its error is shown
+            if let Some(r) = self.recorder.lock().unwrap().as_mut() {
+                let title = self.metadata.read().unwrap().stream_title.clone();
+                r.write(&buf[..n], title.as_deref());
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Local HTTP server which connects to a radio stream with ICY metadata
+/// enabled, strips metadata from audio and serves audio to the player.
+///
+/// The player opens the relay url instead of the station url,
+/// every connection to the relay gets live audio from the current position.
+/// Upstream connection is closed when the relay is dropped.
+///
+/// Playlist urls (M3U, PLS, XSPF, ASX) are resolved, and the contained
+/// streams are tried in order until one connects. HLS playlists are read
+/// with `HlsReader` and served as one continuous stream.
+///
+/// Audio which can be decoded is served as WAV with the relay volume applied,
+/// since url2audio can't change volume. Other audio is served unchanged.
+///
+/// Upstream is read also when no client is connected, so the stream
+/// can be recorded without listening.
+pub struct StreamRelay {
+    local_addr: SocketAddr,
+    resolved_url: String,
+    content_type: String,
+    metadata: Arc<RwLock<IcyMetadata>>,
+    running: Arc<AtomicBool>,
+    recorder: SharedRecorder,
+    last_data: Arc<Mutex<Instant>>,
+    gain: Gain,
+}
+
+impl StreamRelay {
+    pub fn start(url: &str) -> Result<Self, Box<dyn Error>> {
+        Self::start_resolving(url, 0)
+    }
+
+    fn start_resolving(url: &str, depth: usize) -> Result<Self, Box<dyn Error>> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout_read(READ_TIMEOUT)
+            .build();
+        let response = agent.get(url)
+            .set("Icy-MetaData", "1")
+            .call()?;
+
+        let metadata = Arc::new(RwLock::new(IcyMetadata::from_response(&response)));
+        let content_type = response.content_type().to_string();
+        let metaint: Option<usize> = response.header("icy-metaint").and_then(|m| m.trim().parse().ok());
+        let mut upstream = BufReader::with_capacity(CHUNK_SIZE, response.into_reader());
+
+        // streams with ICY metadata are never playlists
+        if metaint.is_none() {
+            if let Some(kind) = playlist::detect(url, &content_type, upstream.fill_buf()?) {
+                return Self::start_from_playlist(agent, kind, upstream, url, depth);
+            }
+        }
+
+        let reader = IcyReader::new(upstream, metaint, metadata.clone());
+        Self::serve(reader, content_type, metadata, url)
+    }
+
+    /// Serve audio from `reader` to local clients
+    fn serve(reader: impl Read + Send + Sync + 'static, content_type: String, metadata: Arc<RwLock<IcyMetadata>>, url: &str) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let clients: SharedClients = Default::default();
+        let recorder: SharedRecorder = Default::default();
+        let last_data = Arc::new(Mutex::new(Instant::now()));
+        let gain = Gain::default();
+
+        {
+            let upstream = Upstream {
+                inner: reader,
+                running: running.clone(),
+                recorder: recorder.clone(),
+                metadata: metadata.clone(),
+                last_data: last_data.clone(),
+            };
+            let running = running.clone();
+            let clients = clients.clone();
+            let content_type = content_type.clone();
+            let gain = gain.clone();
+            thread::spawn(move || {
+                Self::read_upstream(upstream, &content_type, gain, &clients);
+                running.store(false, Ordering::Relaxed);
+            });
+        }
+
+        {
+            let running = running.clone();
+            thread::spawn(move || Self::accept_clients(listener, running, clients));
+        }
+
+        Ok(StreamRelay {
+            local_addr,
+            resolved_url: url.to_string(),
+            content_type,
+            metadata,
+            running,
+            recorder,
+            last_data,
+            gain,
+        })
+    }
+
+    fn start_from_playlist(agent: ureq::Agent, kind: playlist::PlaylistKind, upstream: impl Read, url: &str, depth: usize) -> Result<Self, Box<dyn Error>> {
+        if depth >= MAX_PLAYLIST_DEPTH {
+            return Err("playlist nesting is too deep".into());
+        }
+
+        let mut content = vec![];
+        upstream.take(MAX_PLAYLIST_SIZE).read_to_end(&mut content)?;
+        let text = String::from_utf8_lossy(&content);
+        if playlist::is_hls(&text) {
+            let reader = HlsReader::new(agent, url, &text)?;
+            let content_type = reader.content_type().to_string();
+            return Self::serve(reader, content_type, Default::default(), url);
+        }
+
+        let mut last_error: Box<dyn Error> = "playlist contains no streams".into();
+        for entry in playlist::parse(kind, &text, url) {
+            match Self::start_resolving(&entry, depth + 1) {
+                Ok(relay) => return Ok(relay),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Url of the stream actually playing, differs from the opened url for playlists
+    pub fn resolved_url(&self) -> &str {
+        &self.resolved_url
+    }
+
+    pub fn local_url(&self) -> String {
+        format!("http://{}/stream", self.local_addr)
+    }
+
+    pub fn metadata(&self) -> IcyMetadata {
+        self.metadata.read().unwrap().clone()
+    }
+
+    /// Start writing stream audio to `dir`, replacing a running recording
+    pub fn start_recording(&self, dir: PathBuf, station: &str, split_on_title: bool) -> std::io::Result<()> {
+        let title = self.metadata.read().unwrap().stream_title.clone();
+        let recorder = Recorder::new(dir, station, &self.content_type, split_on_title, title)?;
+        *self.recorder.lock().unwrap() = Some(recorder);
+        Ok(())
+    }
+
+    pub fn stop_recording(&self) {
+        *self.recorder.lock().unwrap() = None;
+    }
+
+    pub fn recording_status(&self) -> Option<RecordingStatus> {
+        self.recorder.lock().unwrap().as_ref().map(|r| r.status())
+    }
+
+    /// Move running recording out of the relay, e.g. to continue it on a reconnected stream
+    pub fn take_recorder(&self) -> Option<Recorder> {
+        self.recorder.lock().unwrap().take()
+    }
+
+    pub fn set_recorder(&self, recorder: Option<Recorder>) {
+        *self.recorder.lock().unwrap() = recorder;
+    }
+
+    /// Volume of the served audio, 0.0 - 1.0. Not applied to audio which isn't decoded
+    pub fn set_volume(&self, volume: f32) {
+        self.gain.set(volume);
+    }
+
+    /// False once upstream ended or failed
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Time since upstream sent audio
+    pub fn idle_time(&self) -> Duration {
+        self.last_data.lock().unwrap().elapsed()
+    }
+
+    fn read_upstream<R: Read + Send + Sync + 'static>(mut upstream: Upstream<R>, content_type: &str, gain: Gain, clients: &Mutex<Clients>) {
+        let mut prefix = vec![];
+        if (&mut upstream).take(PROBE_SIZE).read_to_end(&mut prefix).is_err() || prefix.is_empty() {
+            return;
+        }
+
+        if PcmStream::can_decode(&prefix) {
+            let mut pcm = match PcmStream::new(Cursor::new(prefix).chain(upstream), gain) {
+                Ok(p) => p,
+                Err(_) => return,
+            };
+            while let Some(samples) = pcm.next_samples() {
+                let mut clients = clients.lock().unwrap();
+                if clients.head.is_none() {
+                    clients.set_head("audio/wav", &pcm.wav_header().unwrap_or_default());
+                }
+                clients.send(Arc::from(samples));
+            }
+            return;
+        }
+
+        clients.lock().unwrap().set_head(content_type, &prefix);
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let n = match upstream.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            clients.lock().unwrap().send(Arc::from(&buf[..n]));
+        }
+    }
+
+    fn accept_clients(listener: TcpListener, running: Arc<AtomicBool>, clients: SharedClients) {
+        while running.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let (tx, rx) = mpsc::sync_channel(CLIENT_BUFFER_CHUNKS);
+                    clients.lock().unwrap().add(tx);
+                    let running = running.clone();
+                    thread::spawn(move || {
+                        let _ = Self::serve_client(stream, rx, running);
+                    });
+                },
+                Err(_) => thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+
+    /// Client gets the response head as first chunk
+    fn serve_client(mut stream: TcpStream, rx: Receiver<Arc<[u8]>>, running: Arc<AtomicBool>) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+
+        // request is ignored, every client gets the same live stream
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf)?;
+            if n == 0 {
+                return Ok(());
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+
+        while running.load(Ordering::Relaxed) {
+            match rx.recv_timeout(Duration::from_secs(1)) {
+                Ok(chunk) => stream.write_all(&chunk)?,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StreamRelay {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
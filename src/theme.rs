@@ -0,0 +1,375 @@
+use std::{collections::HashMap, path::Path, str::FromStr, sync::OnceLock};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::config;
+
+/// Styles by role, widgets and tabs take their colors from here
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// plain text
+    pub text: Style,
+    /// titles, values, what is playing
+    pub accent: Style,
+    /// secondary details, dates, hints
+    pub muted: Style,
+    /// folders, bookmarks, focused fields, warnings
+    pub highlight: Style,
+    pub error: Style,
+    /// scheduled alarms
+    pub alarm: Style,
+    /// border and text of the focused list, both always have a foreground color
+    pub focused: Style,
+    pub unfocused: Style,
+    /// playing item of a list
+    pub playing: Style,
+    /// help and editor overlays
+    pub popup: Style,
+    /// text input dialogs
+    pub dialog: Style,
+    /// waiting message
+    pub waiting: Style,
+    /// timeline gauge and buffered ranges, always has a foreground color
+    pub gauge: Style,
+    pub bar: Style,
+    pub bar_value: Style,
+}
+
+/// Roles whose foreground is drawn as a color of its own, a style without one keeps the base color
+const COLORED_ROLES: [&str; 3] = ["focused", "unfocused", "gauge"];
+
+const ROLES: [&str; 15] = ["text", "accent", "muted", "highlight", "error", "alarm", "focused", "unfocused", "playing", "popup", "dialog", "waiting", "gauge", "bar", "bar_value"];
+
+pub const BUILTIN: [&str; 3] = ["dark", "light", "high-contrast"];
+
+fn fg(color: Color) -> Style {
+    Style::default().fg(color)
+}
+
+impl Theme {
+    /// Colors librecast always had, for dark terminals
+    pub fn dark() -> Self {
+        Theme {
+            text: Style::default(),
+            accent: fg(Color::Blue),
+            muted: fg(Color::DarkGray),
+            highlight: fg(Color::Yellow),
+            error: fg(Color::Red),
+            alarm: fg(Color::Magenta),
+            focused: fg(Color::Blue),
+            unfocused: fg(Color::DarkGray),
+            playing: fg(Color::White).add_modifier(Modifier::BOLD),
+            popup: Style::default().bg(Color::DarkGray),
+            dialog: fg(Color::White).bg(Color::Black),
+            waiting: fg(Color::White).bg(Color::Blue),
+            gauge: fg(Color::Blue).bg(Color::Black).add_modifier(Modifier::BOLD),
+            bar: fg(Color::Blue),
+            bar_value: fg(Color::White).bg(Color::Blue),
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            text: fg(Color::Black),
+            accent: fg(Color::Blue),
+            muted: fg(Color::Indexed(242)),
+            highlight: fg(Color::Indexed(130)),
+            error: fg(Color::Red),
+            alarm: fg(Color::Magenta),
+            focused: fg(Color::Blue),
+            unfocused: fg(Color::Indexed(245)),
+            playing: fg(Color::Black).add_modifier(Modifier::BOLD),
+            popup: fg(Color::Black).bg(Color::Indexed(253)),
+            dialog: fg(Color::Black).bg(Color::Indexed(253)),
+            waiting: fg(Color::White).bg(Color::Blue),
+            gauge: fg(Color::Blue).bg(Color::Indexed(253)).add_modifier(Modifier::BOLD),
+            bar: fg(Color::Blue),
+            bar_value: fg(Color::White).bg(Color::Blue),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Theme {
+            text: fg(Color::White),
+            accent: bold.fg(Color::LightCyan),
+            muted: fg(Color::White),
+            highlight: bold.fg(Color::LightYellow),
+            error: bold.fg(Color::LightRed),
+            alarm: bold.fg(Color::LightMagenta),
+            focused: bold.fg(Color::LightYellow),
+            unfocused: fg(Color::White),
+            playing: bold.fg(Color::LightGreen),
+            popup: fg(Color::White).bg(Color::Black),
+            dialog: fg(Color::White).bg(Color::Black),
+            waiting: bold.fg(Color::Black).bg(Color::LightYellow),
+            gauge: bold.fg(Color::LightCyan).bg(Color::Black),
+            bar: fg(Color::LightCyan),
+            bar_value: bold.fg(Color::Black).bg(Color::LightCyan),
+        }
+    }
+
+    /// Border and text style of a list depending on its focus
+    pub fn focus(&self, focused: bool) -> Style {
+        if focused { self.focused } else { self.unfocused }
+    }
+
+    /// Background of the selected playing item of a list depending on its focus
+    pub fn focus_color(&self, focused: bool) -> Color {
+        self.focus(focused).fg.unwrap_or_else(|| unreachable!("focus styles always have a color"))
+    }
+
+    /// Buffered ranges of the timeline
+    pub fn gauge_color(&self) -> Color {
+        self.gauge.fg.unwrap_or_else(|| unreachable!("gauge style always has a color"))
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Style> {
+        Some(match role {
+            "text" => &mut self.text,
+            "accent" => &mut self.accent,
+            "muted" => &mut self.muted,
+            "highlight" => &mut self.highlight,
+            "error" => &mut self.error,
+            "alarm" => &mut self.alarm,
+            "focused" => &mut self.focused,
+            "unfocused" => &mut self.unfocused,
+            "playing" => &mut self.playing,
+            "popup" => &mut self.popup,
+            "dialog" => &mut self.dialog,
+            "waiting" => &mut self.waiting,
+            "gauge" => &mut self.gauge,
+            "bar" => &mut self.bar,
+            "bar_value" => &mut self.bar_value,
+            _ => return None,
+        })
+    }
+
+    fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Theme `config.name` (built in, or `themes/<name>.toml` next to the config file)
+    /// with `config.styles` applied
+    pub fn load(config: &ThemeConfig) -> Result<Theme, Vec<String>> {
+        Theme::load_from(config, &config::config_dir().join("themes"))
+    }
+
+    fn load_from(config: &ThemeConfig, themes_dir: &Path) -> Result<Theme, Vec<String>> {
+        let mut theme = match Theme::builtin(&config.name) {
+            Some(t) => t,
+            None => {
+                let path = themes_dir.join(format!("{}.toml", config.name));
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| vec![format!("[theme] name {:?} is neither one of {} nor a readable {}: {}", config.name, BUILTIN.join(", "), path.display(), e)])?;
+                let file: ThemeFile = toml::from_str(&content)
+                    .map_err(|e| vec![format!("invalid theme file {}: {}", path.display(), e)])?;
+                let base = file.base.as_deref().unwrap_or("dark");
+                let mut theme = Theme::builtin(base)
+                    .ok_or(vec![format!("theme file {}: base must be one of {}", path.display(), BUILTIN.join(", "))])?;
+                theme.apply(&file.styles, &format!("theme file {}", path.display()))?;
+                theme
+            },
+        };
+        theme.apply(&config.styles, "[theme.styles]")?;
+        Ok(theme)
+    }
+
+    fn apply(&mut self, styles: &HashMap<String, StyleSpec>, source: &str) -> Result<(), Vec<String>> {
+        let mut problems = vec![];
+        let mut styles: Vec<_> = styles.iter().collect();
+        styles.sort_by_key(|(role, _)| *role);
+        for (role, spec) in styles {
+            let style = match spec.to_style() {
+                Ok(s) => s,
+                Err(e) => {
+                    problems.push(format!("{} {}: {}", source, role, e));
+                    continue;
+                },
+            };
+            match self.role_mut(role) {
+                Some(r) if COLORED_ROLES.contains(&role.as_str()) => *r = Style { fg: style.fg.or(r.fg), ..style },
+                Some(r) => *r = style,
+                None => problems.push(format!("{}: unknown style {:?}, expected one of {}", source, role, ROLES.join(", "))),
+            }
+        }
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+}
+
+/// Style of a role: a foreground color like `"blue"`, `"#3a3a3a"` or `"244"`,
+/// or `{ fg = "...", bg = "...", modifiers = ["bold", "italic"] }`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum StyleSpec {
+    Fg(String),
+    Full {
+        fg: Option<String>,
+        bg: Option<String>,
+        #[serde(default)]
+        modifiers: Vec<String>,
+    },
+}
+
+impl StyleSpec {
+    fn to_style(&self) -> Result<Style, String> {
+        let color = |c: &str| Color::from_str(c).map_err(|_| format!("unknown color {:?}", c));
+        match self {
+            StyleSpec::Fg(c) => Ok(Style::default().fg(color(c)?)),
+            StyleSpec::Full { fg, bg, modifiers } => {
+                let mut style = Style::default();
+                if let Some(c) = fg {
+                    style = style.fg(color(c)?);
+                }
+                if let Some(c) = bg {
+                    style = style.bg(color(c)?);
+                }
+                for m in modifiers {
+                    style = style.add_modifier(match m.to_lowercase().as_str() {
+                        "bold" => Modifier::BOLD,
+                        "dim" => Modifier::DIM,
+                        "italic" => Modifier::ITALIC,
+                        "underlined" => Modifier::UNDERLINED,
+                        "reversed" => Modifier::REVERSED,
+                        "crossed_out" => Modifier::CROSSED_OUT,
+                        _ => return Err(format!("unknown modifier {:?}", m)),
+                    });
+                }
+                Ok(style)
+            },
+        }
+    }
+}
+
+/// `[theme]` table of the config file, e.g.
+///
+/// ```toml
+/// [theme]
+/// name = "light"
+///
+/// [theme.styles]
+/// accent = "#005f87"
+/// popup = { fg = "black", bg = "white" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// built in theme or user theme file
+    pub name: String,
+    pub styles: HashMap<String, StyleSpec>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            name: "dark".to_string(),
+            styles: Default::default(),
+        }
+    }
+}
+
+/// `themes/<name>.toml`, roles not given are taken from `base`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    #[serde(default)]
+    styles: HashMap<String, StyleSpec>,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Theme of the loaded config, it was validated when the config was loaded
+pub fn get() -> &'static Theme {
+    THEME.get_or_init(|| Theme::load(&config::get().theme).unwrap_or_else(|_| Theme::dark()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(toml: &str) -> Result<Style, String> {
+        #[derive(Deserialize)]
+        struct Role {
+            style: StyleSpec,
+        }
+        toml::from_str::<Role>(toml).unwrap().style.to_style()
+    }
+
+    fn theme_config(name: &str, styles: &str) -> ThemeConfig {
+        ThemeConfig {
+            name: name.to_string(),
+            styles: toml::from_str(styles).unwrap(),
+        }
+    }
+
+    #[test]
+    fn builtin_themes_color_gauge_and_focus() {
+        for name in BUILTIN {
+            let theme = Theme::builtin(name).unwrap();
+            for style in [theme.focused, theme.unfocused, theme.gauge] {
+                assert!(style.fg.is_some(), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn style_specs_parse() {
+        assert_eq!(spec("style = \"blue\""), Ok(Style::default().fg(Color::Blue)));
+        assert_eq!(spec("style = \"244\""), Ok(Style::default().fg(Color::Indexed(244))));
+        assert_eq!(spec("style = \"#3a3a3a\""), Ok(Style::default().fg(Color::Rgb(0x3a, 0x3a, 0x3a))));
+        assert_eq!(
+            spec("style = { fg = \"black\", bg = \"white\", modifiers = [\"bold\", \"Italic\"] }"),
+            Ok(Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD | Modifier::ITALIC)),
+        );
+        assert_eq!(spec("style = { bg = \"red\" }"), Ok(Style::default().bg(Color::Red)));
+
+        assert_eq!(spec("style = \"blurple\""), Err("unknown color \"blurple\"".to_string()));
+        assert_eq!(spec("style = { fg = \"red\", modifiers = [\"blink\"] }"), Err("unknown modifier \"blink\"".to_string()));
+    }
+
+    #[test]
+    fn styles_override_the_theme() {
+        let theme = Theme::load_from(&theme_config("light", "accent = \"red\"\ngauge = { bg = \"black\" }"), Path::new("")).unwrap();
+        assert_eq!(theme.accent, Style::default().fg(Color::Red));
+        // the gauge keeps its color
+        assert_eq!(theme.gauge, Style::default().fg(Color::Blue).bg(Color::Black));
+        assert_eq!(theme.muted, Theme::light().muted);
+    }
+
+    #[test]
+    fn unknown_roles_and_colors_are_reported() {
+        let problems = Theme::load_from(&theme_config("dark", "acent = \"red\"\nerror = \"reddish\""), Path::new("")).unwrap_err();
+        assert_eq!(problems, vec![
+            format!("[theme.styles]: unknown style \"acent\", expected one of {}", ROLES.join(", ")),
+            "[theme.styles] error: unknown color \"reddish\"".to_string(),
+        ]);
+
+        let problems = Theme::load_from(&theme_config("nope", ""), Path::new("/nonexistent")).unwrap_err();
+        assert!(problems[0].starts_with("[theme] name \"nope\" is neither one of dark, light, high-contrast nor a readable /nonexistent/nope.toml: "), "{:?}", problems);
+    }
+
+    #[test]
+    fn user_theme_is_loaded_from_themes_dir() {
+        let dir = std::env::temp_dir().join(format!("librecast-themes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("solarized.toml"), "base = \"light\"\n[styles]\naccent = \"#268bd2\"\nhighlight = \"yellow\"\n").unwrap();
+        std::fs::write(dir.join("broken.toml"), "base = \"sepia\"\n").unwrap();
+
+        let theme = Theme::load_from(&theme_config("solarized", "highlight = \"green\""), &dir).unwrap();
+        assert_eq!(theme.accent, Style::default().fg(Color::Rgb(0x26, 0x8b, 0xd2)));
+        // config styles win over the theme file
+        assert_eq!(theme.highlight, Style::default().fg(Color::Green));
+        assert_eq!(theme.text, Theme::light().text);
+
+        let problems = Theme::load_from(&theme_config("broken", ""), &dir).unwrap_err();
+        assert_eq!(problems, vec![format!("theme file {}: base must be one of dark, light, high-contrast", dir.join("broken.toml").display())]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,4 @@
-use chrono::DateTime;
-use sea_orm::prelude::Uuid;
+use chrono::{DateTime, NaiveDate};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ChannelItem {
@@ -10,7 +9,9 @@ pub struct ChannelItem {
     pub source: Option<String>,
     pub enclosure: String,
     pub description: Option<String>,
-    pub guid: Option<Uuid>,
+    pub guid: Option<String>,
+    /// stable identity of the episode, see [`episode_key`]
+    pub episode_key: String,
     pub pub_date: Option<DateTime<chrono::FixedOffset>>,
     pub listening_state: Option<ListeningState>
 }
@@ -21,3 +22,227 @@ pub struct ListeningState {
     pub finished: bool,
 
 }
+
+/// Identity of an episode which survives enclosure changes: feed GUID scoped
+/// to the channel when present (GUIDs like `1` are only unique within a feed),
+/// normalized enclosure url otherwise.
+///
+/// Feeds sharing an episode with the same GUID and enclosure share
+/// its key, see `DataProvider::store_channel`
+pub fn episode_key(channel_id: i32, guid: Option<&str>, enclosure: &str) -> String {
+    match guid.map(str::trim).filter(|g| !g.is_empty()) {
+        Some(g) => format!("guid:{}:{}", channel_id, g),
+        None => format!("url:{}", normalize_enclosure(enclosure)),
+    }
+}
+
+/// Strip scheme, tracking query parameters and fragment, and lowercase host.
+/// Other parameters are kept, they may be what tells episodes apart (`?id=123`)
+pub fn normalize_enclosure(enclosure: &str) -> String {
+    let url = enclosure.trim();
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let url = url.split('#').next().unwrap_or_default();
+    let (url, query) = url.split_once('?').unwrap_or((url, ""));
+    let url = match url.split_once('/') {
+        Some((host, path)) => format!("{}/{}", host.to_lowercase(), path),
+        None => url.to_lowercase(),
+    };
+    let query: Vec<&str> = query.split('&').filter(|p| !p.is_empty() && !is_tracking_param(p)).collect();
+    if query.is_empty() {
+        url
+    } else {
+        format!("{}?{}", url, query.join("&"))
+    }
+}
+
+/// Parameters added by analytics and ad networks, they differ between requests for the same file
+fn is_tracking_param(param: &str) -> bool {
+    let name = param.split('=').next().unwrap_or_default().to_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+const TRACKING_PARAMS: [&str; 8] = ["fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "_ga", "awcollectionid"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListeningSession {
+    pub id: i32,
+    pub channel_title: Option<String>,
+    pub item: Option<ChannelItem>,
+    pub enclosure: String,
+    pub episode_key: String,
+    pub started_at: DateTime<chrono::FixedOffset>,
+    pub ended_at: Option<DateTime<chrono::FixedOffset>>,
+    pub start_position: f32,
+    pub end_position: Option<f32>,
+}
+
+/// Format `seconds` as `h:mm:ss.s`
+pub fn time_to_display(seconds: f32) -> String {
+    let is: i64 = seconds.round() as i64;
+    let hours = is / (60 * 60);
+    let mins = (is % (60 * 60)) / 60;
+    let secs = seconds - 60.0 * mins as f32 - 60.0 * 60.0 * hours as f32;
+    format!("{}:{:0>2}:{:0>4.1}", hours, mins, secs)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyListening {
+    pub day: NaiveDate,
+    pub channel_id: i32,
+    pub channel_title: Option<String>,
+    pub enclosure: String,
+    pub episode_key: String,
+    pub item_title: Option<String>,
+    pub seconds: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ListeningStats {
+    pub total_seconds: f32,
+    /// (channel id, channel title, seconds), most listened first
+    pub per_channel: Vec<(i32, String, f32)>,
+    /// (week label, seconds), oldest first
+    pub per_week: Vec<(String, f32)>,
+    pub episodes_started: usize,
+    pub episodes_finished: usize,
+    /// finished / started episodes
+    pub completion_rate: f32,
+    /// longest run of consecutive days with listening
+    pub longest_streak: u32,
+    pub daily: Vec<DailyListening>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub id: i32,
+    pub channel_id: i32,
+    pub channel_title: Option<String>,
+    pub item: Option<ChannelItem>,
+    pub enclosure: String,
+    pub episode_key: String,
+    pub time: f32,
+    pub note: Option<String>,
+    pub created_at: DateTime<chrono::FixedOffset>,
+}
+
+/// Station found in the radio directory
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DirectoryStation {
+    pub name: String,
+    pub url: String,
+    pub homepage: Option<String>,
+    /// comma separated
+    pub tags: Option<String>,
+    pub country: Option<String>,
+    pub language: Option<String>,
+    pub codec: Option<String>,
+    pub bitrate: Option<i32>,
+    pub votes: i64,
+}
+
+/// Radio directory search, empty fields are not filtered on
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DirectorySearch {
+    pub name: String,
+    pub tag: String,
+    pub country: String,
+    pub language: String,
+}
+
+impl DirectorySearch {
+    /// Parse query like `jazz tag:smooth country:"united kingdom" language:english`,
+    /// words without a prefix are the station name. Double quotes keep spaces in a word
+    pub fn parse(query: &str) -> Self {
+        let mut search = DirectorySearch::default();
+        let mut name = vec![];
+        for word in query_words(query) {
+            match word.split_once(':') {
+                Some(("tag", v)) => search.tag = v.to_string(),
+                Some(("country", v)) => search.country = v.to_string(),
+                Some(("language", v)) => search.language = v.to_string(),
+                _ => name.push(word),
+            }
+        }
+        search.name = name.join(" ");
+        search
+    }
+}
+
+/// Split query on whitespace outside of double quotes, the quotes are removed
+fn query_words(query: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            },
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn episode_key_prefers_guid_of_the_channel() {
+        assert_eq!(episode_key(3, Some(" abc-1 "), "https://cdn.example.com/a.mp3"), "guid:3:abc-1");
+        assert_ne!(episode_key(3, Some("1"), "https://a.example.com/1.mp3"), episode_key(4, Some("1"), "https://b.example.com/1.mp3"));
+        assert_eq!(episode_key(3, Some(""), "https://cdn.example.com/a.mp3"), "url:cdn.example.com/a.mp3");
+        assert_eq!(episode_key(3, None, "https://cdn.example.com/a.mp3"), "url:cdn.example.com/a.mp3");
+    }
+
+    #[test]
+    fn normalize_enclosure_ignores_scheme_host_case_and_fragment() {
+        assert_eq!(normalize_enclosure(" http://CDN.Example.com/Show/Ep1.mp3#t=10 "), "cdn.example.com/Show/Ep1.mp3");
+        assert_eq!(normalize_enclosure("https://cdn.example.com/Show/Ep1.mp3"), "cdn.example.com/Show/Ep1.mp3");
+        assert_eq!(normalize_enclosure("cdn.example.com"), "cdn.example.com");
+    }
+
+    #[test]
+    fn normalize_enclosure_strips_only_tracking_params() {
+        assert_eq!(normalize_enclosure("https://cdn.example.com/ep.mp3?utm_source=rss&utm_medium=feed"), "cdn.example.com/ep.mp3");
+        assert_eq!(normalize_enclosure("https://cdn.example.com/ep.mp3?UTM_Campaign=x&fbclid=y&gclid=z"), "cdn.example.com/ep.mp3");
+        assert_eq!(normalize_enclosure("https://cdn.example.com/ep.mp3?"), "cdn.example.com/ep.mp3");
+        assert_eq!(normalize_enclosure("https://cdn.example.com/get?id=123&utm_source=rss"), "cdn.example.com/get?id=123");
+        assert_eq!(normalize_enclosure("https://cdn.example.com/get?utm_source=rss&id=123&part=2"), "cdn.example.com/get?id=123&part=2");
+    }
+
+    #[test]
+    fn enclosures_differing_by_query_are_different_episodes() {
+        assert_ne!(episode_key(1, None, "https://cdn.example.com/get?id=123"), episode_key(1, None, "https://cdn.example.com/get?id=124"));
+        assert_eq!(episode_key(1, None, "https://cdn.example.com/get?id=123&utm_source=a"), episode_key(2, None, "http://cdn.example.com/get?id=123&utm_source=b"));
+    }
+
+    #[test]
+    fn directory_search_parses_prefixed_words() {
+        let search = DirectorySearch::parse("  smooth  jazz tag:lounge country:germany language:german ");
+        assert_eq!(search, DirectorySearch {
+            name: "smooth jazz".to_string(),
+            tag: "lounge".to_string(),
+            country: "germany".to_string(),
+            language: "german".to_string(),
+        });
+        assert_eq!(DirectorySearch::parse(""), DirectorySearch::default());
+    }
+
+    #[test]
+    fn directory_search_keeps_quoted_spaces() {
+        let search = DirectorySearch::parse("country:\"United Kingdom\" \"radio 3\" tag:\"classic rock\"");
+        assert_eq!(search.country, "United Kingdom");
+        assert_eq!(search.tag, "classic rock");
+        assert_eq!(search.name, "radio 3");
+        // unterminated quote runs to the end
+        assert_eq!(DirectorySearch::parse("language:\"swiss german").language, "swiss german");
+    }
+}
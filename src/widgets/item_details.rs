@@ -1,22 +1,25 @@
-use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Style, Stylize}, text::{Line, Span, Text}, widgets::{Block, Borders, Paragraph, Widget, Wrap}};
+use ratatui::{buffer::Buffer, layout::Rect, style::{Style, Stylize}, text::{Line, Span, Text}, widgets::{Block, Borders, Paragraph, Widget, Wrap}};
 
-use crate::ui_models::ChannelItem;
+use crate::{theme, ui_models::{Bookmark, ChannelItem}};
 
 
 pub struct ItemDetails<'a> {
     pub currently_playing: bool,
     pub item: &'a Option<&'a ChannelItem>,
-    pub fg_color: Color
+    pub bookmarks: Vec<&'a Bookmark>,
+    pub focused: bool,
+    /// lines the description is scrolled by
+    pub scroll: u16,
 }
 
 impl<'a> Widget for ItemDetails<'a> {
 
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let block = Block::default().borders(Borders::all()).fg(self.fg_color);
+        let theme = theme::get();
+        let block = Block::default().borders(Borders::all()).style(theme.focus(self.focused));
         block.render(area, buf);
 
-        let height = 8;
-        let style = Style::default().fg(self.fg_color);
+        let height = if self.bookmarks.is_empty() { 8 } else { 9 + self.bookmarks.len() as u16 };
         let no_data = "-".to_string();
         let mut lines = vec![];
         let empty = Line::default();
@@ -24,14 +27,14 @@ impl<'a> Widget for ItemDetails<'a> {
             Some(item) => {
                 if self.currently_playing {
                     let playing = Line::from(vec![
-                        Span::styled("▶", style.blue()),
+                        Span::styled("▶", theme.accent),
                     ]);
                     lines.push(playing);
                 } else {
                     match item.listening_state.as_ref() {
                         Some(s) => {
                             let playing = Line::from(vec![
-                                Span::styled(format!("[{}]", time_to_display(s.time)), style.blue()),
+                                Span::styled(format!("[{}]", time_to_display(s.time)), theme.accent),
                             ]);
                             lines.push(playing);
                         },
@@ -41,20 +44,31 @@ impl<'a> Widget for ItemDetails<'a> {
 
                 let t = item.title.as_ref().unwrap_or(&no_data);
                 let title = Line::from(vec![
-                    Span::styled("title: ", style.italic().dark_gray()),
-                    Span::styled(t, style.dark_gray()),
+                    Span::styled("title: ", theme.muted.italic()),
+                    Span::styled(t, theme.muted),
                 ]);
                 lines.push(title);
                 lines.push(empty.clone());
 
                 let pd = item.pub_date.as_ref().unwrap_or(&Default::default()).format("%v");
                 let pub_date = Line::from(vec![
-                    Span::styled("date: ", style.italic().dark_gray()),
-                    Span::styled(pd.to_string(), style.dark_gray()),
+                    Span::styled("date: ", theme.muted.italic()),
+                    Span::styled(pd.to_string(), theme.muted),
                 ]);
                 lines.push(pub_date);
                 lines.push(empty.clone());
 
+                if !self.bookmarks.is_empty() {
+                    lines.push(Line::from(Span::styled("bookmarks:", theme.muted.italic())));
+                    self.bookmarks.iter().for_each(|b| {
+                        lines.push(Line::from(vec![
+                            Span::styled(format!("▼ [{}] ", time_to_display(b.time)), theme.highlight),
+                            Span::styled(b.note.clone().unwrap_or_default(), theme.muted),
+                        ]));
+                    });
+                    lines.push(empty.clone());
+                }
+
                 let p = Paragraph::new(lines).wrap(Wrap { trim: true });
                 p.render(Rect {
                     x: area.x + 1,
@@ -67,7 +81,7 @@ impl<'a> Widget for ItemDetails<'a> {
                 let d = d.to_string();
 
                 let t = Text::from(d); //.to_line();
-                let p = Paragraph::new(t).wrap(Wrap { trim: true });
+                let p = Paragraph::new(t).wrap(Wrap { trim: true }).scroll((self.scroll, 0));
 
                 p.render(Rect {
                     x: area.x + 1,
@@ -4,3 +4,4 @@ pub mod waiting_message_dialog;
 pub mod open_dialog;
 pub mod item_details;
 
+pub mod now_playing;
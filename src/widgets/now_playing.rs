@@ -0,0 +1,42 @@
+use ratatui::{prelude::*, widgets::*};
+use url2audio::player_engine::Playing;
+
+use crate::{playback::PlaybackSource, theme};
+
+/// One line summary of the shared player, shown on every tab
+pub struct NowPlaying {
+    pub source: Option<PlaybackSource>,
+    pub playing: Playing,
+    /// stream title for radio, position for podcasts
+    pub detail: Option<String>,
+}
+
+impl Widget for NowPlaying {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where Self: Sized {
+        let theme = theme::get();
+        let play_char = match self.playing {
+            Playing::Playing => "▶",
+            Playing::Paused => "Ⅱ",
+            Playing::Finished => "⏹",
+        };
+
+        let (kind, title) = match self.source {
+            Some(PlaybackSource::Radio { name, .. }) => ("Radio", name),
+            Some(PlaybackSource::Podcast(item)) => ("Podcast", item.title.unwrap_or("-".to_string())),
+            None => {
+                Paragraph::new(Span::styled(" ■ nothing playing", theme.muted)).render(area, buf);
+                return;
+            },
+        };
+
+        let mut spans = vec![
+            Span::styled(format!(" {} {}: ", play_char, kind), theme.muted),
+            Span::styled(title, theme.accent),
+        ];
+        if let Some(detail) = self.detail {
+            spans.push(Span::styled(format!("  {}", detail), theme.muted));
+        }
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+}
@@ -1,22 +1,26 @@
 use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{prelude::*, widgets::*};
-use ratatui::style::Color;
 use tui_textbox::{Textbox, TextboxState};
 
+use crate::theme;
+
 pub struct OpenDialog {
-    pub fg_color: Color,
-    pub bg_color: Color,
     pub title: String,
+    pub enter_label: String,
 }
 
 impl OpenDialog {
     pub fn new(title: String) -> Self {
         OpenDialog {
             title,
-            fg_color: Color::White,
-            bg_color: Color::Black,
+            enter_label: "add stream".to_string(),
         }
     }
+
+    pub fn enter_label(mut self, enter_label: &str) -> Self {
+        self.enter_label = enter_label.to_string();
+        self
+    }
 }
 
 pub struct OpenDialogState {
@@ -42,6 +46,10 @@ impl OpenDialogState {
         self.textbox_state.text.clone()
     }
 
+    pub fn set_hint(&mut self, hint: &str) {
+        self.textbox_state.hint_text = Some(hint.to_string());
+    }
+
     pub fn clear(&mut self) {
         self.textbox_state.text = String::new();
         self.textbox_state.cursor_pos = 0;
@@ -65,7 +73,7 @@ impl StatefulWidget for OpenDialog {
         let clear = Clear::default();
         clear.render(dialog_area, buf);
 
-        let block = Block::default().borders(Borders::all()).bg(self.bg_color).fg(self.fg_color).title(self.title);
+        let block = Block::default().borders(Borders::all()).style(theme::get().dialog).title(self.title);
         block.render(dialog_area, buf);
 
         let text_box_rect = Rect::new(x + 1, y + 1, width - 2, 1);
@@ -74,7 +82,7 @@ impl StatefulWidget for OpenDialog {
 
         let mut lines = vec![];
 
-        let line = Line::from(vec![Span::styled("<Enter> - add stream", Style::default())]);
+        let line = Line::from(vec![Span::styled(format!("<Enter> - {}", self.enter_label), Style::default())]);
         lines.push(line);
 
         let line = Line::from(vec![Span::styled("<Esc> - cancel", Style::default())]);
@@ -1,11 +1,11 @@
-use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Style, Stylize}, text::{Line, Span}, widgets::{Block, Borders, ListState, StatefulWidget, Widget}};
+use ratatui::{buffer::Buffer, layout::Rect, style::Stylize, text::{Line, Span}, widgets::{Block, Borders, ListState, StatefulWidget, Widget}};
 
-use crate::ui_models;
+use crate::{theme, ui_models};
 
 pub struct SimpleList<'a> {
     pub items: &'a Vec<ui_models::ChannelItem>,
     pub active: &'a Option<ui_models::ChannelItem>,
-    pub fg_color: Color
+    pub focused: bool
 }
 
 enum ItemState {
@@ -18,7 +18,9 @@ impl<'a> StatefulWidget for SimpleList<'a> {
     type State = ListState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let block = Block::default().borders(Borders::all()).fg(self.fg_color);
+        let theme = theme::get();
+        let focus = theme.focus(self.focused);
+        let block = Block::default().borders(Borders::all()).style(focus);
         block.render(area, buf);
 
         if self.items.is_empty() {
@@ -40,14 +42,14 @@ impl<'a> StatefulWidget for SimpleList<'a> {
         }
 
         let start = state.offset();
-        let end = if self.items.len() - 1 < start + num_of_visible { self.items.len() - 1 } else { start + num_of_visible };
+        let end = std::cmp::min(self.items.len(), start + num_of_visible);
 
         let mut dx = 1;
         for i in start..end {
             let item = &self.items[i];
 
             let playing = match self.active.as_ref() {
-                Some(a) => item.channel_id == a.channel_id && item.enclosure == a.enclosure,
+                Some(a) => item.channel_id == a.channel_id && item.episode_key == a.episode_key,
                 None => false
             };
 
@@ -67,10 +69,10 @@ impl<'a> StatefulWidget for SimpleList<'a> {
             dx = dx + 1;
 
             let style = match (playing, i == sel_index) {
-                (true, true) => Style::default().bg(self.fg_color).white().bold(),
-                (true, false) => Style::default().white().bold(),
-                (false, true) => Style::default().fg(self.fg_color).reversed(),
-                (false, false) => Style::default().fg(self.fg_color)
+                (true, true) => theme.playing.bg(theme.focus_color(self.focused)),
+                (true, false) => theme.playing,
+                (false, true) => focus.reversed(),
+                (false, false) => focus
             };
 
             let item_state = match self.items[i].listening_state.as_ref() {
@@ -82,7 +84,7 @@ impl<'a> StatefulWidget for SimpleList<'a> {
                 ItemState::Finished => {
                     let text = format!("✓ {}", &item.title.clone().unwrap_or("".to_string()));
                     Line::from(vec![
-                        Span::styled(text, style.italic().dark_gray())
+                        Span::styled(text, style.patch(theme.muted).italic())
                     ])
                 },
                 ItemState::InProgress(t) => {
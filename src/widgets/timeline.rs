@@ -3,6 +3,8 @@ use ratatui::widgets::Widget;
 use ratatui::widgets::canvas::Canvas;
 use url2audio::player_engine::Playing;
 
+use crate::theme;
+
 pub struct Timeline<'a> {
     pub progress: f64,
     pub progress_display: String,
@@ -11,12 +13,15 @@ pub struct Timeline<'a> {
     pub playing: Playing,
     pub error: Option<String>,
     pub title: String,
-    pub buffer: &'a Vec<(f32, f32)>
+    pub buffer: &'a Vec<(f32, f32)>,
+    /// bookmark positions in seconds
+    pub bookmarks: &'a [f64],
 }
 
 impl<'a> Widget for Timeline<'a> {
     fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::buffer::Buffer)
     where Self: Sized {
+        let theme = theme::get();
 
         // buff
         let canvas = Canvas::default()
@@ -29,7 +34,7 @@ impl<'a> Widget for Timeline<'a> {
                         y: 0.5,
                         width: (c.1 - c.0) as f64,
                         height: 0.5,
-                        color: Color::Blue
+                        color: theme.gauge_color()
                     });
 
                 });
@@ -60,7 +65,7 @@ impl<'a> Widget for Timeline<'a> {
         };
 
         let playing_line = Line::from(vec![
-            Span::styled(format!("{} {}", play_char, self.title), Style::default().fg(ratatui::style::Color::Blue)),
+            Span::styled(format!("{} {}", play_char, self.title), theme.accent),
         ]);
 
         // Line::from(vec![
@@ -68,7 +73,7 @@ impl<'a> Widget for Timeline<'a> {
         // ])
         let error_line = if self.error.is_some() {
             Line::from(vec![
-                Span::styled(format!("Err: {}", self.error.clone().unwrap()), Style::default().fg(ratatui::style::Color::Red)),
+                Span::styled(format!("Err: {}", self.error.clone().unwrap()), theme.error),
             ])
         } else {
             Line::default()
@@ -77,16 +82,21 @@ impl<'a> Widget for Timeline<'a> {
         let gauge = Gauge::default()
             .block(Block::bordered().title(playing_line).title_bottom(error_line)) //"Progress"))
             .label(format!("{} - {}", self.progress_display, self.total_display))
-            .gauge_style(
-                Style::default()
-                .fg(Color::Blue)
-                .bg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-            )
+            .gauge_style(theme.gauge)
             .use_unicode(true)
             .ratio(ratio);
         gauge.render(area, buf);
 
+        // bookmark markers on the top border
+        if self.total > 0.0 && area.width > 2 {
+            let width = (area.width - 2) as f64;
+            self.bookmarks.iter().for_each(|b| {
+                let r = (b / self.total).clamp(0.0, 1.0);
+                let x = area.x + 1 + f64::min(r * width, width - 1.0) as u16;
+                buf.set_string(x, area.y, "▼", theme.highlight);
+            });
+        }
+
 
     }
 }
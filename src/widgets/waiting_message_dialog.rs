@@ -1,19 +1,16 @@
 use ratatui::{prelude::*, widgets::*};
-use ratatui::style::Color;
 use tokio::time::Instant;
 
+use crate::theme;
+
 pub struct WaitingMessageDialog {
     pub message: String,
-    pub fg_color: Color,
-    pub bg_color: Color
 }
 
 impl WaitingMessageDialog {
     pub fn new(message: String) -> Self {
         WaitingMessageDialog {
             message,
-            fg_color: Color::White,
-            bg_color: Color::Blue
         }
     }
 }
@@ -48,8 +45,9 @@ impl StatefulWidget for WaitingMessageDialog {
         let rem = d.as_secs() % 4;
 
         let title = PROGRESS.chars().nth(rem as usize).unwrap();
-        let block = Block::default().borders(Borders::all()).bg(self.bg_color).fg(self.fg_color).title(title.to_string());
+        let style = theme::get().waiting;
+        let block = Block::default().borders(Borders::all()).style(style).title(title.to_string());
         block.render(dialog_area, buf);
-        buf.set_string(dialog_area.x + 1, dialog_area.y + 1, self.message, Style::default().bg(self.bg_color).fg(self.fg_color));
+        buf.set_string(dialog_area.x + 1, dialog_area.y + 1, self.message, style);
     }
 }